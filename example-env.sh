@@ -9,19 +9,66 @@ echo "Setting up isolate-sandbox environment with custom resource limits..."
 export ISOLATE_SANDBOX_PORT=3000
 export ISOLATE_SANDBOX_CONFIG_DIR="./config"
 export ISOLATE_SANDBOX_BOX_POOL_SIZE=10
+export ISOLATE_SANDBOX_BOX_QUEUE_LENGTH=100      # Requests that may wait for a free box
+export ISOLATE_SANDBOX_BOX_MAX_WAIT=30           # Seconds to wait for a free box before failing
 export ISOLATE_SANDBOX_API_KEY="your-api-key-here"
+export ISOLATE_SANDBOX_API_KEYS="ide:ide-api-key,rejudge:rejudge-api-key"  # Per-tenant API keys (tenant:key)
+export ISOLATE_SANDBOX_TENANT_WEIGHTS="ide=4,rejudge=1"                   # Relative share of boxes (default 1)
+export ISOLATE_SANDBOX_JOB_RETENTION=3600        # Keep completed jobs for 1 hour
+export ISOLATE_SANDBOX_SESSION_IDLE_TIMEOUT=600  # Release session boxes unused for 10 minutes
+
+# Completion webhooks (callback_url is rejected unless a secret is set)
+export ISOLATE_SANDBOX_WEBHOOK_SECRET="your-webhook-secret-here"
+export ISOLATE_SANDBOX_WEBHOOK_MAX_ATTEMPTS=5    # Give up after 5 attempts
+export ISOLATE_SANDBOX_WEBHOOK_RETRY_DELAY=1000  # 1 second before the first retry, doubled each time
+export ISOLATE_SANDBOX_WEBHOOK_TIMEOUT=10        # 10 seconds per request
+export ISOLATE_SANDBOX_WEBHOOK_RETENTION=3600    # Keep delivery records for 1 hour
+
+# Compilation, run in its own isolate boxes
+export ISOLATE_SANDBOX_COMPILE_BOX_COUNT=4       # Boxes numbered after the execution boxes
+export ISOLATE_SANDBOX_COMPILE_CG_MEM=524288     # 512MB
+export ISOLATE_SANDBOX_COMPILE_TIME=10           # 10 seconds
+export ISOLATE_SANDBOX_COMPILE_WALL_TIME=20      # 20 seconds
+export ISOLATE_SANDBOX_COMPILE_PROCESSES=64      # 64 processes
+export ISOLATE_SANDBOX_COMPILE_FSIZE=102400      # 100MB
+export ISOLATE_SANDBOX_COMPILE_MAX_OUTPUT_SIZE=65536 # 64KB of compiler messages
+
+# Compiled artifact cache (cleared on startup)
+export ISOLATE_SANDBOX_COMPILE_CACHE_DIR="/tmp/isolate-sandbox-compile-cache"
+export ISOLATE_SANDBOX_COMPILE_CACHE_SIZE=536870912 # 512MB (0 = disabled)
+export ISOLATE_SANDBOX_COMPILE_CACHE_MAX_AGE=86400 # Reuse artifacts for 1 day
 
 # Sandbox resource limits
 export ISOLATE_SANDBOX_DEFAULT_CG_MEM=262144      # 256MB
-export ISOLATE_SANDBOX_DEFAULT_MEM=256000        # 256MB
+export ISOLATE_SANDBOX_DEFAULT_MEM=256000        # 250MB
 export ISOLATE_SANDBOX_DEFAULT_TIME=15           # 15 seconds
 export ISOLATE_SANDBOX_DEFAULT_WALL_TIME=30      # 30 seconds
 export ISOLATE_SANDBOX_DEFAULT_EXTRA_TIME=5      # 5 seconds
-export ISOLATE_SANDBOX_DEFAULT_STACK=64000       # 64KB
+export ISOLATE_SANDBOX_DEFAULT_STACK=64000       # 62.5MB
 export ISOLATE_SANDBOX_DEFAULT_FSIZE=51200       # 50MB
 export ISOLATE_SANDBOX_DEFAULT_OPEN_FILES=32     # 32 files
 export ISOLATE_SANDBOX_DEFAULT_PROCESSES=0       # Unlimited processes (0 = --processes without value)
 
+# Maximum resource limits a request may override to (defaults to the values above)
+export ISOLATE_SANDBOX_MAX_CG_MEM=524288         # 512MB
+export ISOLATE_SANDBOX_MAX_MEM=512000            # 500MB
+export ISOLATE_SANDBOX_MAX_TIME=30               # 30 seconds
+export ISOLATE_SANDBOX_MAX_WALL_TIME=60          # 60 seconds
+export ISOLATE_SANDBOX_MAX_EXTRA_TIME=10         # 10 seconds
+export ISOLATE_SANDBOX_MAX_STACK=128000          # 125MB
+export ISOLATE_SANDBOX_MAX_FSIZE=102400          # 100MB
+export ISOLATE_SANDBOX_MAX_OPEN_FILES=64         # 64 files
+export ISOLATE_SANDBOX_MAX_PROCESSES=0           # No ceiling
+
+# Bytes of stdout and stderr returned per stream (0 = unlimited)
+export ISOLATE_SANDBOX_MAX_OUTPUT_SIZE=1048576   # 1MB
+
+# Bytes accepted per file uploaded into a box, also capped by the default file size limit
+export ISOLATE_SANDBOX_MAX_UPLOAD_SIZE=104857600 # 100MB
+
+# Bytes of box contents downloaded as one archive, before and after packing
+export ISOLATE_SANDBOX_MAX_ARCHIVE_SIZE=268435456 # 256MB
+
 echo "Environment variables set:"
 echo "- Memory limit: 256MB"
 echo "- Time limit: 15 seconds"
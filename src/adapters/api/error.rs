@@ -1,6 +1,6 @@
 use crate::adapters::api::models::ErrorResponse;
 use crate::domain::error::DomainError;
-use axum::http::StatusCode;
+use axum::http::{header, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 
@@ -16,15 +16,28 @@ impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let (status, message) = match self.0 {
             DomainError::LanguageNotFound(ref msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            DomainError::JobNotFound(ref msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            DomainError::DeliveryNotFound(ref msg) => (StatusCode::NOT_FOUND, msg.clone()),
             DomainError::CompilationFailed(ref msg) => (StatusCode::BAD_REQUEST, msg.clone()),
-            DomainError::ExecutionFailed(ref msg) => (StatusCode::BAD_REQUEST, msg.clone()),
-            DomainError::BoxPoolExhausted => (
+            DomainError::InvalidRequest(ref msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            DomainError::ExecutionNotFound(ref msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            DomainError::SessionNotFound(box_id) => {
+                (StatusCode::NOT_FOUND, format!("No session in box {}", box_id))
+            }
+            DomainError::TooManySessions(_) => (StatusCode::TOO_MANY_REQUESTS, self.0.to_string()),
+            DomainError::BoxNotFound(box_id) => {
+                (StatusCode::NOT_FOUND, format!("Box {} is not initialized", box_id))
+            }
+            DomainError::FileNotFound(ref msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            DomainError::PayloadTooLarge(ref msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg.clone()),
+            DomainError::Cancelled => (
+                StatusCode::CONFLICT,
+                "Execution was cancelled".to_string(),
+            ),
+            DomainError::BoxPoolExhausted { .. } => (
                 StatusCode::SERVICE_UNAVAILABLE,
                 "Service is busy, please try again later".to_string(),
             ),
-            DomainError::InvalidConfiguration(ref msg) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, msg.clone())
-            }
             DomainError::SandboxError(ref msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
             DomainError::IoError(ref e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             DomainError::Internal(ref msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
@@ -32,7 +45,13 @@ impl IntoResponse for ApiError {
 
         let body = Json(ErrorResponse { error: message });
 
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        if let DomainError::BoxPoolExhausted { retry_after } = self.0 {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
     }
 }
 
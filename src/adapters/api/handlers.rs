@@ -1,25 +1,96 @@
 use crate::adapters::api::error::ApiError;
+use crate::adapters::api::middleware::Tenant;
 use crate::adapters::api::models::{
-    BoxFileResponse, BoxFilesResponse, CleanupResponse, ExecuteRequest, ExecuteResponse,
-    HealthResponse, LanguagesResponse, MetadataResponse,
+    BatchExecuteRequest, BatchExecuteResponse, BoxArchiveQuery, BoxFileResponse,
+    BoxFileUploadResponse, BoxFilesQuery, BoxFilesResponse, CancelExecutionResponse,
+    CleanupResponse, CompileCacheStatsResponse, CompileResponse, ContentEncoding,
+    DeleteBoxFileQuery, DeleteBoxFileResponse, ErrorResponse, ExecuteRequest, ExecuteResponse,
+    HealthResponse, JobResponse, LanguagesResponse, OutputChunkEvent, ProgramRequest,
+    SessionResponse, SourceFileRequest, StreamResultEvent, StreamStartedEvent,
+    WebhookDeliveriesQuery, WebhookDeliveriesResponse, WebhookDeliveryResponse,
 };
-use crate::domain::entities::ExecutionRequest as DomainExecutionRequest;
+use crate::domain::entities::{
+    ArchiveFormat, ExecutionRequest as DomainExecutionRequest, OutputChunk, OutputStream,
+    Priority, ProgramSource, SchedulingContext, SourceFile, TestCase, TestCaseBatch,
+};
+use crate::domain::error::DomainError;
 use crate::use_cases::{
-    CleanupBoxUseCase, ExecuteCodeUseCase, GetBoxFileUseCase, HealthCheckUseCase,
-    ListBoxFilesUseCase, ListLanguagesUseCase,
+    BoxArchive, CancelExecutionUseCase, CancelJobUseCase, CancellationRegistry, CleanupBoxUseCase,
+    CompileCodeUseCase, CreateSessionUseCase, DeleteBoxFileUseCase, DownloadBoxArchiveUseCase,
+    DownloadBoxFileUseCase, ExecuteCodeUseCase, ExecuteInSessionUseCase, GetBoxFileUseCase,
+    GetCompileCacheStatsUseCase, GetJobUseCase, GetWebhookDeliveryUseCase, HealthCheckUseCase,
+    ListBoxFilesUseCase, ListLanguagesUseCase, ListWebhookDeliveriesUseCase, RunTestCasesUseCase,
+    SessionRegistry, SubmitJobUseCase, UploadBoxFileUseCase, WebhookNotifier,
 };
-use axum::extract::{Path, State};
+use axum::body::Body;
+use axum::extract::{Extension, FromRequest, Multipart, Path, Query, Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use base64::Engine;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::{Stream, StreamExt};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+/// Response header carrying the webhook delivery ID of an execution
+const DELIVERY_ID_HEADER: &str = "x-isolate-delivery";
+
+/// Response header carrying the ID to cancel a synchronous execution with
+const EXECUTION_ID_HEADER: &str = "x-isolate-execution";
+
+/// Number of output chunks buffered between the sandbox and a streaming client
+const STREAM_BUFFER_SIZE: usize = 64;
 
 pub struct AppState {
     pub execute_code_use_case: Arc<ExecuteCodeUseCase>,
+    pub run_test_cases_use_case: Arc<RunTestCasesUseCase>,
+    pub cancel_execution_use_case: Arc<CancelExecutionUseCase>,
+    pub executions: Arc<CancellationRegistry>,
+    pub submit_job_use_case: Arc<SubmitJobUseCase>,
+    pub get_job_use_case: Arc<GetJobUseCase>,
+    pub cancel_job_use_case: Arc<CancelJobUseCase>,
+    pub list_webhook_deliveries_use_case: Arc<ListWebhookDeliveriesUseCase>,
+    pub get_webhook_delivery_use_case: Arc<GetWebhookDeliveryUseCase>,
+    pub webhooks: Arc<WebhookNotifier>,
     pub list_languages_use_case: Arc<ListLanguagesUseCase>,
+    pub compile_code_use_case: Arc<CompileCodeUseCase>,
+    pub get_compile_cache_stats_use_case: Arc<GetCompileCacheStatsUseCase>,
     pub health_check_use_case: Arc<HealthCheckUseCase>,
     pub list_box_files_use_case: Arc<ListBoxFilesUseCase>,
     pub get_box_file_use_case: Arc<GetBoxFileUseCase>,
+    pub upload_box_file_use_case: Arc<UploadBoxFileUseCase>,
+    pub delete_box_file_use_case: Arc<DeleteBoxFileUseCase>,
+    pub download_box_file_use_case: Arc<DownloadBoxFileUseCase>,
+    pub download_box_archive_use_case: Arc<DownloadBoxArchiveUseCase>,
     pub cleanup_box_use_case: Arc<CleanupBoxUseCase>,
-    pub api_key: Option<String>,
+    pub create_session_use_case: Arc<CreateSessionUseCase>,
+    pub execute_in_session_use_case: Arc<ExecuteInSessionUseCase>,
+    pub sessions: Arc<SessionRegistry>,
+    pub api_keys: Arc<HashMap<String, String>>,
+    /// Highest priority each tenant may ask for, any priority for tenants not listed
+    pub max_priorities: Arc<HashMap<String, Priority>>,
+}
+
+impl AppState {
+    /// Schedule a request of `tenant`, lowering the requested priority to the tenant's
+    /// maximum so a tenant cannot jump ahead of the others at will
+    fn scheduling(&self, tenant: Tenant, priority: Priority) -> SchedulingContext {
+        let priority = match self.max_priorities.get(&tenant.0) {
+            Some(&max_priority) => priority.min(max_priority),
+            None => priority,
+        };
+        SchedulingContext {
+            tenant: tenant.0,
+            priority,
+        }
+    }
 }
 
 /// Health check endpoint
@@ -65,67 +136,760 @@ pub async fn list_languages_handler(
     }))
 }
 
+/// Get compile cache statistics
+///
+/// Returns hit and miss counters and the current size of the compiled artifact cache
+#[utoipa::path(
+    get,
+    path = "/compile/cache",
+    responses(
+        (status = 200, description = "Compile cache statistics", body = CompileCacheStatsResponse),
+    ),
+    tag = "Languages"
+)]
+pub async fn compile_cache_stats_handler(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<CompileCacheStatsResponse>, ApiError> {
+    let stats = state.get_compile_cache_stats_use_case.execute().await;
+
+    Ok(Json(stats.into()))
+}
+
+/// Compile code without running it
+///
+/// Runs only the compiler of the language and reports whether the program compiled, along
+/// with the errors and warnings parsed from the compiler output
+#[utoipa::path(
+    post,
+    path = "/compile",
+    request_body = ProgramRequest,
+    responses(
+        (status = 200, description = "Compilation finished", body = CompileResponse),
+        (status = 400, description = "Invalid request or compilation exceeded its limits"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "Execution"
+)]
+pub async fn compile_code_handler(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ProgramRequest>,
+) -> Result<Json<CompileResponse>, ApiError> {
+    let source = program_source(
+        request.language,
+        request.code,
+        request.files,
+        request.entry_point,
+    )?;
+
+    let report = state.compile_code_use_case.execute(source).await?;
+
+    Ok(Json(report.into()))
+}
+
 /// Execute code in a sandboxed environment
 ///
-/// Executes the provided code in the specified language within an isolated sandbox
+/// Executes the provided code in the specified language within an isolated sandbox. The
+/// execution is cancelled if the client disconnects before it completes.
 #[utoipa::path(
     post,
     path = "/execute",
     request_body = ExecuteRequest,
     responses(
-        (status = 200, description = "Code executed successfully", body = ExecuteResponse),
-        (status = 400, description = "Invalid request"),
+        (status = 200, description = "Code executed successfully", body = ExecuteResponse,
+            headers(
+                ("X-Isolate-Execution" = String, description = "Execution ID"),
+                ("X-Isolate-Delivery" = String, description = "Webhook delivery ID, when a callback URL was given"),
+            )),
+        (status = 400, description = "Invalid request or resource limits out of range"),
+        (status = 409, description = "Execution was cancelled"),
         (status = 500, description = "Internal server error"),
+        (status = 503, description = "No box became free in time",
+            headers(("Retry-After" = u64, description = "Seconds to wait before retrying"))),
     ),
     tag = "Execution"
 )]
 pub async fn execute_code_handler(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
     Json(request): Json<ExecuteRequest>,
-) -> Result<Json<ExecuteResponse>, ApiError> {
-    let domain_request = DomainExecutionRequest {
-        language: request.language,
-        code: request.code,
+) -> Result<(HeaderMap, Json<ExecuteResponse>), ApiError> {
+    let callback_url = request.callback_url.clone();
+    let execution_id = request.execution_id.clone();
+    let domain_request = execution_request(&state, request, tenant)?;
+
+    let registration = state.executions.register(
+        &domain_request.scheduling.tenant,
+        &execution_id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+    )?;
+
+    let delivery_id = match callback_url {
+        Some(callback_url) => Some(
+            state
+                .webhooks
+                .register(&callback_url, &domain_request.scheduling.tenant, None)
+                .await?
+                .id,
+        ),
+        None => None,
     };
 
-    let result = state.execute_code_use_case.execute(domain_request).await?;
-
-    Ok(Json(ExecuteResponse {
-        stdout: result.stdout,
-        stderr: result.stderr,
-        metadata: MetadataResponse {
-            time: result.metadata.time,
-            time_wall: result.metadata.time_wall,
-            memory: result.metadata.memory,
-            exit_code: result.metadata.exit_code,
-            status: result.metadata.status,
-        },
-        box_id: result.box_id,
+    // Run detached from the request: if the client disconnects, this handler is dropped
+    // along with the registration, which cancels the execution and lets the task clean up
+    let use_case = state.execute_code_use_case.clone();
+    let webhooks = state.webhooks.clone();
+    let cancel = registration.token().clone();
+    let task_delivery_id = delivery_id.clone();
+    let task = tokio::spawn(async move {
+        let result = use_case.execute(domain_request, &cancel).await;
+        if let Some(delivery_id) = task_delivery_id {
+            // Same body as the synchronous response
+            let payload = match &result {
+                Ok(result) => serde_json::to_value(ExecuteResponse::from(result.clone())),
+                Err(e) => serde_json::to_value(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            };
+            match payload {
+                Ok(payload) => webhooks.deliver(delivery_id, payload),
+                Err(e) => log::warn!("Failed to serialize webhook {}: {}", delivery_id, e),
+            }
+        }
+        result
+    });
+    let result = task
+        .await
+        .map_err(|e| DomainError::Internal(format!("Execution task failed: {}", e)))?;
+
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(registration.id()) {
+        headers.insert(EXECUTION_ID_HEADER, value);
+    }
+    if let Some(value) = delivery_id.and_then(|id| HeaderValue::from_str(&id).ok()) {
+        headers.insert(DELIVERY_ID_HEADER, value);
+    }
+
+    Ok((headers, Json(result?.into())))
+}
+
+/// Execute code and stream its output
+///
+/// Accepts the same request as `/execute` and responds with Server-Sent Events: a `started`
+/// event (`StreamStartedEvent`), then `stdout` and `stderr` events (`OutputChunkEvent`) as
+/// output is produced, followed by a single `result` event (`StreamResultEvent`), or an
+/// `error` event (`ErrorResponse`) if the execution failed after the stream started. The
+/// execution is cancelled if the client disconnects before it completes.
+#[utoipa::path(
+    post,
+    path = "/execute/stream",
+    request_body = ExecuteRequest,
+    responses(
+        (status = 200, description = "Stream of execution events", content_type = "text/event-stream", body = String),
+        (status = 400, description = "Invalid request body"),
+    ),
+    tag = "Execution"
+)]
+pub async fn execute_stream_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Json(request): Json<ExecuteRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    let execution_id = request.execution_id.clone();
+    let domain_request = execution_request(&state, request, tenant)?;
+
+    let registration = state.executions.register(
+        &domain_request.scheduling.tenant,
+        &execution_id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+    )?;
+
+    let (event_tx, event_rx) = mpsc::channel(STREAM_BUFFER_SIZE);
+    let use_case = state.execute_code_use_case.clone();
+
+    let started = StreamStartedEvent {
+        execution_id: registration.id().to_string(),
+    };
+    let _ = event_tx.send(json_event("started", &started)).await;
+
+    // Cancel the execution once the client stops listening
+    let disconnect = {
+        let event_tx = event_tx.clone();
+        let cancel = registration.token().clone();
+        tokio::spawn(async move {
+            event_tx.closed().await;
+            cancel.cancel();
+        })
+    };
+
+    tokio::spawn(async move {
+        let cancel = registration.token();
+        let (chunk_tx, mut chunk_rx) = mpsc::channel::<OutputChunk>(STREAM_BUFFER_SIZE);
+
+        let forward_output = async {
+            let mut pending_stdout = Vec::new();
+            let mut pending_stderr = Vec::new();
+            while let Some(chunk) = chunk_rx.recv().await {
+                let (name, pending) = match chunk.stream {
+                    OutputStream::Stdout => ("stdout", &mut pending_stdout),
+                    OutputStream::Stderr => ("stderr", &mut pending_stderr),
+                };
+                pending.extend_from_slice(&chunk.data);
+                let data = take_utf8(pending);
+                if !data.is_empty() {
+                    let _ = event_tx.send(json_event(name, &OutputChunkEvent { data })).await;
+                }
+            }
+
+            // Flush incomplete characters left at the end of the output
+            for (name, pending) in [("stdout", pending_stdout), ("stderr", pending_stderr)] {
+                if !pending.is_empty() {
+                    let data = String::from_utf8_lossy(&pending).to_string();
+                    let _ = event_tx.send(json_event(name, &OutputChunkEvent { data })).await;
+                }
+            }
+        };
+
+        let (result, _) = tokio::join!(
+            use_case.execute_streaming(domain_request, chunk_tx, cancel),
+            forward_output
+        );
+        disconnect.abort();
+
+        let event = match result {
+            Ok(result) => json_event(
+                "result",
+                &StreamResultEvent {
+                    stdout_truncated: result.stdout_size.truncated,
+                    stderr_truncated: result.stderr_size.truncated,
+                    stdout_size: result.stdout_size.total,
+                    stderr_size: result.stderr_size.total,
+                    metadata: result.metadata.into(),
+                    box_id: result.box_id,
+                },
+            ),
+            Err(e) => json_event(
+                "error",
+                &ErrorResponse {
+                    error: e.to_string(),
+                },
+            ),
+        };
+        let _ = event_tx.send(event).await;
+    });
+
+    Ok(Sse::new(ReceiverStream::new(event_rx)).keep_alive(KeepAlive::default()))
+}
+
+/// Cancel a synchronous execution
+///
+/// Stops a running `/execute` or `/execute/stream` request of the same tenant by its
+/// execution ID. The program is killed and its box cleaned up and released; the cancelled
+/// request fails with 409.
+#[utoipa::path(
+    delete,
+    path = "/executions/{execution_id}",
+    params(
+        ("execution_id" = String, Path, description = "Execution ID")
+    ),
+    responses(
+        (status = 200, description = "Execution cancelled", body = CancelExecutionResponse),
+        (status = 404, description = "No running execution with this ID"),
+    ),
+    tag = "Execution"
+)]
+pub async fn cancel_execution_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Path(execution_id): Path<String>,
+) -> Result<Json<CancelExecutionResponse>, ApiError> {
+    state
+        .cancel_execution_use_case
+        .execute(&tenant.0, &execution_id)
+        .await?;
+
+    Ok(Json(CancelExecutionResponse {
+        message: format!("Execution {} cancelled", execution_id),
     }))
 }
 
+/// Submit an asynchronous execution job
+///
+/// Accepts the same request as `/execute` but returns immediately with a job that can be
+/// polled with `GET /jobs/{job_id}`
+#[utoipa::path(
+    post,
+    path = "/jobs",
+    request_body = ExecuteRequest,
+    responses(
+        (status = 202, description = "Job queued", body = JobResponse),
+        (status = 400, description = "Invalid request or resource limits out of range"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "Jobs"
+)]
+pub async fn submit_job_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Json(request): Json<ExecuteRequest>,
+) -> Result<(StatusCode, Json<JobResponse>), ApiError> {
+    let callback_url = request.callback_url.clone();
+    let domain_request = execution_request(&state, request, tenant)?;
+
+    let job = state
+        .submit_job_use_case
+        .execute(domain_request, callback_url)
+        .await?;
+
+    Ok((StatusCode::ACCEPTED, Json(job.into())))
+}
+
+/// Get the status of a job
+///
+/// Returns the job status, and the execution result once the job has finished
+#[utoipa::path(
+    get,
+    path = "/jobs/{job_id}",
+    params(
+        ("job_id" = String, Path, description = "Job ID")
+    ),
+    responses(
+        (status = 200, description = "Job status", body = JobResponse),
+        (status = 404, description = "Job not found"),
+    ),
+    tag = "Jobs"
+)]
+pub async fn get_job_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobResponse>, ApiError> {
+    let job = state.get_job_use_case.execute(&tenant.0, &job_id).await?;
+
+    Ok(Json(job.into()))
+}
+
+/// Cancel a job
+///
+/// Stops a queued, compiling or running job. A box acquired for the job is cleaned up and
+/// released.
+#[utoipa::path(
+    delete,
+    path = "/jobs/{job_id}",
+    params(
+        ("job_id" = String, Path, description = "Job ID")
+    ),
+    responses(
+        (status = 200, description = "Job cancelled", body = JobResponse),
+        (status = 400, description = "Job has already completed"),
+        (status = 404, description = "Job not found"),
+    ),
+    tag = "Jobs"
+)]
+pub async fn cancel_job_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobResponse>, ApiError> {
+    let job = state.cancel_job_use_case.execute(&tenant.0, &job_id).await?;
+
+    Ok(Json(job.into()))
+}
+
+/// List webhook deliveries
+///
+/// Returns the recorded deliveries of the caller's executions with their attempts, newest first
+#[utoipa::path(
+    get,
+    path = "/webhooks/deliveries",
+    params(WebhookDeliveriesQuery),
+    responses(
+        (status = 200, description = "Webhook deliveries", body = WebhookDeliveriesResponse),
+    ),
+    tag = "Webhooks"
+)]
+pub async fn list_webhook_deliveries_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Query(query): Query<WebhookDeliveriesQuery>,
+) -> Result<Json<WebhookDeliveriesResponse>, ApiError> {
+    let deliveries = state
+        .list_webhook_deliveries_use_case
+        .execute(&tenant.0, query.job_id.as_deref())
+        .await?;
+
+    Ok(Json(WebhookDeliveriesResponse {
+        deliveries: deliveries.into_iter().map(Into::into).collect(),
+    }))
+}
+
+/// Get a webhook delivery
+///
+/// Returns the delivery status and every attempt made so far
+#[utoipa::path(
+    get,
+    path = "/webhooks/deliveries/{delivery_id}",
+    params(
+        ("delivery_id" = String, Path, description = "Delivery ID")
+    ),
+    responses(
+        (status = 200, description = "Webhook delivery", body = WebhookDeliveryResponse),
+        (status = 404, description = "Delivery not found"),
+    ),
+    tag = "Webhooks"
+)]
+pub async fn get_webhook_delivery_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Path(delivery_id): Path<String>,
+) -> Result<Json<WebhookDeliveryResponse>, ApiError> {
+    let delivery = state
+        .get_webhook_delivery_use_case
+        .execute(&tenant.0, &delivery_id)
+        .await?;
+
+    Ok(Json(delivery.into()))
+}
+
+/// Execute code against a batch of test cases
+///
+/// Compiles the code once and runs it in a sandbox for each test case. The box is cleaned
+/// up and released after the batch completes. The batch is cancelled if the client
+/// disconnects before it completes.
+#[utoipa::path(
+    post,
+    path = "/execute/batch",
+    request_body = BatchExecuteRequest,
+    responses(
+        (status = 200, description = "Test cases executed", body = BatchExecuteResponse,
+            headers(("X-Isolate-Execution" = String, description = "Execution ID"))),
+        (status = 400, description = "Invalid request or resource limits out of range"),
+        (status = 409, description = "Execution was cancelled"),
+        (status = 500, description = "Internal server error"),
+        (status = 503, description = "No box became free in time",
+            headers(("Retry-After" = u64, description = "Seconds to wait before retrying"))),
+    ),
+    tag = "Execution"
+)]
+pub async fn execute_batch_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Json(request): Json<BatchExecuteRequest>,
+) -> Result<(HeaderMap, Json<BatchExecuteResponse>), ApiError> {
+    let execution_id = request.execution_id.clone();
+    let program = program_source(
+        request.language,
+        request.code,
+        request.files,
+        request.entry_point,
+    )?;
+
+    let test_cases = request
+        .test_cases
+        .into_iter()
+        .map(|test_case| {
+            Ok(TestCase {
+                stdin: test_case
+                    .stdin
+                    .map(|stdin| decode_content(stdin, test_case.stdin_encoding, "stdin"))
+                    .transpose()?,
+                args: test_case.args,
+                limits: test_case.limits.map(Into::into).unwrap_or_default(),
+                expected_output: test_case.expected_output,
+                comparison: test_case.comparison.map(Into::into).unwrap_or_default(),
+            })
+        })
+        .collect::<Result<Vec<_>, DomainError>>()?;
+
+    let checker = request
+        .checker
+        .map(|checker| {
+            program_source(
+                checker.language,
+                checker.code,
+                checker.files,
+                checker.entry_point,
+            )
+        })
+        .transpose()?;
+
+    let interactor = request
+        .interactor
+        .map(|interactor| {
+            program_source(
+                interactor.language,
+                interactor.code,
+                interactor.files,
+                interactor.entry_point,
+            )
+        })
+        .transpose()?;
+
+    let batch = TestCaseBatch {
+        program,
+        env: request.env,
+        test_cases,
+        stop_on_failure: request.stop_on_failure,
+        checker,
+        interactor,
+        interactor_limits: request.interactor_limits.map(Into::into).unwrap_or_default(),
+        scheduling: state.scheduling(tenant, request.priority.into()),
+        output_encoding: request.output_encoding.into(),
+    };
+
+    let registration = state.executions.register(
+        &batch.scheduling.tenant,
+        &execution_id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+    )?;
+
+    // Run detached from the request: if the client disconnects, this handler is dropped
+    // along with the registration, which cancels the batch and lets the task release its
+    // boxes
+    let use_case = state.run_test_cases_use_case.clone();
+    let cancel = registration.token().clone();
+    let results = tokio::spawn(async move { use_case.execute(batch, &cancel).await })
+        .await
+        .map_err(|e| DomainError::Internal(format!("Batch task failed: {}", e)))??;
+
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(registration.id()) {
+        headers.insert(EXECUTION_ID_HEADER, value);
+    }
+
+    Ok((
+        headers,
+        Json(BatchExecuteResponse {
+            results: results.into_iter().map(Into::into).collect(),
+        }),
+    ))
+}
+
+/// Build a domain program source, decoding any submitted files
+fn program_source(
+    language: String,
+    code: String,
+    files: Vec<SourceFileRequest>,
+    entry_point: Option<String>,
+) -> Result<ProgramSource, DomainError> {
+    let files = files
+        .into_iter()
+        .map(|file| {
+            Ok(SourceFile {
+                content: decode_content(file.content, file.encoding, &file.path)?,
+                path: file.path,
+            })
+        })
+        .collect::<Result<Vec<_>, DomainError>>()?;
+
+    Ok(ProgramSource {
+        language,
+        code,
+        files,
+        entry_point,
+    })
+}
+
+/// Build a domain execution request from the API request
+fn execution_request(
+    state: &AppState,
+    request: ExecuteRequest,
+    tenant: Tenant,
+) -> Result<DomainExecutionRequest, DomainError> {
+    let program = program_source(
+        request.language,
+        request.code,
+        request.files,
+        request.entry_point,
+    )?;
+
+    let stdin = request
+        .stdin
+        .map(|stdin| decode_content(stdin, request.stdin_encoding, "stdin"))
+        .transpose()?;
+
+    Ok(DomainExecutionRequest {
+        program,
+        stdin,
+        args: request.args,
+        env: request.env,
+        limits: request.limits.map(Into::into).unwrap_or_default(),
+        scheduling: state.scheduling(tenant, request.priority.into()),
+        output_encoding: request.output_encoding.into(),
+    })
+}
+
+/// Build a named Server-Sent Event with a JSON payload
+fn json_event<T: Serialize>(name: &str, data: &T) -> Result<Event, Infallible> {
+    let event = Event::default().event(name);
+    Ok(event.json_data(data).unwrap_or_else(|e| {
+        log::error!("Failed to serialize {} event: {}", name, e);
+        Event::default().event("error")
+    }))
+}
+
+/// Decode the complete UTF-8 prefix of `pending`, leaving a trailing incomplete character
+/// in place for the next chunk. Invalid sequences are replaced like `from_utf8_lossy`.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let mut text = String::new();
+    let mut pos = 0;
+    loop {
+        match std::str::from_utf8(&pending[pos..]) {
+            Ok(valid) => {
+                text.push_str(valid);
+                pos = pending.len();
+                break;
+            }
+            Err(e) => {
+                let valid_up_to = pos + e.valid_up_to();
+                text.push_str(&String::from_utf8_lossy(&pending[pos..valid_up_to]));
+                pos = valid_up_to;
+                match e.error_len() {
+                    Some(len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        pos += len;
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+    pending.drain(..pos);
+    text
+}
+
+/// Decode request content according to its declared encoding
+fn decode_content(
+    content: String,
+    encoding: ContentEncoding,
+    field: &str,
+) -> Result<Vec<u8>, DomainError> {
+    match encoding {
+        ContentEncoding::Utf8 => Ok(content.into_bytes()),
+        ContentEncoding::Base64 => base64::engine::general_purpose::STANDARD
+            .decode(content)
+            .map_err(|e| DomainError::InvalidRequest(format!("Invalid base64 in {}: {}", field, e))),
+    }
+}
+
+/// Create a session
+///
+/// Allocates and initializes a box that code can be run in repeatedly with
+/// `POST /boxes/{box_id}/execute`. The session ends when the box is cleaned up, or after it
+/// has been unused for the idle timeout. Each tenant may only have a limited number of
+/// sessions open at once.
+#[utoipa::path(
+    post,
+    path = "/boxes",
+    responses(
+        (status = 201, description = "Session created", body = SessionResponse),
+        (status = 429, description = "The tenant has too many sessions open"),
+        (status = 500, description = "Internal server error"),
+        (status = 503, description = "No box became free in time",
+            headers(("Retry-After" = u64, description = "Seconds to wait before retrying"))),
+    ),
+    tag = "Box Management"
+)]
+pub async fn create_session_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+) -> Result<(StatusCode, Json<SessionResponse>), ApiError> {
+    let scheduling = state.scheduling(tenant, Priority::default());
+    let box_id = state.create_session_use_case.execute(&scheduling).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SessionResponse {
+            box_id,
+            idle_timeout: state.sessions.idle_timeout().as_secs(),
+        }),
+    ))
+}
+
+/// Execute code in a session
+///
+/// Runs code in the box of a session without re-initializing it, so files written by
+/// earlier runs are still there. Accepts the same request as `/execute`; `callback_url`
+/// and `priority` are ignored. Runs in the same session are serialized, and a run completes
+/// even if the client disconnects. A run can be cancelled by its execution ID, which resets
+/// the box and ends the session.
+#[utoipa::path(
+    post,
+    path = "/boxes/{box_id}/execute",
+    params(
+        ("box_id" = u32, Path, description = "Box ID of the session")
+    ),
+    request_body = ExecuteRequest,
+    responses(
+        (status = 200, description = "Code executed successfully", body = ExecuteResponse,
+            headers(("X-Isolate-Execution" = String, description = "Execution ID"))),
+        (status = 400, description = "Invalid request or resource limits out of range"),
+        (status = 404, description = "No session in this box"),
+        (status = 409, description = "Execution was cancelled"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "Box Management"
+)]
+pub async fn execute_in_session_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Path(box_id): Path<u32>,
+    Json(request): Json<ExecuteRequest>,
+) -> Result<(HeaderMap, Json<ExecuteResponse>), ApiError> {
+    let execution_id = request.execution_id.clone();
+    let domain_request = execution_request(&state, request, tenant)?;
+
+    let registration = state.executions.register(
+        &domain_request.scheduling.tenant,
+        &execution_id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+    )?;
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(registration.id()) {
+        headers.insert(EXECUTION_ID_HEADER, value);
+    }
+
+    // Run detached from the request, keeping the registration with the task, so a
+    // disconnect never interrupts the session's box but cancelling by ID still does
+    let use_case = state.execute_in_session_use_case.clone();
+    let result = tokio::spawn(async move {
+        use_case
+            .execute(box_id, domain_request, registration.token())
+            .await
+    })
+    .await
+    .map_err(|e| DomainError::Internal(format!("Execution task failed: {}", e)))??;
+
+    Ok((headers, Json(result.into())))
+}
+
 /// List files in a sandbox box
 ///
-/// Returns a list of files in the specified sandbox box
+/// Returns the entries of the specified sandbox box with their type, size and modification
+/// time, optionally descending into subdirectories, filtered by a glob and paginated
 #[utoipa::path(
     get,
     path = "/boxes/{box_id}/files",
     params(
-        ("box_id" = u32, Path, description = "Box ID to list files from")
+        ("box_id" = u32, Path, description = "Box ID to list files from"),
+        BoxFilesQuery
     ),
     responses(
         (status = 200, description = "List of files in the box", body = BoxFilesResponse),
+        (status = 400, description = "Invalid pattern"),
+        (status = 404, description = "Box is not initialized or held by another tenant"),
         (status = 500, description = "Internal server error"),
     ),
     tag = "Box Management"
 )]
 pub async fn list_box_files_handler(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
     Path(box_id): Path<u32>,
+    Query(query): Query<BoxFilesQuery>,
 ) -> Result<Json<BoxFilesResponse>, ApiError> {
-    let files = state.list_box_files_use_case.execute(box_id).await?;
+    let offset = query.offset;
+    let listing = state
+        .list_box_files_use_case
+        .execute(box_id, &tenant.0, query.into())
+        .await?;
 
-    Ok(Json(BoxFilesResponse { files }))
+    Ok(Json(BoxFilesResponse::new(listing, offset)))
 }
 
 /// Get a file from a sandbox box
@@ -140,22 +904,337 @@ pub async fn list_box_files_handler(
     ),
     responses(
         (status = 200, description = "File content (base64 encoded)", body = BoxFileResponse),
+        (status = 404, description = "Box is not initialized or held by another tenant"),
         (status = 500, description = "Internal server error"),
     ),
     tag = "Box Management"
 )]
 pub async fn get_box_file_handler(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
     Path((box_id, filename)): Path<(u32, String)>,
 ) -> Result<Json<BoxFileResponse>, ApiError> {
     let content_base64 = state
         .get_box_file_use_case
-        .execute(box_id, &filename)
+        .execute(box_id, &tenant.0, &filename)
         .await?;
 
     Ok(Json(BoxFileResponse { content: content_base64, filename }))
 }
 
+/// Download a file from a sandbox box
+///
+/// Streams the raw content of a file with a `Content-Type` guessed from its extension and an
+/// `ETag`. Supports a single `bytes` range in `Range` (honouring `If-Range`) and
+/// `If-None-Match`.
+#[utoipa::path(
+    get,
+    path = "/boxes/{box_id}/raw/{path}",
+    params(
+        ("box_id" = u32, Path, description = "Box ID to download the file from"),
+        ("path" = String, Path, description = "Path of the file inside the box"),
+        ("Range" = Option<String>, Header, description = "Byte range, e.g. bytes=0-1023"),
+    ),
+    responses(
+        (status = 200, description = "File content", body = Vec<u8>,
+            content_type = "application/octet-stream"),
+        (status = 206, description = "Requested range of the file content", body = Vec<u8>,
+            content_type = "application/octet-stream"),
+        (status = 304, description = "File matches If-None-Match"),
+        (status = 400, description = "Invalid path, or not a regular file"),
+        (status = 404,
+            description = "Box is not initialized or held by another tenant, or file not found"),
+        (status = 416, description = "Range is outside the file"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "Box Management"
+)]
+pub async fn download_box_file_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Path((box_id, path)): Path<(u32, String)>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let use_case = &state.download_box_file_use_case;
+    let file = use_case.stat(box_id, &tenant.0, &path).await?;
+    let size = file.size;
+
+    // Like common static file servers, identify the content by its modification time and size
+    let etag = format!("\"{:x}-{:x}\"", file.modified_at.timestamp_micros(), size);
+    let content_type = mime_guess::from_path(&file.path).first_or_octet_stream();
+
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .any(|tag| tag == "*" || tag == etag)
+        });
+    if not_modified {
+        return Ok((StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response());
+    }
+
+    let (status, offset, length) = match byte_range(&headers, size, &etag) {
+        ByteRange::Full => (StatusCode::OK, 0, size),
+        ByteRange::Partial { offset, length } => (StatusCode::PARTIAL_CONTENT, offset, length),
+        ByteRange::Unsatisfiable => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+            )
+                .into_response());
+        }
+    };
+
+    let reader = use_case.open(box_id, &tenant.0, &file, offset, length).await?;
+    let mut response = (
+        status,
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_LENGTH, length.to_string()),
+            (header::ETAG, etag),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(reader)),
+    )
+        .into_response();
+    if status == StatusCode::PARTIAL_CONTENT {
+        let content_range = format!("bytes {}-{}/{}", offset, offset + length - 1, size);
+        if let Ok(value) = HeaderValue::from_str(&content_range) {
+            response.headers_mut().insert(header::CONTENT_RANGE, value);
+        }
+    }
+
+    Ok(response)
+}
+
+/// Part of a file selected by the `Range` header of a request
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Full,
+    Partial { offset: u64, length: u64 },
+    Unsatisfiable,
+}
+
+/// Select the part of a file of `size` bytes to send.
+///
+/// Only single `bytes` ranges are supported; other and malformed ranges, and ranges whose
+/// `If-Range` does not match `etag`, are ignored and the whole file is sent.
+fn byte_range(headers: &HeaderMap, size: u64, etag: &str) -> ByteRange {
+    let header_value = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if header_value(header::IF_RANGE).is_some_and(|if_range| if_range.trim() != etag) {
+        return ByteRange::Full;
+    }
+    let Some(range) = header_value(header::RANGE).and_then(|range| range.strip_prefix("bytes="))
+    else {
+        return ByteRange::Full;
+    };
+    let Some((start, end)) = range.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    if range.contains(',') {
+        return ByteRange::Full;
+    }
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        // The last `end` bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) if size > 0 => (size.saturating_sub(suffix), size - 1),
+            Ok(_) => return ByteRange::Unsatisfiable,
+            Err(_) => return ByteRange::Full,
+        },
+        (start, end) => {
+            let Ok(start) = start.parse::<u64>() else {
+                return ByteRange::Full;
+            };
+            let end = match end {
+                "" => u64::MAX,
+                end => match end.parse::<u64>() {
+                    Ok(end) if end >= start => end,
+                    _ => return ByteRange::Full,
+                },
+            };
+            if start >= size {
+                return ByteRange::Unsatisfiable;
+            }
+            (start, end.min(size - 1))
+        }
+    };
+
+    ByteRange::Partial {
+        offset: start,
+        length: end - start + 1,
+    }
+}
+
+/// Upload a file into a sandbox box
+///
+/// Writes the request body to the given path inside a box the caller holds, creating parent
+/// directories and replacing any existing file. The body is either the raw file content or
+/// a `multipart/form-data` form whose first part is the file. Files larger than the upload
+/// limit or the box's default file size limit are rejected.
+#[utoipa::path(
+    put,
+    path = "/boxes/{box_id}/files/{path}",
+    params(
+        ("box_id" = u32, Path, description = "Box ID to upload the file to"),
+        ("path" = String, Path, description = "Path of the file inside the box")
+    ),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream",
+        description = "File content, or a multipart/form-data form"),
+    responses(
+        (status = 200, description = "File written to the box", body = BoxFileUploadResponse),
+        (status = 400, description = "Invalid or reserved path, or malformed body"),
+        (status = 404, description = "Box is not initialized or held by another tenant"),
+        (status = 413, description = "File exceeds the upload limit"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "Box Management"
+)]
+pub async fn upload_box_file_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Path((box_id, path)): Path<(u32, String)>,
+    request: Request,
+) -> Result<Json<BoxFileUploadResponse>, ApiError> {
+    let is_multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+    let (box_path, size) = if is_multipart {
+        let mut multipart = Multipart::from_request(request, &state)
+            .await
+            .map_err(|e| DomainError::InvalidRequest(e.body_text()))?;
+        let field = multipart
+            .next_field()
+            .await
+            .map_err(|e| DomainError::InvalidRequest(format!("Invalid multipart body: {}", e)))?
+            .ok_or_else(|| DomainError::InvalidRequest("Multipart body has no parts".to_string()))?;
+        let content = field.map(|chunk| chunk.map_err(|e| upload_error(&e)));
+        state
+            .upload_box_file_use_case
+            .execute(box_id, &tenant.0, &path, content)
+            .await?
+    } else {
+        let content = request
+            .into_body()
+            .into_data_stream()
+            .map(|chunk| chunk.map_err(|e| upload_error(&e)));
+        state
+            .upload_box_file_use_case
+            .execute(box_id, &tenant.0, &path, content)
+            .await?
+    };
+
+    Ok(Json(BoxFileUploadResponse {
+        path: box_path.to_string_lossy().to_string(),
+        size,
+    }))
+}
+
+/// Error for an upload body that could not be read
+fn upload_error(error: &dyn std::fmt::Display) -> DomainError {
+    DomainError::InvalidRequest(format!("Failed to read upload: {}", error))
+}
+
+/// Delete a file from a sandbox box
+///
+/// Removes a file or symlink from the box, or a directory with everything in it when
+/// `recursive` is set, leaving the rest of the box in place
+#[utoipa::path(
+    delete,
+    path = "/boxes/{box_id}/files/{path}",
+    params(
+        ("box_id" = u32, Path, description = "Box ID to delete the file from"),
+        ("path" = String, Path, description = "Path of the file or directory inside the box"),
+        DeleteBoxFileQuery
+    ),
+    responses(
+        (status = 200, description = "File deleted", body = DeleteBoxFileResponse),
+        (status = 400, description = "Invalid path, or a directory without recursive"),
+        (status = 404, description = "Box is not initialized, held by another tenant, or file not found"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "Box Management"
+)]
+pub async fn delete_box_file_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Path((box_id, path)): Path<(u32, String)>,
+    Query(query): Query<DeleteBoxFileQuery>,
+) -> Result<Json<DeleteBoxFileResponse>, ApiError> {
+    let path = state
+        .delete_box_file_use_case
+        .execute(box_id, &tenant.0, &path, query.recursive)
+        .await?;
+
+    Ok(Json(DeleteBoxFileResponse {
+        message: format!("Deleted {} from box {}", path.display(), box_id),
+    }))
+}
+
+/// Download a sandbox box as an archive
+///
+/// Streams a tar.gz or zip archive of the files in the box, or of those matching `include`.
+/// Boxes whose contents or archive exceed the archive size limit are refused.
+#[utoipa::path(
+    get,
+    path = "/boxes/{box_id}/archive",
+    params(
+        ("box_id" = u32, Path, description = "Box ID to archive"),
+        BoxArchiveQuery
+    ),
+    responses(
+        (status = 200, description = "Archive of the box contents", content(
+            (Vec<u8> = "application/gzip"),
+            (Vec<u8> = "application/zip"),
+        )),
+        (status = 400, description = "Invalid pattern"),
+        (status = 404, description = "Box is not initialized or held by another tenant"),
+        (status = 413, description = "Box contents exceed the archive limit"),
+        (status = 500, description = "Internal server error"),
+    ),
+    tag = "Box Management"
+)]
+pub async fn download_box_archive_handler(
+    State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
+    Path(box_id): Path<u32>,
+    Query(query): Query<BoxArchiveQuery>,
+) -> Result<Response, ApiError> {
+    let format = ArchiveFormat::from(query.format);
+    let BoxArchive { file, size } = state
+        .download_box_archive_use_case
+        .execute(box_id, &tenant.0, format, query.include.as_deref())
+        .await?;
+
+    let content_type = match format {
+        ArchiveFormat::TarGz => "application/gzip",
+        ArchiveFormat::Zip => "application/zip",
+    };
+    let disposition = format!(
+        "attachment; filename=\"box-{}.{}\"",
+        box_id,
+        format.extension()
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_LENGTH, size.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
 /// Cleanup a sandbox box
 ///
 /// Cleans up the specified sandbox box and releases it back to the pool
@@ -167,18 +1246,125 @@ pub async fn get_box_file_handler(
     ),
     responses(
         (status = 200, description = "Box cleaned up successfully", body = CleanupResponse),
+        (status = 404, description = "Box holds a session of another tenant"),
         (status = 500, description = "Internal server error"),
     ),
     tag = "Box Management"
 )]
 pub async fn cleanup_box_handler(
     State(state): State<Arc<AppState>>,
+    Extension(tenant): Extension<Tenant>,
     Path(box_id): Path<u32>,
 ) -> Result<Json<CleanupResponse>, ApiError> {
-    state.cleanup_box_use_case.execute(box_id).await?;
+    state
+        .cleanup_box_use_case
+        .execute(box_id, &tenant.0)
+        .await?;
 
     Ok(Json(CleanupResponse {
         message: format!("Box {} cleaned up successfully", box_id),
     }))
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn take_utf8_keeps_incomplete_character_for_next_chunk() {
+        // "é" is 0xC3 0xA9 and "€" is 0xE2 0x82 0xAC
+        let cases: [(&[u8], &str, &[u8]); 7] = [
+            (b"hello", "hello", b""),
+            (b"", "", b""),
+            (b"caf\xc3", "caf", b"\xc3"),
+            (b"\xe2\x82", "", b"\xe2\x82"),
+            (b"a\xffb", "a\u{fffd}b", b""),
+            (b"a\xff\xe2", "a\u{fffd}", b"\xe2"),
+            (b"\xc3\xa9\xe2\x82\xac", "é€", b""),
+        ];
+
+        for (input, text, rest) in cases {
+            let mut pending = input.to_vec();
+            assert_eq!(take_utf8(&mut pending), text, "{:?}", input);
+            assert_eq!(pending, rest, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn take_utf8_reassembles_characters_split_across_chunks() {
+        let output = "naïve € 😀".as_bytes();
+
+        for split in 0..=output.len() {
+            let mut pending = output[..split].to_vec();
+            let mut text = take_utf8(&mut pending);
+            pending.extend_from_slice(&output[split..]);
+            text.push_str(&take_utf8(&mut pending));
+
+            assert_eq!(text, "naïve € 😀", "split at {}", split);
+            assert!(pending.is_empty());
+        }
+    }
+
+    fn range_headers(range: Option<&str>, if_range: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(range) = range {
+            headers.insert(header::RANGE, HeaderValue::from_str(range).unwrap());
+        }
+        if let Some(if_range) = if_range {
+            headers.insert(header::IF_RANGE, HeaderValue::from_str(if_range).unwrap());
+        }
+        headers
+    }
+
+    fn partial(offset: u64, length: u64) -> ByteRange {
+        ByteRange::Partial { offset, length }
+    }
+
+    #[test]
+    fn byte_range_selects_single_ranges() {
+        let cases = [
+            (None, ByteRange::Full),
+            (Some("bytes=0-4"), partial(0, 5)),
+            (Some("bytes=5-"), partial(5, 5)),
+            (Some("bytes= 2 - 2 "), partial(2, 1)),
+            (Some("bytes=8-100"), partial(8, 2)),
+            // Suffix ranges
+            (Some("bytes=-3"), partial(7, 3)),
+            (Some("bytes=-20"), partial(0, 10)),
+            (Some("bytes=-0"), ByteRange::Unsatisfiable),
+            // Out of bounds
+            (Some("bytes=10-"), ByteRange::Unsatisfiable),
+            (Some("bytes=20-30"), ByteRange::Unsatisfiable),
+            // Multiple, malformed and non-byte ranges are ignored
+            (Some("bytes=0-1,4-5"), ByteRange::Full),
+            (Some("bytes=5-2"), ByteRange::Full),
+            (Some("bytes=a-4"), ByteRange::Full),
+            (Some("bytes=-x"), ByteRange::Full),
+            (Some("bytes=4"), ByteRange::Full),
+            (Some("items=0-4"), ByteRange::Full),
+        ];
+
+        for (range, expected) in cases {
+            let headers = range_headers(range, None);
+            assert_eq!(byte_range(&headers, 10, "\"e\""), expected, "{:?}", range);
+        }
+    }
+
+    #[test]
+    fn byte_range_of_empty_file_is_unsatisfiable() {
+        for range in ["bytes=0-", "bytes=0-0", "bytes=-5"] {
+            let selected = byte_range(&range_headers(Some(range), None), 0, "\"e\"");
+            assert_eq!(selected, ByteRange::Unsatisfiable, "{}", range);
+        }
+    }
+
+    #[test]
+    fn byte_range_honors_if_range() {
+        let matching = range_headers(Some("bytes=0-4"), Some("\"e\""));
+        assert_eq!(byte_range(&matching, 10, "\"e\""), partial(0, 5));
+
+        let stale = range_headers(Some("bytes=0-4"), Some("\"old\""));
+        assert_eq!(byte_range(&stale, 10, "\"e\""), ByteRange::Full);
+    }
+}
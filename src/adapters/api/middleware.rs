@@ -1,3 +1,4 @@
+use crate::domain::entities::DEFAULT_TENANT;
 use axum::{
     extract::Request,
     http::StatusCode,
@@ -6,20 +7,37 @@ use axum::{
     Json,
 };
 use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Tenant a request is made on behalf of, available as a request extension
+#[derive(Debug, Clone)]
+pub struct Tenant(pub String);
 
 /// Middleware to check API key authentication
 ///
 /// Returns 403 if X-API-Key header is missing
 /// Returns 401 if X-API-Key header is present but invalid
+///
+/// The tenant is the one the API key belongs to. When authentication is disabled, it is
+/// taken from the optional X-Tenant header instead.
 pub async fn auth_middleware(
-    expected_api_key: Option<String>,
-    request: Request,
+    api_keys: Arc<HashMap<String, String>>,
+    mut request: Request,
     next: Next,
 ) -> Response {
     // If no API key is configured, allow all requests
-    let Some(expected_key) = expected_api_key else {
+    if api_keys.is_empty() {
+        let tenant = request
+            .headers()
+            .get("x-tenant")
+            .and_then(|tenant| tenant.to_str().ok())
+            .filter(|tenant| !tenant.is_empty())
+            .unwrap_or(DEFAULT_TENANT)
+            .to_string();
+        request.extensions_mut().insert(Tenant(tenant));
         return next.run(request).await;
-    };
+    }
 
     // Check if X-API-Key header is present
     let api_key_header = request.headers().get("x-api-key");
@@ -37,12 +55,14 @@ pub async fn auth_middleware(
         }
         Some(key) => {
             // Header is present - validate it
-            match key.to_str() {
-                Ok(key_str) if key_str == expected_key => {
+            match key.to_str().ok().and_then(|key| api_keys.get(key)) {
+                Some(tenant) => {
                     // Valid API key - proceed
+                    let tenant = Tenant(tenant.clone());
+                    request.extensions_mut().insert(tenant);
                     next.run(request).await
                 }
-                _ => {
+                None => {
                     // Invalid API key - return 401
                     (
                         StatusCode::UNAUTHORIZED,
@@ -56,4 +76,3 @@ pub async fn auth_middleware(
         }
     }
 }
-
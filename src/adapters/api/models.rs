@@ -1,12 +1,168 @@
+use crate::domain::entities::{
+    ArchiveFormat, BoxEntry, BoxEntryKind, BoxListing, BoxListingQuery, CompileReport,
+    DeliveryAttempt, DeliveryStatus, Diagnostic, DiagnosticSeverity, ExecutionMetadata,
+    ExecutionResult, Job, JobStatus, OutputComparison, OutputEncoding, Priority,
+    ResourceLimitOverrides, TestCaseResult, Verdict, WebhookDelivery,
+};
+use crate::domain::services::CompileCacheStats;
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
 
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct ExecuteRequest {
     /// Programming language to execute (e.g., "python", "rust", "javascript")
     pub language: String,
-    /// Source code to execute
+    /// Source code to execute (leave empty when submitting `files`)
+    #[serde(default)]
     pub code: String,
+    /// Source files for multi-file submissions
+    #[serde(default)]
+    pub files: Vec<SourceFileRequest>,
+    /// Path of the file to compile and run (required when `files` has more than one entry)
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    /// Standard input passed to the program
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Encoding of `stdin` ("utf8" or "base64")
+    #[serde(default)]
+    pub stdin_encoding: ContentEncoding,
+    /// Command-line arguments passed to the program
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables (reserved names such as HOME and PYTHONPATH are rejected)
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Resource limit overrides (must not exceed the server maximums)
+    #[serde(default)]
+    pub limits: Option<ResourceLimitsRequest>,
+    /// URL to POST the signed result to once the execution completes
+    #[serde(default)]
+    pub callback_url: Option<String>,
+    /// Scheduling class used when waiting for a free box
+    #[serde(default)]
+    pub priority: PriorityRequest,
+    /// Encoding of `stdout` and `stderr` in the response
+    #[serde(default)]
+    pub output_encoding: OutputEncodingRequest,
+    /// ID to cancel a synchronous execution with (generated when absent, ignored by `/jobs`).
+    /// IDs are scoped to the tenant, and must not be in use by a running execution.
+    #[serde(default)]
+    pub execution_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriorityRequest {
+    /// Bulk work such as rejudges, served after everything else
+    Batch,
+    /// Regular requests
+    #[default]
+    Normal,
+    /// Latency sensitive requests, served first
+    Interactive,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputEncodingRequest {
+    /// UTF-8 text, with invalid sequences replaced
+    #[default]
+    Utf8,
+    /// Base64 of the raw bytes
+    Base64,
+    /// UTF-8 when the output is valid UTF-8, base64 otherwise
+    Auto,
+}
+
+impl From<OutputEncodingRequest> for OutputEncoding {
+    fn from(encoding: OutputEncodingRequest) -> Self {
+        match encoding {
+            OutputEncodingRequest::Utf8 => Self::Utf8,
+            OutputEncodingRequest::Base64 => Self::Base64,
+            OutputEncodingRequest::Auto => Self::Auto,
+        }
+    }
+}
+
+impl From<OutputEncoding> for ContentEncoding {
+    fn from(encoding: OutputEncoding) -> Self {
+        match encoding {
+            OutputEncoding::Base64 => Self::Base64,
+            // Results are only ever plain text or base64
+            OutputEncoding::Utf8 | OutputEncoding::Auto => Self::Utf8,
+        }
+    }
+}
+
+impl From<PriorityRequest> for Priority {
+    fn from(priority: PriorityRequest) -> Self {
+        match priority {
+            PriorityRequest::Batch => Self::Batch,
+            PriorityRequest::Normal => Self::Normal,
+            PriorityRequest::Interactive => Self::Interactive,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct ResourceLimitsRequest {
+    /// Control group memory limit in KB
+    pub cg_mem: Option<u32>,
+    /// Address space memory limit in KB
+    pub mem: Option<u32>,
+    /// CPU time limit in seconds
+    pub time: Option<u32>,
+    /// Wall clock time limit in seconds
+    pub wall_time: Option<u32>,
+    /// Extra time before the process is killed after exceeding the time limit, in seconds
+    pub extra_time: Option<u32>,
+    /// Stack size limit in KB
+    pub stack: Option<u32>,
+    /// Maximum size of created files in KB
+    pub fsize: Option<u32>,
+    /// Maximum number of open files
+    pub open_files: Option<u32>,
+    /// Maximum number of processes
+    pub processes: Option<u32>,
+}
+
+impl From<ResourceLimitsRequest> for ResourceLimitOverrides {
+    fn from(limits: ResourceLimitsRequest) -> Self {
+        Self {
+            cg_mem: limits.cg_mem,
+            mem: limits.mem,
+            time: limits.time,
+            wall_time: limits.wall_time,
+            extra_time: limits.extra_time,
+            stack: limits.stack,
+            fsize: limits.fsize,
+            open_files: limits.open_files,
+            processes: limits.processes,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SourceFileRequest {
+    /// Relative path of the file inside the box (e.g., "utils.py" or "lib/helpers.py")
+    pub path: String,
+    /// File content
+    pub content: String,
+    /// Encoding of `content` ("utf8" or "base64")
+    #[serde(default)]
+    pub encoding: ContentEncoding,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentEncoding {
+    /// Plain UTF-8 text
+    #[default]
+    Utf8,
+    /// Base64 encoded bytes
+    Base64,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -15,6 +171,213 @@ pub struct ExecuteResponse {
     pub stdout: String,
     /// Standard error from the execution
     pub stderr: String,
+    /// Encoding of `stdout`
+    pub stdout_encoding: ContentEncoding,
+    /// Encoding of `stderr`
+    pub stderr_encoding: ContentEncoding,
+    /// Whether stdout was cut off at the server's output limit
+    pub stdout_truncated: bool,
+    /// Whether stderr was cut off at the server's output limit
+    pub stderr_truncated: bool,
+    /// Total number of bytes written to stdout, including any truncated part
+    pub stdout_size: u64,
+    /// Total number of bytes written to stderr, including any truncated part
+    pub stderr_size: u64,
+    /// Execution metadata
+    pub metadata: MetadataResponse,
+    /// Box ID used for execution
+    pub box_id: u32,
+}
+
+impl From<ExecutionResult> for ExecuteResponse {
+    fn from(result: ExecutionResult) -> Self {
+        Self {
+            stdout: result.stdout,
+            stderr: result.stderr,
+            stdout_encoding: result.stdout_encoding.into(),
+            stderr_encoding: result.stderr_encoding.into(),
+            stdout_truncated: result.stdout_size.truncated,
+            stderr_truncated: result.stderr_size.truncated,
+            stdout_size: result.stdout_size.total,
+            stderr_size: result.stderr_size.total,
+            metadata: result.metadata.into(),
+            box_id: result.box_id,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobResponse {
+    /// Job ID used to poll or cancel the job
+    pub id: String,
+    /// Current status of the job
+    pub status: JobStatusResponse,
+    /// Execution result, once the job has finished
+    pub result: Option<ExecuteResponse>,
+    /// Error message, if the job failed
+    pub error: Option<String>,
+    /// ID of the webhook delivery reporting the job, if a callback URL was given
+    pub webhook_delivery_id: Option<String>,
+    /// Time the job was submitted (RFC 3339)
+    pub created_at: String,
+    /// Time of the last status change (RFC 3339)
+    pub updated_at: String,
+}
+
+impl From<Job> for JobResponse {
+    fn from(job: Job) -> Self {
+        Self {
+            id: job.id,
+            status: job.status.into(),
+            result: job.result.map(Into::into),
+            error: job.error,
+            webhook_delivery_id: job.webhook_delivery_id,
+            created_at: job.created_at.to_rfc3339(),
+            updated_at: job.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatusResponse {
+    /// Waiting to start
+    Queued,
+    /// Compiling the source code
+    Compiling,
+    /// Running in a sandbox
+    Running,
+    /// Execution completed, see `result`
+    Finished,
+    /// Compilation or execution failed, see `error`
+    Failed,
+    /// Cancelled by the client
+    Cancelled,
+}
+
+impl From<JobStatus> for JobStatusResponse {
+    fn from(status: JobStatus) -> Self {
+        match status {
+            JobStatus::Queued => Self::Queued,
+            JobStatus::Compiling => Self::Compiling,
+            JobStatus::Running => Self::Running,
+            JobStatus::Finished => Self::Finished,
+            JobStatus::Failed => Self::Failed,
+            JobStatus::Cancelled => Self::Cancelled,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct WebhookDeliveriesQuery {
+    /// Only list deliveries reporting this job
+    pub job_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookDeliveriesResponse {
+    /// Webhook deliveries, newest first
+    pub deliveries: Vec<WebhookDeliveryResponse>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookDeliveryResponse {
+    /// Delivery ID, also sent in the `X-Isolate-Delivery` header
+    pub id: String,
+    /// URL the result is POSTed to
+    pub callback_url: String,
+    /// Job reported by this delivery (absent for `/execute` requests)
+    pub job_id: Option<String>,
+    /// Current delivery status
+    pub status: DeliveryStatusResponse,
+    /// Attempts made so far, oldest first
+    pub attempts: Vec<DeliveryAttemptResponse>,
+    /// Time the delivery was registered (RFC 3339)
+    pub created_at: String,
+    /// Time of the last attempt or status change (RFC 3339)
+    pub updated_at: String,
+}
+
+impl From<WebhookDelivery> for WebhookDeliveryResponse {
+    fn from(delivery: WebhookDelivery) -> Self {
+        Self {
+            id: delivery.id,
+            callback_url: delivery.callback_url,
+            job_id: delivery.job_id,
+            status: delivery.status.into(),
+            attempts: delivery.attempts.into_iter().map(Into::into).collect(),
+            created_at: delivery.created_at.to_rfc3339(),
+            updated_at: delivery.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatusResponse {
+    /// Waiting for the execution to complete, or retrying
+    Pending,
+    /// Acknowledged by the receiver with a 2xx response
+    Delivered,
+    /// Every attempt failed
+    Failed,
+}
+
+impl From<DeliveryStatus> for DeliveryStatusResponse {
+    fn from(status: DeliveryStatus) -> Self {
+        match status {
+            DeliveryStatus::Pending => Self::Pending,
+            DeliveryStatus::Delivered => Self::Delivered,
+            DeliveryStatus::Failed => Self::Failed,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeliveryAttemptResponse {
+    /// Time of the attempt (RFC 3339)
+    pub attempted_at: String,
+    /// HTTP status returned by the receiver, if it responded
+    pub status_code: Option<u16>,
+    /// Why the attempt failed
+    pub error: Option<String>,
+}
+
+impl From<DeliveryAttempt> for DeliveryAttemptResponse {
+    fn from(attempt: DeliveryAttempt) -> Self {
+        Self {
+            attempted_at: attempt.attempted_at.to_rfc3339(),
+            status_code: attempt.status_code,
+            error: attempt.error,
+        }
+    }
+}
+
+/// Data of the first `started` event sent by the streaming execute endpoint
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StreamStartedEvent {
+    /// ID to cancel the execution with
+    pub execution_id: String,
+}
+
+/// Data of a `stdout` or `stderr` event sent by the streaming execute endpoint
+#[derive(Debug, Serialize, ToSchema)]
+pub struct OutputChunkEvent {
+    /// Output text produced since the previous event on the same stream
+    pub data: String,
+}
+
+/// Data of the final `result` event sent by the streaming execute endpoint
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StreamResultEvent {
+    /// Whether stdout was cut off at the server's output limit
+    pub stdout_truncated: bool,
+    /// Whether stderr was cut off at the server's output limit
+    pub stderr_truncated: bool,
+    /// Total number of bytes written to stdout, including any truncated part
+    pub stdout_size: u64,
+    /// Total number of bytes written to stderr, including any truncated part
+    pub stderr_size: u64,
     /// Execution metadata
     pub metadata: MetadataResponse,
     /// Box ID used for execution
@@ -33,6 +396,222 @@ pub struct MetadataResponse {
     pub exit_code: i32,
     /// Execution status
     pub status: String,
+    /// Explanation of the status from the sandbox (e.g. "Time limit exceeded"), kept
+    /// separate from the program's stderr
+    pub sandbox_message: Option<String>,
+}
+
+impl From<ExecutionMetadata> for MetadataResponse {
+    fn from(metadata: ExecutionMetadata) -> Self {
+        Self {
+            time: metadata.time,
+            time_wall: metadata.time_wall,
+            memory: metadata.memory,
+            exit_code: metadata.exit_code,
+            status: metadata.status,
+            sandbox_message: metadata.message,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BatchExecuteRequest {
+    /// Programming language to execute (e.g., "python", "rust", "javascript")
+    pub language: String,
+    /// Source code to execute (leave empty when submitting `files`)
+    #[serde(default)]
+    pub code: String,
+    /// Source files for multi-file submissions
+    #[serde(default)]
+    pub files: Vec<SourceFileRequest>,
+    /// Path of the file to compile and run (required when `files` has more than one entry)
+    #[serde(default)]
+    pub entry_point: Option<String>,
+    /// Extra environment variables shared by all test cases
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Test cases to run against the compiled program
+    pub test_cases: Vec<TestCaseRequest>,
+    /// Stop running test cases after the first one that fails
+    #[serde(default)]
+    pub stop_on_failure: bool,
+    /// Checker program deciding verdicts instead of `expected_output` comparison.
+    /// It is run as `checker input output answer` and its exit code decides the verdict
+    /// (0 = accepted, 1 = wrong answer, anything else = judge error)
+    #[serde(default)]
+    pub checker: Option<ProgramRequest>,
+    /// Interactor program for interactive problems. It runs in its own box with its stdin and
+    /// stdout connected to the program's stdout and stdin, is invoked as
+    /// `interactor input answer` (test case stdin and expected output) and its exit code
+    /// decides the verdict
+    #[serde(default)]
+    pub interactor: Option<ProgramRequest>,
+    /// Resource limit overrides for the interactor
+    #[serde(default)]
+    pub interactor_limits: Option<ResourceLimitsRequest>,
+    /// Scheduling class used when waiting for a free box
+    #[serde(default)]
+    pub priority: PriorityRequest,
+    /// Encoding of `stdout` and `stderr` in the results
+    #[serde(default)]
+    pub output_encoding: OutputEncodingRequest,
+    /// ID to cancel the batch with (generated when absent). IDs are scoped to the tenant,
+    /// and must not be in use by a running execution.
+    #[serde(default)]
+    pub execution_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ProgramRequest {
+    /// Programming language of the program
+    pub language: String,
+    /// Source code (leave empty when submitting `files`)
+    #[serde(default)]
+    pub code: String,
+    /// Source files for multi-file programs
+    #[serde(default)]
+    pub files: Vec<SourceFileRequest>,
+    /// Path of the file to compile and run (required when `files` has more than one entry)
+    #[serde(default)]
+    pub entry_point: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TestCaseRequest {
+    /// Standard input passed to the program
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Encoding of `stdin` ("utf8" or "base64")
+    #[serde(default)]
+    pub stdin_encoding: ContentEncoding,
+    /// Command-line arguments passed to the program
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Resource limit overrides for this test case
+    #[serde(default)]
+    pub limits: Option<ResourceLimitsRequest>,
+    /// Expected standard output; when set the result carries a verdict
+    #[serde(default)]
+    pub expected_output: Option<String>,
+    /// How stdout is compared against `expected_output` (defaults to exact)
+    #[serde(default)]
+    pub comparison: Option<ComparisonRequest>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ComparisonRequest {
+    /// Byte-for-byte equality
+    Exact,
+    /// Ignore trailing whitespace on each line and trailing blank lines
+    IgnoreTrailingWhitespace,
+    /// Compare whitespace separated tokens
+    Tokens,
+    /// Compare tokens, allowing numbers to differ within an absolute or relative epsilon
+    Float {
+        /// Maximum absolute difference between numbers
+        #[serde(default = "default_float_epsilon")]
+        abs_epsilon: f64,
+        /// Maximum difference relative to the expected number
+        #[serde(default = "default_float_epsilon")]
+        rel_epsilon: f64,
+    },
+}
+
+fn default_float_epsilon() -> f64 {
+    1e-6
+}
+
+impl From<ComparisonRequest> for OutputComparison {
+    fn from(comparison: ComparisonRequest) -> Self {
+        match comparison {
+            ComparisonRequest::Exact => Self::Exact,
+            ComparisonRequest::IgnoreTrailingWhitespace => Self::IgnoreTrailingWhitespace,
+            ComparisonRequest::Tokens => Self::Tokens,
+            ComparisonRequest::Float {
+                abs_epsilon,
+                rel_epsilon,
+            } => Self::Float {
+                abs_epsilon,
+                rel_epsilon,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BatchExecuteResponse {
+    /// Results of the test cases that were run, in request order
+    pub results: Vec<TestCaseResponse>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TestCaseResponse {
+    /// Standard output from the execution
+    pub stdout: String,
+    /// Standard error from the execution
+    pub stderr: String,
+    /// Encoding of `stdout`
+    pub stdout_encoding: ContentEncoding,
+    /// Encoding of `stderr`
+    pub stderr_encoding: ContentEncoding,
+    /// Whether stdout was cut off at the server's output limit
+    pub stdout_truncated: bool,
+    /// Whether stderr was cut off at the server's output limit
+    pub stderr_truncated: bool,
+    /// Total number of bytes written to stdout, including any truncated part
+    pub stdout_size: u64,
+    /// Total number of bytes written to stderr, including any truncated part
+    pub stderr_size: u64,
+    /// Execution metadata
+    pub metadata: MetadataResponse,
+    /// Verdict against the expected output or from the checker (absent when there is
+    /// nothing to judge against or the program did not exit normally)
+    pub verdict: Option<VerdictResponse>,
+    /// Score between 0 and 1 (reported by the checker, or implied by the verdict)
+    pub score: Option<f64>,
+    /// Feedback printed by the checker or interactor to stderr
+    pub checker_message: Option<String>,
+}
+
+impl From<TestCaseResult> for TestCaseResponse {
+    fn from(result: TestCaseResult) -> Self {
+        Self {
+            stdout: result.stdout,
+            stderr: result.stderr,
+            stdout_encoding: result.stdout_encoding.into(),
+            stderr_encoding: result.stderr_encoding.into(),
+            stdout_truncated: result.stdout_size.truncated,
+            stderr_truncated: result.stderr_size.truncated,
+            stdout_size: result.stdout_size.total,
+            stderr_size: result.stderr_size.total,
+            metadata: result.metadata.into(),
+            verdict: result.verdict.map(Into::into),
+            score: result.score,
+            checker_message: result.checker_message,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VerdictResponse {
+    Accepted,
+    WrongAnswer,
+    JudgeError,
+    /// Output was cut off at the server's output limit, so it was not judged
+    OutputLimitExceeded,
+}
+
+impl From<Verdict> for VerdictResponse {
+    fn from(verdict: Verdict) -> Self {
+        match verdict {
+            Verdict::Accepted => Self::Accepted,
+            Verdict::WrongAnswer => Self::WrongAnswer,
+            Verdict::JudgeError => Self::JudgeError,
+            Verdict::OutputLimitExceeded => Self::OutputLimitExceeded,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -41,6 +620,103 @@ pub struct HealthResponse {
     pub status: String,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CompileResponse {
+    /// Whether the program compiled
+    pub success: bool,
+    /// Exit code of the compiler
+    pub exit_code: i32,
+    /// Number of warning diagnostics
+    pub warnings: usize,
+    /// Errors and warnings parsed from the compiler output (empty for languages without a
+    /// diagnostics parser)
+    pub diagnostics: Vec<DiagnosticResponse>,
+    /// Compiler output with the paths of the submitted sources masked
+    pub output: String,
+}
+
+impl From<CompileReport> for CompileResponse {
+    fn from(report: CompileReport) -> Self {
+        Self {
+            success: report.success,
+            exit_code: report.exit_code,
+            warnings: report.warning_count(),
+            diagnostics: report.diagnostics.into_iter().map(Into::into).collect(),
+            output: report.output,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DiagnosticResponse {
+    /// File as named in the compiler output, with submission paths masked
+    pub file: String,
+    /// 1-based line number
+    pub line: u32,
+    /// 1-based column, when the compiler points at one
+    pub column: Option<u32>,
+    /// Severity of the diagnostic
+    pub severity: DiagnosticSeverityResponse,
+    /// Compiler message
+    pub message: String,
+}
+
+impl From<Diagnostic> for DiagnosticResponse {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Self {
+            file: diagnostic.file,
+            line: diagnostic.line,
+            column: diagnostic.column,
+            severity: diagnostic.severity.into(),
+            message: diagnostic.message,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverityResponse {
+    Error,
+    Warning,
+}
+
+impl From<DiagnosticSeverity> for DiagnosticSeverityResponse {
+    fn from(severity: DiagnosticSeverity) -> Self {
+        match severity {
+            DiagnosticSeverity::Error => Self::Error,
+            DiagnosticSeverity::Warning => Self::Warning,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CompileCacheStatsResponse {
+    /// Whether compiled artifacts are cached
+    pub enabled: bool,
+    /// Compilations served from the cache
+    pub hits: u64,
+    /// Compilations that had to run the compiler
+    pub misses: u64,
+    /// Number of cached artifacts
+    pub entries: usize,
+    /// Total size of the cached artifacts in bytes
+    pub size: u64,
+}
+
+impl From<Option<CompileCacheStats>> for CompileCacheStatsResponse {
+    fn from(stats: Option<CompileCacheStats>) -> Self {
+        let enabled = stats.is_some();
+        let stats = stats.unwrap_or_default();
+        Self {
+            enabled,
+            hits: stats.hits,
+            misses: stats.misses,
+            entries: stats.entries,
+            size: stats.size,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct LanguagesResponse {
     /// List of supported programming languages
@@ -53,10 +729,149 @@ pub struct ErrorResponse {
     pub error: String,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionResponse {
+    /// Box ID of the session, used to run code in it and to inspect or clean it up
+    pub box_id: u32,
+    /// Seconds the session may go unused before its box is cleaned up and released
+    /// (0 = never)
+    pub idle_timeout: u64,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct BoxFilesQuery {
+    /// Include the contents of subdirectories
+    #[serde(default)]
+    pub recursive: bool,
+    /// Only list entries whose path matches this glob (`*`, `**`, `?`, `[...]`); a pattern
+    /// without `/` is matched against the entry name
+    pub pattern: Option<String>,
+    /// Number of matching entries to skip
+    #[serde(default)]
+    pub offset: usize,
+    /// Maximum number of entries to return
+    pub limit: Option<usize>,
+    /// Include the SHA-256 of every listed regular file
+    #[serde(default)]
+    pub sha256: bool,
+}
+
+impl From<BoxFilesQuery> for BoxListingQuery {
+    fn from(query: BoxFilesQuery) -> Self {
+        Self {
+            recursive: query.recursive,
+            pattern: query.pattern,
+            offset: query.offset,
+            limit: query.limit,
+            sha256: query.sha256,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct BoxFilesResponse {
-    /// List of files in the box
+    /// Paths of the listed entries, relative to the box root
     pub files: Vec<String>,
+    /// Listed entries with their metadata, sorted by path
+    pub entries: Vec<BoxEntryResponse>,
+    /// Number of matching entries across all pages
+    pub total: usize,
+    /// Offset of the next page, if there is one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_offset: Option<usize>,
+}
+
+impl BoxFilesResponse {
+    pub fn new(listing: BoxListing, offset: usize) -> Self {
+        let end = offset + listing.entries.len();
+        Self {
+            files: listing
+                .entries
+                .iter()
+                .map(|entry| entry.path.clone())
+                .collect(),
+            entries: listing.entries.into_iter().map(Into::into).collect(),
+            total: listing.total,
+            next_offset: (end < listing.total).then_some(end),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BoxEntryResponse {
+    /// Path relative to the box root
+    pub path: String,
+    #[serde(rename = "type")]
+    pub kind: BoxEntryKindResponse,
+    /// Size in bytes
+    pub size: u64,
+    /// Last modification time (RFC 3339)
+    pub modified_at: String,
+    /// SHA-256 of the content, for regular files when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl From<BoxEntry> for BoxEntryResponse {
+    fn from(entry: BoxEntry) -> Self {
+        Self {
+            path: entry.path,
+            kind: entry.kind.into(),
+            size: entry.size,
+            modified_at: entry.modified_at.to_rfc3339(),
+            sha256: entry.sha256,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BoxEntryKindResponse {
+    File,
+    Directory,
+    Symlink,
+    /// Socket, pipe or device file
+    Other,
+}
+
+impl From<BoxEntryKind> for BoxEntryKindResponse {
+    fn from(kind: BoxEntryKind) -> Self {
+        match kind {
+            BoxEntryKind::File => Self::File,
+            BoxEntryKind::Directory => Self::Directory,
+            BoxEntryKind::Symlink => Self::Symlink,
+            BoxEntryKind::Other => Self::Other,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct BoxArchiveQuery {
+    /// Archive format
+    #[serde(default)]
+    pub format: ArchiveFormatRequest,
+    /// Only archive entries whose path matches this glob; a pattern without `/` is matched
+    /// against the entry name
+    pub include: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+pub enum ArchiveFormatRequest {
+    /// Gzip-compressed tarball
+    #[default]
+    #[serde(rename = "tar.gz")]
+    TarGz,
+    #[serde(rename = "zip")]
+    Zip,
+}
+
+impl From<ArchiveFormatRequest> for ArchiveFormat {
+    fn from(format: ArchiveFormatRequest) -> Self {
+        match format {
+            ArchiveFormatRequest::TarGz => Self::TarGz,
+            ArchiveFormatRequest::Zip => Self::Zip,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -67,9 +882,36 @@ pub struct BoxFileResponse {
     pub filename: String,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BoxFileUploadResponse {
+    /// Normalized path of the file inside the box
+    pub path: String,
+    /// Number of bytes written
+    pub size: u64,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeleteBoxFileQuery {
+    /// Delete a directory together with everything in it
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeleteBoxFileResponse {
+    /// Success message
+    pub message: String,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct CleanupResponse {
     /// Success message
     pub message: String,
 }
 
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CancelExecutionResponse {
+    /// Success message
+    pub message: String,
+}
+
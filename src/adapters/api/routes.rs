@@ -1,12 +1,26 @@
 use crate::adapters::api::handlers::{
-    cleanup_box_handler, execute_code_handler, get_box_file_handler, health_handler,
-    list_box_files_handler, list_languages_handler, AppState,
+    cancel_execution_handler, cancel_job_handler, cleanup_box_handler, compile_cache_stats_handler,
+    compile_code_handler, create_session_handler, delete_box_file_handler,
+    download_box_archive_handler, download_box_file_handler, execute_batch_handler,
+    execute_code_handler, execute_in_session_handler, execute_stream_handler, get_box_file_handler,
+    get_job_handler, get_webhook_delivery_handler, health_handler, list_box_files_handler,
+    list_languages_handler, list_webhook_deliveries_handler, submit_job_handler,
+    upload_box_file_handler, AppState,
 };
 use crate::adapters::api::middleware::auth_middleware;
 use crate::adapters::api::models::{
-    BoxFileResponse, BoxFilesResponse, CleanupResponse, ErrorResponse, ExecuteRequest,
-    ExecuteResponse, HealthResponse, LanguagesResponse, MetadataResponse,
+    ArchiveFormatRequest, BatchExecuteRequest, BatchExecuteResponse, BoxEntryKindResponse,
+    BoxEntryResponse, BoxFileResponse, BoxFileUploadResponse, BoxFilesResponse,
+    CancelExecutionResponse, CleanupResponse, ComparisonRequest, CompileCacheStatsResponse,
+    CompileResponse, ContentEncoding, DeleteBoxFileResponse, DeliveryAttemptResponse,
+    DeliveryStatusResponse, DiagnosticResponse, DiagnosticSeverityResponse, ErrorResponse,
+    ExecuteRequest, ExecuteResponse, HealthResponse, JobResponse, JobStatusResponse,
+    LanguagesResponse, MetadataResponse, OutputChunkEvent, OutputEncodingRequest, PriorityRequest,
+    ProgramRequest, ResourceLimitsRequest, SessionResponse, SourceFileRequest, StreamResultEvent,
+    StreamStartedEvent, TestCaseRequest, TestCaseResponse, VerdictResponse,
+    WebhookDeliveriesResponse, WebhookDeliveryResponse,
 };
+use axum::extract::DefaultBodyLimit;
 use axum::{middleware, routing::delete, routing::get, routing::post, Router};
 use std::sync::Arc;
 use utoipa::OpenApi;
@@ -17,21 +31,69 @@ use utoipa_swagger_ui::SwaggerUi;
     paths(
         crate::adapters::api::handlers::health_handler,
         crate::adapters::api::handlers::list_languages_handler,
+        crate::adapters::api::handlers::compile_code_handler,
+        crate::adapters::api::handlers::compile_cache_stats_handler,
         crate::adapters::api::handlers::execute_code_handler,
+        crate::adapters::api::handlers::execute_stream_handler,
+        crate::adapters::api::handlers::execute_batch_handler,
+        crate::adapters::api::handlers::cancel_execution_handler,
+        crate::adapters::api::handlers::submit_job_handler,
+        crate::adapters::api::handlers::get_job_handler,
+        crate::adapters::api::handlers::cancel_job_handler,
+        crate::adapters::api::handlers::list_webhook_deliveries_handler,
+        crate::adapters::api::handlers::get_webhook_delivery_handler,
+        crate::adapters::api::handlers::create_session_handler,
+        crate::adapters::api::handlers::execute_in_session_handler,
         crate::adapters::api::handlers::list_box_files_handler,
         crate::adapters::api::handlers::get_box_file_handler,
+        crate::adapters::api::handlers::upload_box_file_handler,
+        crate::adapters::api::handlers::delete_box_file_handler,
+        crate::adapters::api::handlers::download_box_file_handler,
+        crate::adapters::api::handlers::download_box_archive_handler,
         crate::adapters::api::handlers::cleanup_box_handler,
     ),
     components(
         schemas(
             HealthResponse,
             LanguagesResponse,
+            CompileResponse,
+            DiagnosticResponse,
+            DiagnosticSeverityResponse,
+            CompileCacheStatsResponse,
             ExecuteRequest,
+            SourceFileRequest,
+            ContentEncoding,
+            ResourceLimitsRequest,
             ExecuteResponse,
             MetadataResponse,
+            StreamStartedEvent,
+            OutputChunkEvent,
+            StreamResultEvent,
+            BatchExecuteRequest,
+            TestCaseRequest,
+            ProgramRequest,
+            ComparisonRequest,
+            BatchExecuteResponse,
+            TestCaseResponse,
+            VerdictResponse,
+            PriorityRequest,
+            OutputEncodingRequest,
+            JobResponse,
+            JobStatusResponse,
+            WebhookDeliveriesResponse,
+            WebhookDeliveryResponse,
+            DeliveryStatusResponse,
+            DeliveryAttemptResponse,
+            SessionResponse,
             BoxFilesResponse,
+            BoxEntryResponse,
+            BoxEntryKindResponse,
             BoxFileResponse,
+            BoxFileUploadResponse,
+            DeleteBoxFileResponse,
+            ArchiveFormatRequest,
             CleanupResponse,
+            CancelExecutionResponse,
             ErrorResponse,
         )
     ),
@@ -39,7 +101,9 @@ use utoipa_swagger_ui::SwaggerUi;
         (name = "Health", description = "Health check endpoints"),
         (name = "Languages", description = "Language management endpoints"),
         (name = "Execution", description = "Code execution endpoints"),
-        (name = "Box Management", description = "Sandbox box file management and cleanup endpoints")
+        (name = "Jobs", description = "Asynchronous execution endpoints"),
+        (name = "Webhooks", description = "Completion webhook delivery records"),
+        (name = "Box Management", description = "Sandbox box sessions, file management and cleanup endpoints")
     ),
     info(
         title = "Isolate Sandbox API",
@@ -68,18 +132,38 @@ impl utoipa::Modify for SecurityAddon {
 }
 
 pub fn create_router(state: Arc<AppState>) -> Router {
-    let api_key = state.api_key.clone();
+    let api_keys = state.api_keys.clone();
 
     // Protected routes that require authentication
     let protected_routes = Router::new()
         .route("/languages", get(list_languages_handler))
+        .route("/compile", post(compile_code_handler))
+        .route("/compile/cache", get(compile_cache_stats_handler))
         .route("/execute", post(execute_code_handler))
+        .route("/execute/stream", post(execute_stream_handler))
+        .route("/execute/batch", post(execute_batch_handler))
+        .route("/executions/:execution_id", delete(cancel_execution_handler))
+        .route("/jobs", post(submit_job_handler))
+        .route("/jobs/:job_id", get(get_job_handler).delete(cancel_job_handler))
+        .route("/webhooks/deliveries", get(list_webhook_deliveries_handler))
+        .route("/webhooks/deliveries/:delivery_id", get(get_webhook_delivery_handler))
+        .route("/boxes", post(create_session_handler))
+        .route("/boxes/:box_id/execute", post(execute_in_session_handler))
         .route("/boxes/:box_id/files", get(list_box_files_handler))
-        .route("/boxes/:box_id/files/:filename", get(get_box_file_handler))
+        .route(
+            "/boxes/:box_id/files/*path",
+            get(get_box_file_handler)
+                .put(upload_box_file_handler)
+                .delete(delete_box_file_handler)
+                // Uploads enforce their own size limit while streaming to disk
+                .layer(DefaultBodyLimit::disable()),
+        )
+        .route("/boxes/:box_id/raw/*path", get(download_box_file_handler))
+        .route("/boxes/:box_id/archive", get(download_box_archive_handler))
         .route("/boxes/:box_id", delete(cleanup_box_handler))
         .layer(middleware::from_fn(move |request, next| {
-            let api_key = api_key.clone();
-            async move { auth_middleware(api_key, request, next).await }
+            let api_keys = api_keys.clone();
+            async move { auth_middleware(api_keys, request, next).await }
         }));
 
     Router::new()
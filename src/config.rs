@@ -1,4 +1,26 @@
+use crate::domain::entities::{Priority, ResourceLimits, DEFAULT_TENANT};
+use std::collections::{BTreeSet, HashMap};
 use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Read an environment variable, falling back to `default` when unset or unparsable
+fn parse_env<T: FromStr>(name: &str, default: T) -> T {
+    std::env::var(name)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Read a comma separated list of `name<separator>value` pairs, skipping malformed entries
+fn parse_env_pairs(name: &str, separator: char) -> Vec<(String, String)> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|pair| pair.split_once(separator))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .filter(|(name, value)| !name.is_empty() && !value.is_empty())
+        .collect()
+}
 
 #[derive(Debug, Clone)]
 pub struct SandboxConfig {
@@ -11,20 +33,57 @@ pub struct SandboxConfig {
     pub default_fsize: u32,       // File size limit in KB (0 = unlimited)
     pub default_open_files: u32,  // Open files limit (0 = unlimited)
     pub default_processes: u32,   // Processes limit (0 = unlimited, uses --processes without value)
+    pub max_cg_mem: u32,          // Maximum requestable memory limit in KB (0 = no ceiling)
+    pub max_mem: u32,             // Maximum requestable memory limit in KB (0 = no ceiling)
+    pub max_time: u32,            // Maximum requestable time limit in seconds (0 = no ceiling)
+    pub max_wall_time: u32,       // Maximum requestable wall time in seconds (0 = no ceiling)
+    pub max_extra_time: u32,      // Maximum requestable extra time in seconds (0 = no ceiling)
+    pub max_stack: u32,           // Maximum requestable stack limit in KB (0 = no ceiling)
+    pub max_fsize: u32,           // Maximum requestable file size limit in KB (0 = no ceiling)
+    pub max_open_files: u32,      // Maximum requestable open files limit (0 = no ceiling)
+    pub max_processes: u32,       // Maximum requestable processes limit (0 = no ceiling)
+    pub max_output_size: u64,     // Bytes of stdout/stderr returned per stream (0 = unlimited)
+    pub max_upload_size: u64,     // Bytes accepted per file uploaded into a box (0 = unlimited)
+    pub max_archive_size: u64,    // Bytes of box contents downloaded as one archive (0 = unlimited)
 }
 
 impl Default for SandboxConfig {
     fn default() -> Self {
+        let limits = ResourceLimits {
+            cg_mem: 524288,  // 512MB
+            mem: 512000,     // 500MB
+            time: 30,        // 30 seconds
+            wall_time: 60,   // 60 seconds
+            extra_time: 10,  // 10 seconds
+            stack: 128000,   // 125MB
+            fsize: 102400,   // 100MB
+            open_files: 64,  // 64 files
+            processes: 0,    // Unlimited processes
+        };
+
+        // Like in `from_env`, the maximums are the defaults
         Self {
-            default_cg_mem: 524288,      // 512MB
-            default_mem: 512000,         // 512MB
-            default_time: 30,            // 30 seconds
-            default_wall_time: 60,       // 60 seconds
-            default_extra_time: 10,      // 10 seconds
-            default_stack: 128000,       // 128KB
-            default_fsize: 102400,       // 100MB
-            default_open_files: 64,      // 64 files
-            default_processes: 0,        // Unlimited processes
+            default_cg_mem: limits.cg_mem,
+            default_mem: limits.mem,
+            default_time: limits.time,
+            default_wall_time: limits.wall_time,
+            default_extra_time: limits.extra_time,
+            default_stack: limits.stack,
+            default_fsize: limits.fsize,
+            default_open_files: limits.open_files,
+            default_processes: limits.processes,
+            max_cg_mem: limits.cg_mem,
+            max_mem: limits.mem,
+            max_time: limits.time,
+            max_wall_time: limits.wall_time,
+            max_extra_time: limits.extra_time,
+            max_stack: limits.stack,
+            max_fsize: limits.fsize,
+            max_open_files: limits.open_files,
+            max_processes: limits.processes,
+            max_output_size: 1048576,    // 1MB
+            max_upload_size: 104857600,  // 100MB
+            max_archive_size: 268435456, // 256MB
         }
     }
 }
@@ -33,83 +92,269 @@ impl SandboxConfig {
     pub fn from_env() -> Self {
         let default = Self::default();
 
+        let default_cg_mem = parse_env("ISOLATE_SANDBOX_DEFAULT_CG_MEM", default.default_cg_mem);
+        let default_mem = parse_env("ISOLATE_SANDBOX_DEFAULT_MEM", default.default_mem);
+        let default_time = parse_env("ISOLATE_SANDBOX_DEFAULT_TIME", default.default_time);
+        let default_wall_time =
+            parse_env("ISOLATE_SANDBOX_DEFAULT_WALL_TIME", default.default_wall_time);
+        let default_extra_time =
+            parse_env("ISOLATE_SANDBOX_DEFAULT_EXTRA_TIME", default.default_extra_time);
+        let default_stack = parse_env("ISOLATE_SANDBOX_DEFAULT_STACK", default.default_stack);
+        let default_fsize = parse_env("ISOLATE_SANDBOX_DEFAULT_FSIZE", default.default_fsize);
+        let default_open_files =
+            parse_env("ISOLATE_SANDBOX_DEFAULT_OPEN_FILES", default.default_open_files);
+        let default_processes =
+            parse_env("ISOLATE_SANDBOX_DEFAULT_PROCESSES", default.default_processes);
+
+        // Maximums fall back to the defaults, so clients can only tighten limits unless
+        // the operator explicitly raises the ceiling
         Self {
-            default_cg_mem: std::env::var("ISOLATE_SANDBOX_DEFAULT_CG_MEM")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(default.default_cg_mem),
-            default_mem: std::env::var("ISOLATE_SANDBOX_DEFAULT_MEM")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(default.default_mem),
-            default_time: std::env::var("ISOLATE_SANDBOX_DEFAULT_TIME")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(default.default_time),
-            default_wall_time: std::env::var("ISOLATE_SANDBOX_DEFAULT_WALL_TIME")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(default.default_wall_time),
-            default_extra_time: std::env::var("ISOLATE_SANDBOX_DEFAULT_EXTRA_TIME")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(default.default_extra_time),
-            default_stack: std::env::var("ISOLATE_SANDBOX_DEFAULT_STACK")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(default.default_stack),
-            default_fsize: std::env::var("ISOLATE_SANDBOX_DEFAULT_FSIZE")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(default.default_fsize),
-            default_open_files: std::env::var("ISOLATE_SANDBOX_DEFAULT_OPEN_FILES")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(default.default_open_files),
-            default_processes: std::env::var("ISOLATE_SANDBOX_DEFAULT_PROCESSES")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(default.default_processes),
+            default_cg_mem,
+            default_mem,
+            default_time,
+            default_wall_time,
+            default_extra_time,
+            default_stack,
+            default_fsize,
+            default_open_files,
+            default_processes,
+            max_cg_mem: parse_env("ISOLATE_SANDBOX_MAX_CG_MEM", default_cg_mem),
+            max_mem: parse_env("ISOLATE_SANDBOX_MAX_MEM", default_mem),
+            max_time: parse_env("ISOLATE_SANDBOX_MAX_TIME", default_time),
+            max_wall_time: parse_env("ISOLATE_SANDBOX_MAX_WALL_TIME", default_wall_time),
+            max_extra_time: parse_env("ISOLATE_SANDBOX_MAX_EXTRA_TIME", default_extra_time),
+            max_stack: parse_env("ISOLATE_SANDBOX_MAX_STACK", default_stack),
+            max_fsize: parse_env("ISOLATE_SANDBOX_MAX_FSIZE", default_fsize),
+            max_open_files: parse_env("ISOLATE_SANDBOX_MAX_OPEN_FILES", default_open_files),
+            max_processes: parse_env("ISOLATE_SANDBOX_MAX_PROCESSES", default_processes),
+            max_output_size: parse_env("ISOLATE_SANDBOX_MAX_OUTPUT_SIZE", default.max_output_size),
+            max_upload_size: parse_env("ISOLATE_SANDBOX_MAX_UPLOAD_SIZE", default.max_upload_size),
+            max_archive_size: parse_env(
+                "ISOLATE_SANDBOX_MAX_ARCHIVE_SIZE",
+                default.max_archive_size,
+            ),
+        }
+    }
+
+    /// Limits applied when a request does not override them
+    pub fn default_limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            cg_mem: self.default_cg_mem,
+            mem: self.default_mem,
+            time: self.default_time,
+            wall_time: self.default_wall_time,
+            extra_time: self.default_extra_time,
+            stack: self.default_stack,
+            fsize: self.default_fsize,
+            open_files: self.default_open_files,
+            processes: self.default_processes,
+        }
+    }
+
+    /// Ceiling that request overrides may not exceed
+    pub fn max_limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            cg_mem: self.max_cg_mem,
+            mem: self.max_mem,
+            time: self.max_time,
+            wall_time: self.max_wall_time,
+            extra_time: self.max_extra_time,
+            stack: self.max_stack,
+            fsize: self.max_fsize,
+            open_files: self.max_open_files,
+            processes: self.max_processes,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WebhookConfig {
+    pub secret: Option<String>, // HMAC-SHA256 key for signing payloads (webhooks are disabled when unset)
+    pub max_attempts: u32,      // Delivery attempts before giving up
+    pub retry_delay: u64,       // Delay before the first retry in milliseconds, doubled on each retry
+    pub timeout: u64,           // Request timeout in seconds
+    pub retention: u64,         // Seconds to keep completed delivery records
+    pub allow_private: bool,    // Deliver to loopback, private and link-local addresses
+}
+
+impl std::fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never log the signing secret
+        f.debug_struct("WebhookConfig")
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("max_attempts", &self.max_attempts)
+            .field("retry_delay", &self.retry_delay)
+            .field("timeout", &self.timeout)
+            .field("retention", &self.retention)
+            .field("allow_private", &self.allow_private)
+            .finish()
+    }
+}
+
+impl WebhookConfig {
+    pub fn from_env() -> Self {
+        Self {
+            secret: std::env::var("ISOLATE_SANDBOX_WEBHOOK_SECRET").ok(),
+            max_attempts: parse_env("ISOLATE_SANDBOX_WEBHOOK_MAX_ATTEMPTS", 5),
+            retry_delay: parse_env("ISOLATE_SANDBOX_WEBHOOK_RETRY_DELAY", 1000),
+            timeout: parse_env("ISOLATE_SANDBOX_WEBHOOK_TIMEOUT", 10),
+            retention: parse_env("ISOLATE_SANDBOX_WEBHOOK_RETENTION", 3600),
+            allow_private: parse_env("ISOLATE_SANDBOX_WEBHOOK_ALLOW_PRIVATE", false),
         }
     }
 }
 
 #[derive(Debug, Clone)]
+pub struct CompileConfig {
+    pub box_count: u32,       // Boxes reserved for compiling, numbered after the execution boxes
+    pub cg_mem: u32,          // Memory limit in KB (0 = unlimited)
+    pub time: u32,            // Time limit in seconds (0 = unlimited)
+    pub wall_time: u32,       // Wall time limit in seconds (0 = unlimited)
+    pub processes: u32,       // Processes limit (0 = unlimited)
+    pub fsize: u32,           // File size limit in KB (0 = unlimited)
+    pub max_output_size: u64, // Bytes of compiler output kept for error messages
+}
+
+impl CompileConfig {
+    pub fn from_env() -> Self {
+        Self {
+            box_count: parse_env("ISOLATE_SANDBOX_COMPILE_BOX_COUNT", 4),
+            cg_mem: parse_env("ISOLATE_SANDBOX_COMPILE_CG_MEM", 524288),
+            time: parse_env("ISOLATE_SANDBOX_COMPILE_TIME", 10),
+            wall_time: parse_env("ISOLATE_SANDBOX_COMPILE_WALL_TIME", 20),
+            processes: parse_env("ISOLATE_SANDBOX_COMPILE_PROCESSES", 64),
+            fsize: parse_env("ISOLATE_SANDBOX_COMPILE_FSIZE", 102400),
+            max_output_size: parse_env("ISOLATE_SANDBOX_COMPILE_MAX_OUTPUT_SIZE", 65536),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompileCacheConfig {
+    pub dir: PathBuf,  // Directory in which a private cache directory is created per run
+    pub max_size: u64, // Total bytes of cached artifacts (0 = cache disabled)
+    pub max_age: u64,  // Seconds an artifact is reused after compiling it
+}
+
+impl CompileCacheConfig {
+    pub fn from_env() -> Self {
+        Self {
+            dir: std::env::var("ISOLATE_SANDBOX_COMPILE_CACHE_DIR")
+                .ok()
+                .map(PathBuf::from)
+                .unwrap_or_else(|| std::env::temp_dir().join("isolate-sandbox-compile-cache")),
+            max_size: parse_env("ISOLATE_SANDBOX_COMPILE_CACHE_SIZE", 536870912),
+            max_age: parse_env("ISOLATE_SANDBOX_COMPILE_CACHE_MAX_AGE", 86400),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct Config {
     pub port: u16,
     pub config_dir: PathBuf,
     pub box_pool_size: u32,
-    pub api_key: Option<String>,
+    pub box_queue_length: usize, // Requests that may wait for a box at once (0 = fail immediately)
+    pub box_max_wait: u64,       // Seconds a request may wait for a box
+    pub api_keys: HashMap<String, String>, // API key -> tenant (empty = authentication disabled)
+    pub tenant_weights: HashMap<String, u32>, // Relative share of boxes per tenant (default 1)
+    pub tenant_max_priorities: HashMap<String, Priority>, // Highest priority per tenant (default any)
+    pub job_retention: u64, // Seconds to keep completed jobs before they are removed
+    pub session_idle_timeout: u64, // Seconds a session box may go unused before it is released (0 = never)
+    pub session_max_per_tenant: usize, // Sessions a tenant may have open at once (0 = unlimited)
     pub sandbox: SandboxConfig,
+    pub webhook: WebhookConfig,
+    pub compile: CompileConfig,
+    pub compile_cache: CompileCacheConfig,
+}
+
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never log the API keys, only the tenants they belong to
+        let tenants: BTreeSet<&String> = self.api_keys.values().collect();
+        f.debug_struct("Config")
+            .field("port", &self.port)
+            .field("config_dir", &self.config_dir)
+            .field("box_pool_size", &self.box_pool_size)
+            .field("box_queue_length", &self.box_queue_length)
+            .field("box_max_wait", &self.box_max_wait)
+            .field("api_keys", &format!("<{} redacted>", self.api_keys.len()))
+            .field("tenants", &tenants)
+            .field("tenant_weights", &self.tenant_weights)
+            .field("tenant_max_priorities", &self.tenant_max_priorities)
+            .field("job_retention", &self.job_retention)
+            .field("session_idle_timeout", &self.session_idle_timeout)
+            .field("session_max_per_tenant", &self.session_max_per_tenant)
+            .field("sandbox", &self.sandbox)
+            .field("webhook", &self.webhook)
+            .field("compile", &self.compile)
+            .field("compile_cache", &self.compile_cache)
+            .finish()
+    }
 }
 
 impl Config {
     pub fn from_env() -> Self {
-        let port = std::env::var("ISOLATE_SANDBOX_PORT")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(3000);
+        let port = parse_env("ISOLATE_SANDBOX_PORT", 3000);
 
         let config_dir = std::env::var("ISOLATE_SANDBOX_CONFIG_DIR")
             .ok()
             .map(PathBuf::from)
             .unwrap_or_else(|| PathBuf::from("./config"));
 
-        let box_pool_size = std::env::var("ISOLATE_SANDBOX_BOX_POOL_SIZE")
-            .ok()
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(10);
+        let box_pool_size = parse_env("ISOLATE_SANDBOX_BOX_POOL_SIZE", 10);
+        let box_queue_length = parse_env("ISOLATE_SANDBOX_BOX_QUEUE_LENGTH", 100);
+        let box_max_wait = parse_env("ISOLATE_SANDBOX_BOX_MAX_WAIT", 30);
 
-        let api_key = std::env::var("ISOLATE_SANDBOX_API_KEY").ok();
+        // The single API key belongs to the default tenant, further keys are named per tenant
+        let mut api_keys: HashMap<String, String> =
+            parse_env_pairs("ISOLATE_SANDBOX_API_KEYS", ':')
+                .into_iter()
+                .map(|(tenant, key)| (key, tenant))
+                .collect();
+        if let Ok(api_key) = std::env::var("ISOLATE_SANDBOX_API_KEY") {
+            api_keys.insert(api_key, DEFAULT_TENANT.to_string());
+        }
+
+        let tenant_weights = parse_env_pairs("ISOLATE_SANDBOX_TENANT_WEIGHTS", '=')
+            .into_iter()
+            .filter_map(|(tenant, weight)| Some((tenant, weight.parse().ok()?)))
+            .collect();
+
+        let tenant_max_priorities = parse_env_pairs("ISOLATE_SANDBOX_TENANT_MAX_PRIORITIES", '=')
+            .into_iter()
+            .filter_map(|(tenant, priority)| Some((tenant, priority.parse().ok()?)))
+            .collect();
+
+        let job_retention = parse_env("ISOLATE_SANDBOX_JOB_RETENTION", 3600);
+
+        let session_idle_timeout = parse_env("ISOLATE_SANDBOX_SESSION_IDLE_TIMEOUT", 600);
+        let session_max_per_tenant = parse_env("ISOLATE_SANDBOX_SESSION_MAX_PER_TENANT", 4);
 
         let sandbox = SandboxConfig::from_env();
 
+        let webhook = WebhookConfig::from_env();
+
+        let compile = CompileConfig::from_env();
+
+        let compile_cache = CompileCacheConfig::from_env();
+
         Self {
             port,
             config_dir,
             box_pool_size,
-            api_key,
+            box_queue_length,
+            box_max_wait,
+            api_keys,
+            tenant_weights,
+            tenant_max_priorities,
+            job_retention,
+            session_idle_timeout,
+            session_max_per_tenant,
             sandbox,
+            webhook,
+            compile,
+            compile_cache,
         }
     }
 }
@@ -0,0 +1,147 @@
+use crate::domain::entities::{Diagnostic, DiagnosticSeverity};
+use regex::Regex;
+
+/// Extract the diagnostics of a compiler run from its output, using the parser of the
+/// language. Languages without a parser report no diagnostics.
+pub fn parse_diagnostics(language: &str, output: &str) -> Vec<Diagnostic> {
+    match language {
+        "python" => parse_python(output),
+        _ => Vec::new(),
+    }
+}
+
+/// Parse `python -m compileall` output.
+///
+/// Errors are printed as a traceback without frames:
+///
+/// ```text
+///   File "*******.py", line 3
+///     print("a"
+///          ^
+/// SyntaxError: '(' was never closed
+/// ```
+///
+/// and warnings as `*******.py:1: SyntaxWarning: message`.
+fn parse_python(output: &str) -> Vec<Diagnostic> {
+    let location_regex = Regex::new(r#"^\s*File "(.+)", line (\d+)"#).unwrap();
+    let caret_regex = Regex::new(r"^\s*\^[\^~]*\s*$").unwrap();
+    let error_regex = Regex::new(r"^(\w*(?:Error|Exception)): (.*)$").unwrap();
+    let warning_regex = Regex::new(r"^(.+?):(\d+): (\w*Warning): (.*)$").unwrap();
+
+    let mut diagnostics = Vec::new();
+    let mut location: Option<(String, u32, Option<u32>)> = None;
+    let mut previous_line = "";
+
+    for line in output.lines() {
+        if let Some(cap) = warning_regex.captures(line) {
+            diagnostics.push(Diagnostic {
+                file: cap[1].to_string(),
+                line: cap[2].parse().unwrap_or(0),
+                column: None,
+                severity: DiagnosticSeverity::Warning,
+                message: format!("{}: {}", &cap[3], &cap[4]),
+            });
+        } else if let Some(cap) = location_regex.captures(line) {
+            location = Some((cap[1].to_string(), cap[2].parse().unwrap_or(0), None));
+        } else if caret_regex.is_match(line) {
+            // Python strips the indentation of the offending line, the caret is aligned with
+            // the displayed code
+            if let Some((_, _, column)) = location.as_mut() {
+                let caret = line.find('^').unwrap_or(0);
+                let code_start = previous_line.len() - previous_line.trim_start().len();
+                *column = Some((caret.saturating_sub(code_start) + 1) as u32);
+            }
+        } else if let Some(cap) = error_regex.captures(line) {
+            if let Some((file, line, column)) = location.take() {
+                diagnostics.push(Diagnostic {
+                    file,
+                    line,
+                    column,
+                    severity: DiagnosticSeverity::Error,
+                    message: format!("{}: {}", &cap[1], &cap[2]),
+                });
+            }
+        }
+        previous_line = line;
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Summary = (String, u32, Option<u32>, DiagnosticSeverity, String);
+
+    fn summarize(language: &str, output: &str) -> Vec<Summary> {
+        parse_diagnostics(language, output)
+            .into_iter()
+            .map(|d| (d.file, d.line, d.column, d.severity, d.message))
+            .collect()
+    }
+
+    fn python(severity: DiagnosticSeverity, line: u32, col: Option<u32>, msg: &str) -> Summary {
+        let file = "*******.py".to_string();
+        (file, line, col, severity, msg.to_string())
+    }
+
+    fn error(line: u32, column: Option<u32>, message: &str) -> Summary {
+        python(DiagnosticSeverity::Error, line, column, message)
+    }
+
+    #[test]
+    fn python_syntax_error_with_caret() {
+        let output = "*** Error compiling '*******.py'...\n  File \"*******.py\", line 3\n    \
+                      print(\"a\"\n         ^\nSyntaxError: '(' was never closed\n";
+
+        assert_eq!(
+            summarize("python", output),
+            vec![error(3, Some(6), "SyntaxError: '(' was never closed")]
+        );
+    }
+
+    #[test]
+    fn python_error_without_caret_has_no_column() {
+        let output = "  File \"*******.py\", line 7\nIndentationError: unexpected indent\n";
+
+        assert_eq!(
+            summarize("python", output),
+            vec![error(7, None, "IndentationError: unexpected indent")]
+        );
+    }
+
+    #[test]
+    fn python_warnings_and_errors_in_order() {
+        let output = "*******.py:1: SyntaxWarning: \"is\" with a literal\n  File \"*******.py\", \
+                      line 2\n    x = )\n        ^^\nSyntaxError: unmatched ')'\n";
+
+        assert_eq!(
+            summarize("python", output),
+            vec![
+                python(
+                    DiagnosticSeverity::Warning,
+                    1,
+                    None,
+                    "SyntaxWarning: \"is\" with a literal"
+                ),
+                error(2, Some(5), "SyntaxError: unmatched ')'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn python_errors_without_location_are_ignored() {
+        let output = "Listing '.'...\nValueError: source code string cannot contain null bytes\n";
+
+        assert!(summarize("python", output).is_empty());
+    }
+
+    #[test]
+    fn languages_without_parser_report_nothing() {
+        let output = "main.c:1:1: error: unknown type name 'x'\n";
+
+        assert!(summarize("c", output).is_empty());
+        assert!(summarize("python", "").is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoxEntryKind {
+    File,
+    Directory,
+    Symlink,
+    /// Sockets, pipes and device files
+    Other,
+}
+
+/// A file, directory or symlink inside a box
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoxEntry {
+    /// Path relative to the box root
+    pub path: String,
+    pub kind: BoxEntryKind,
+    /// Size in bytes (for symlinks, the length of the target path)
+    pub size: u64,
+    pub modified_at: DateTime<Utc>,
+    /// SHA-256 of the content of regular files, when requested
+    pub sha256: Option<String>,
+}
+
+/// Which entries of a box to list
+#[derive(Debug, Clone, Default)]
+pub struct BoxListingQuery {
+    /// Include the contents of subdirectories
+    pub recursive: bool,
+    /// Glob the entry paths must match
+    pub pattern: Option<String>,
+    pub offset: usize,
+    /// Maximum number of entries to return (`None` = all)
+    pub limit: Option<usize>,
+    /// Compute the SHA-256 of the listed regular files
+    pub sha256: bool,
+}
+
+/// A page of the entries of a box, sorted by path
+#[derive(Debug, Clone)]
+pub struct BoxListing {
+    pub entries: Vec<BoxEntry>,
+    /// Number of matching entries across all pages
+    pub total: usize,
+}
+
+/// Format of an archive of box contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveFormat {
+    /// Gzip-compressed tarball
+    #[serde(rename = "tar.gz")]
+    TarGz,
+    #[serde(rename = "zip")]
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+}
+
+/// A compiler message located in a source file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// File as named in the compiler output, with submission paths masked
+    pub file: String,
+    pub line: u32,
+    /// 1-based column, when the compiler points at one
+    pub column: Option<u32>,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+/// Result of compiling a program without running it
+#[derive(Debug, Clone)]
+pub struct CompileReport {
+    pub success: bool,
+    pub exit_code: i32,
+    /// Compiler messages with the paths of the submitted sources masked
+    pub output: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CompileReport {
+    pub fn warning_count(&self) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.severity == DiagnosticSeverity::Warning)
+            .count()
+    }
+}
@@ -1,19 +1,82 @@
+use crate::domain::entities::{ResourceLimitOverrides, SchedulingContext};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
+/// Source code of a program in one of the registered languages
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExecutionRequest {
+pub struct ProgramSource {
     pub language: String,
     pub code: String,
+    pub files: Vec<SourceFile>,
+    pub entry_point: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionRequest {
+    pub program: ProgramSource,
+    pub stdin: Option<Vec<u8>>,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub limits: ResourceLimitOverrides,
+    pub scheduling: SchedulingContext,
+    pub output_encoding: OutputEncoding,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceFile {
+    pub path: String,
+    pub content: Vec<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionResult {
+    /// Standard output, encoded as given by `stdout_encoding`
     pub stdout: String,
+    /// Standard error, encoded as given by `stderr_encoding`
     pub stderr: String,
+    pub stdout_encoding: OutputEncoding,
+    pub stderr_encoding: OutputEncoding,
+    pub stdout_size: OutputSize,
+    pub stderr_size: OutputSize,
     pub metadata: ExecutionMetadata,
     pub box_id: u32,
 }
 
+/// How program output is encoded into text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputEncoding {
+    /// UTF-8 text, with invalid sequences replaced
+    #[default]
+    Utf8,
+    /// Base64 of the raw bytes
+    Base64,
+    /// UTF-8 when the output is valid UTF-8, base64 otherwise
+    Auto,
+}
+
+/// Size of a captured output stream, and whether it was cut off at the server's limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputSize {
+    /// Total number of bytes the program wrote, including any part that was not kept
+    pub total: u64,
+    pub truncated: bool,
+}
+
+/// Standard stream a piece of program output was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Program output forwarded while the program is still running
+#[derive(Debug, Clone)]
+pub struct OutputChunk {
+    pub stream: OutputStream,
+    pub data: Vec<u8>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionMetadata {
     pub time: f64,
@@ -21,6 +84,8 @@ pub struct ExecutionMetadata {
     pub memory: u64,
     pub exit_code: i32,
     pub status: String,
+    /// Human readable explanation of the status reported by the sandbox
+    pub message: Option<String>,
 }
 
 impl ExecutionMetadata {
@@ -31,8 +96,14 @@ impl ExecutionMetadata {
             memory: 0,
             exit_code: 0,
             status: "OK".to_string(),
+            message: None,
         }
     }
+
+    /// Whether the program exited normally with a zero exit code
+    pub fn is_success(&self) -> bool {
+        self.status == "OK" && self.exit_code == 0
+    }
 }
 
 impl Default for ExecutionMetadata {
@@ -0,0 +1,61 @@
+use crate::domain::entities::ExecutionResult;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Lifecycle state of an asynchronous execution job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Compiling,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Whether the job has stopped and will not change anymore
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Finished | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+/// An execution submitted to run in the background
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    /// Tenant that submitted the job; other tenants cannot see or cancel it
+    pub tenant: String,
+    pub status: JobStatus,
+    pub result: Option<ExecutionResult>,
+    pub error: Option<String>,
+    pub webhook_delivery_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Job {
+    pub fn new(id: String, tenant: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            tenant,
+            status: JobStatus::Queued,
+            result: None,
+            error: None,
+            webhook_delivery_id: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Move the job to `status`, recording the time of the change
+    pub fn set_status(&mut self, status: JobStatus) {
+        self.status = status;
+        self.updated_at = Utc::now();
+    }
+}
@@ -0,0 +1,163 @@
+use crate::domain::error::{DomainError, DomainResult};
+use serde::{Deserialize, Serialize};
+
+/// Resource limits applied to a single sandbox run (0 = unlimited)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    pub cg_mem: u32,     // Memory limit in KB
+    pub mem: u32,        // Memory limit in KB
+    pub time: u32,       // Time limit in seconds
+    pub wall_time: u32,  // Wall time limit in seconds
+    pub extra_time: u32, // Extra time in seconds
+    pub stack: u32,      // Stack limit in KB
+    pub fsize: u32,      // File size limit in KB
+    pub open_files: u32, // Open files limit
+    pub processes: u32,  // Processes limit
+}
+
+/// Per-request overrides of the default resource limits
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceLimitOverrides {
+    pub cg_mem: Option<u32>,
+    pub mem: Option<u32>,
+    pub time: Option<u32>,
+    pub wall_time: Option<u32>,
+    pub extra_time: Option<u32>,
+    pub stack: Option<u32>,
+    pub fsize: Option<u32>,
+    pub open_files: Option<u32>,
+    pub processes: Option<u32>,
+}
+
+impl ResourceLimits {
+    /// Apply request overrides on top of these defaults, enforcing the given ceiling.
+    ///
+    /// Defaults above the ceiling are clamped, while overrides above the ceiling are rejected.
+    pub fn resolve(
+        &self,
+        ceiling: &ResourceLimits,
+        overrides: &ResourceLimitOverrides,
+    ) -> DomainResult<ResourceLimits> {
+        Ok(ResourceLimits {
+            cg_mem: resolve_limit("cg_mem", self.cg_mem, ceiling.cg_mem, overrides.cg_mem)?,
+            mem: resolve_limit("mem", self.mem, ceiling.mem, overrides.mem)?,
+            time: resolve_limit("time", self.time, ceiling.time, overrides.time)?,
+            wall_time: resolve_limit(
+                "wall_time",
+                self.wall_time,
+                ceiling.wall_time,
+                overrides.wall_time,
+            )?,
+            extra_time: resolve_limit(
+                "extra_time",
+                self.extra_time,
+                ceiling.extra_time,
+                overrides.extra_time,
+            )?,
+            stack: resolve_limit("stack", self.stack, ceiling.stack, overrides.stack)?,
+            fsize: resolve_limit("fsize", self.fsize, ceiling.fsize, overrides.fsize)?,
+            open_files: resolve_limit(
+                "open_files",
+                self.open_files,
+                ceiling.open_files,
+                overrides.open_files,
+            )?,
+            processes: resolve_limit(
+                "processes",
+                self.processes,
+                ceiling.processes,
+                overrides.processes,
+            )?,
+        })
+    }
+}
+
+fn resolve_limit(name: &str, default: u32, max: u32, requested: Option<u32>) -> DomainResult<u32> {
+    match requested {
+        // No ceiling configured, anything goes (including unlimited)
+        Some(value) if max == 0 => Ok(value),
+        Some(value) if value == 0 || value > max => Err(DomainError::InvalidRequest(format!(
+            "limits.{} must be between 1 and {}",
+            name, max
+        ))),
+        Some(value) => Ok(value),
+        None if max == 0 => Ok(default),
+        None if default == 0 => Ok(max),
+        None => Ok(default.min(max)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(value: u32) -> ResourceLimits {
+        ResourceLimits {
+            cg_mem: value,
+            mem: value,
+            time: value,
+            wall_time: value,
+            extra_time: value,
+            stack: value,
+            fsize: value,
+            open_files: value,
+            processes: value,
+        }
+    }
+
+    #[test]
+    fn resolve_limit_applies_defaults_and_ceiling() {
+        // (default, max, requested, resolved or None when rejected)
+        let cases = [
+            // No override
+            (5, 10, None, Some(5)),
+            // Configured default above the max is clamped
+            (20, 10, None, Some(10)),
+            // Unlimited default is capped by the max
+            (0, 10, None, Some(10)),
+            (5, 10, Some(10), Some(10)),
+            (5, 10, Some(1), Some(1)),
+            // Override above the max
+            (5, 10, Some(11), None),
+            // Override of 0 (unlimited) while a max is configured
+            (5, 10, Some(0), None),
+            // max == 0 means no ceiling
+            (5, 0, None, Some(5)),
+            (0, 0, None, Some(0)),
+            (5, 0, Some(1000), Some(1000)),
+            (5, 0, Some(0), Some(0)),
+        ];
+
+        for (default, max, requested, resolved) in cases {
+            let result = resolve_limit("time", default, max, requested).ok();
+            assert_eq!(result, resolved, "{:?}", (default, max, requested));
+        }
+    }
+
+    #[test]
+    fn resolve_names_the_rejected_limit() {
+        let overrides = ResourceLimitOverrides {
+            wall_time: Some(11),
+            ..Default::default()
+        };
+
+        let error = limits(5).resolve(&limits(10), &overrides).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid request: limits.wall_time must be between 1 and 10"
+        );
+    }
+
+    #[test]
+    fn resolve_overrides_only_requested_limits() {
+        let overrides = ResourceLimitOverrides {
+            time: Some(8),
+            ..Default::default()
+        };
+
+        let resolved = limits(5).resolve(&limits(10), &overrides).unwrap();
+        assert_eq!(resolved.time, 8);
+        assert_eq!(resolved.wall_time, 5);
+        assert_eq!(resolved.processes, 5);
+    }
+}
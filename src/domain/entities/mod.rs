@@ -1,6 +1,24 @@
+pub mod box_entry;
+pub mod diagnostic;
 pub mod execution;
+pub mod job;
 pub mod language;
+pub mod limits;
+pub mod scheduling;
+pub mod test_case;
+pub mod verdict;
+pub mod webhook;
 
-pub use execution::{ExecutionMetadata, ExecutionRequest, ExecutionResult};
+pub use box_entry::{ArchiveFormat, BoxEntry, BoxEntryKind, BoxListing, BoxListingQuery};
+pub use diagnostic::{CompileReport, Diagnostic, DiagnosticSeverity};
+pub use execution::{
+    ExecutionMetadata, ExecutionRequest, ExecutionResult, OutputChunk, OutputEncoding, OutputSize,
+    OutputStream, ProgramSource, SourceFile,
+};
+pub use job::{Job, JobStatus};
 pub use language::Language;
-
+pub use limits::{ResourceLimitOverrides, ResourceLimits};
+pub use scheduling::{Priority, SchedulingContext, DEFAULT_TENANT};
+pub use test_case::{TestCase, TestCaseBatch, TestCaseResult};
+pub use verdict::{OutputComparison, Verdict};
+pub use webhook::{DeliveryAttempt, DeliveryStatus, WebhookDelivery};
@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+/// Scheduling class of a request. When boxes are scarce, waiting requests of a higher
+/// class are always served before those of a lower one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Bulk work such as rejudges
+    Batch,
+    #[default]
+    Normal,
+    /// Latency sensitive requests such as runs from an editor
+    Interactive,
+}
+
+impl std::str::FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "batch" => Ok(Self::Batch),
+            "normal" => Ok(Self::Normal),
+            "interactive" => Ok(Self::Interactive),
+            _ => Err(format!("Unknown priority: {}", s)),
+        }
+    }
+}
+
+/// Who is asking for a box and how urgently
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulingContext {
+    /// Tenant whose share of the boxes the request counts against
+    pub tenant: String,
+    pub priority: Priority,
+}
+
+impl Default for SchedulingContext {
+    fn default() -> Self {
+        Self {
+            tenant: DEFAULT_TENANT.to_string(),
+            priority: Priority::default(),
+        }
+    }
+}
+
+/// Tenant of requests that do not identify one
+pub const DEFAULT_TENANT: &str = "default";
@@ -0,0 +1,59 @@
+use crate::domain::entities::{
+    ExecutionMetadata, OutputComparison, OutputEncoding, OutputSize, ProgramSource,
+    ResourceLimitOverrides, SchedulingContext, Verdict,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A single input to run a compiled program against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCase {
+    pub stdin: Option<Vec<u8>>,
+    pub args: Vec<String>,
+    pub limits: ResourceLimitOverrides,
+    pub expected_output: Option<String>,
+    pub comparison: OutputComparison,
+}
+
+/// A program compiled once and run against many test cases
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCaseBatch {
+    pub program: ProgramSource,
+    pub env: BTreeMap<String, String>,
+    pub test_cases: Vec<TestCase>,
+    pub stop_on_failure: bool,
+    /// Program deciding the verdict instead of the output comparison
+    pub checker: Option<ProgramSource>,
+    /// Program talking to the submission over stdin/stdout and deciding the verdict
+    pub interactor: Option<ProgramSource>,
+    pub interactor_limits: ResourceLimitOverrides,
+    pub scheduling: SchedulingContext,
+    pub output_encoding: OutputEncoding,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCaseResult {
+    /// Standard output, encoded as given by `stdout_encoding`
+    pub stdout: String,
+    /// Standard error, encoded as given by `stderr_encoding`
+    pub stderr: String,
+    pub stdout_encoding: OutputEncoding,
+    pub stderr_encoding: OutputEncoding,
+    pub stdout_size: OutputSize,
+    pub stderr_size: OutputSize,
+    pub metadata: ExecutionMetadata,
+    pub verdict: Option<Verdict>,
+    pub score: Option<f64>,
+    pub checker_message: Option<String>,
+}
+
+impl TestCaseResult {
+    /// Whether the run failed or produced a wrong answer
+    pub fn is_failure(&self) -> bool {
+        !self.metadata.is_success()
+            || matches!(
+                self.verdict,
+                Some(Verdict::WrongAnswer | Verdict::JudgeError | Verdict::OutputLimitExceeded)
+            )
+    }
+}
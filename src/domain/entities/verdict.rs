@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+
+/// Judging outcome of a test case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verdict {
+    Accepted,
+    WrongAnswer,
+    /// The checker itself crashed or reported an unexpected exit code
+    JudgeError,
+    /// The output was cut off at the server's output limit, so it could not be judged
+    OutputLimitExceeded,
+}
+
+impl Verdict {
+    /// Map a checker exit code to a verdict (0 = accepted, 1 = wrong answer)
+    pub fn from_checker_exit_code(exit_code: i32) -> Self {
+        match exit_code {
+            0 => Self::Accepted,
+            1 => Self::WrongAnswer,
+            _ => Self::JudgeError,
+        }
+    }
+
+    /// Score implied by the verdict when the checker does not report one
+    pub fn default_score(&self) -> f64 {
+        match self {
+            Self::Accepted => 1.0,
+            Self::WrongAnswer | Self::JudgeError | Self::OutputLimitExceeded => 0.0,
+        }
+    }
+}
+
+/// How program output is compared against the expected output
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub enum OutputComparison {
+    /// Byte-for-byte equality
+    #[default]
+    Exact,
+    /// Equality after trimming trailing whitespace on each line and trailing blank lines
+    IgnoreTrailingWhitespace,
+    /// Equality of whitespace separated tokens
+    Tokens,
+    /// Token equality where numeric tokens may differ within an absolute or relative epsilon
+    Float { abs_epsilon: f64, rel_epsilon: f64 },
+}
+
+impl OutputComparison {
+    pub fn verdict(&self, expected: &str, actual: &[u8]) -> Verdict {
+        if self.matches(expected, actual) {
+            Verdict::Accepted
+        } else {
+            Verdict::WrongAnswer
+        }
+    }
+
+    /// Compare the raw program output against the expected output.
+    ///
+    /// Output that is not valid UTF-8 can only match byte-for-byte.
+    pub fn matches(&self, expected: &str, actual: &[u8]) -> bool {
+        let actual = match (self, std::str::from_utf8(actual)) {
+            (Self::Exact, _) => return expected.as_bytes() == actual,
+            (_, Ok(actual)) => actual,
+            (_, Err(_)) => return false,
+        };
+
+        match self {
+            Self::Exact => expected == actual,
+            Self::IgnoreTrailingWhitespace => trimmed_lines(expected) == trimmed_lines(actual),
+            Self::Tokens => expected.split_whitespace().eq(actual.split_whitespace()),
+            Self::Float {
+                abs_epsilon,
+                rel_epsilon,
+            } => {
+                let mut expected_tokens = expected.split_whitespace();
+                let mut actual_tokens = actual.split_whitespace();
+                loop {
+                    match (expected_tokens.next(), actual_tokens.next()) {
+                        (None, None) => return true,
+                        (Some(e), Some(a)) if tokens_match(e, a, *abs_epsilon, *rel_epsilon) => {}
+                        _ => return false,
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn trimmed_lines(output: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = output.lines().map(str::trim_end).collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+fn tokens_match(expected: &str, actual: &str, abs_epsilon: f64, rel_epsilon: f64) -> bool {
+    if expected == actual {
+        return true;
+    }
+
+    match (expected.parse::<f64>(), actual.parse::<f64>()) {
+        (Ok(e), Ok(a)) if e.is_finite() && a.is_finite() => {
+            let diff = (e - a).abs();
+            diff <= abs_epsilon || diff <= rel_epsilon * e.abs()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comparisons_use_raw_output() {
+        let float = OutputComparison::Float {
+            abs_epsilon: 1e-6,
+            rel_epsilon: 0.0,
+        };
+        let trailing = OutputComparison::IgnoreTrailingWhitespace;
+        let cases = [
+            (OutputComparison::Exact, "1 2\n", &b"1 2\n"[..], true),
+            (OutputComparison::Exact, "1 2\n", b"1 2", false),
+            (OutputComparison::Exact, "\u{fffd}", b"\xff", false),
+            (OutputComparison::Tokens, "1 2", b" 1\n2\n", true),
+            (OutputComparison::Tokens, "\u{fffd}", b"\xff", false),
+            (trailing.clone(), "a\nb", b"a  \nb\n\n", true),
+            (trailing, "a\nb", b" a\nb", false),
+            (float.clone(), "0.5 x", b"0.5000001 x", true),
+            (float, "0.5", b"0.51", false),
+        ];
+
+        for (comparison, expected, actual, matches) in cases {
+            assert_eq!(
+                comparison.matches(expected, actual),
+                matches,
+                "{:?} {:?} {:?}",
+                comparison,
+                expected,
+                actual
+            );
+        }
+    }
+}
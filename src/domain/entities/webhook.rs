@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Delivery state of a completion webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Waiting for the execution to finish, or retrying
+    Pending,
+    /// Acknowledged by the receiver with a 2xx response
+    Delivered,
+    /// Every attempt failed
+    Failed,
+}
+
+/// A single attempt to POST a webhook payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    pub attempted_at: DateTime<Utc>,
+    /// HTTP status returned by the receiver, if a response was received
+    pub status_code: Option<u16>,
+    /// Transport error or non-2xx status description
+    pub error: Option<String>,
+}
+
+/// Completion notification of an execution or job sent to a callback URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: String,
+    /// Tenant whose execution the delivery reports; other tenants cannot see it
+    pub tenant: String,
+    pub callback_url: String,
+    pub job_id: Option<String>,
+    pub status: DeliveryStatus,
+    pub attempts: Vec<DeliveryAttempt>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl WebhookDelivery {
+    pub fn new(id: String, tenant: String, callback_url: String, job_id: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            tenant,
+            callback_url,
+            job_id,
+            status: DeliveryStatus::Pending,
+            attempts: Vec::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Record a delivery attempt
+    pub fn add_attempt(&mut self, attempt: DeliveryAttempt) {
+        self.updated_at = attempt.attempted_at;
+        self.attempts.push(attempt);
+    }
+
+    pub fn set_status(&mut self, status: DeliveryStatus) {
+        self.status = status;
+        self.updated_at = Utc::now();
+    }
+}
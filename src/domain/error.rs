@@ -5,20 +5,47 @@ pub enum DomainError {
     #[error("Language not found: {0}")]
     LanguageNotFound(String),
 
+    #[error("Job not found: {0}")]
+    JobNotFound(String),
+
+    #[error("Webhook delivery not found: {0}")]
+    DeliveryNotFound(String),
+
     #[error("Compilation failed: {0}")]
     CompilationFailed(String),
 
-    #[error("Execution failed: {0}")]
-    ExecutionFailed(String),
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
 
     #[error("Sandbox error: {0}")]
     SandboxError(String),
 
-    #[error("Box pool exhausted")]
-    BoxPoolExhausted,
+    #[error("Session not found: {0}")]
+    SessionNotFound(u32),
+
+    #[error("Too many open sessions, at most {0} per tenant")]
+    TooManySessions(usize),
+
+    #[error("Box not initialized: {0}")]
+    BoxNotFound(u32),
 
-    #[error("Invalid configuration: {0}")]
-    InvalidConfiguration(String),
+    #[error("File not found: {0}")]
+    FileNotFound(String),
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Execution not found: {0}")]
+    ExecutionNotFound(String),
+
+    #[error("Execution cancelled")]
+    Cancelled,
+
+    #[error("Box pool exhausted")]
+    BoxPoolExhausted {
+        /// Suggested number of seconds to wait before retrying
+        retry_after: u64,
+    },
 
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
@@ -1,5 +1,7 @@
+pub mod diagnostics;
 pub mod entities;
 pub mod error;
+pub mod paths;
 pub mod repositories;
 pub mod services;
 
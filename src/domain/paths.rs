@@ -0,0 +1,215 @@
+use crate::domain::error::{DomainError, DomainResult};
+use regex::Regex;
+use std::path::{Component, PathBuf};
+
+/// Validate a client supplied path and normalize it to a relative path.
+///
+/// Rejects empty, absolute and parent-escaping paths so the result can be safely
+/// joined onto a working directory or box root, and paths with NUL bytes, which cannot be
+/// passed to commands.
+pub fn normalize_relative_path(path: &str) -> DomainResult<PathBuf> {
+    if path.contains('\0') {
+        return Err(DomainError::InvalidRequest(format!(
+            "Invalid path '{}': must not contain NUL bytes",
+            path.escape_default()
+        )));
+    }
+
+    let mut normalized = PathBuf::new();
+
+    for component in std::path::Path::new(path).components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(DomainError::InvalidRequest(format!(
+                    "Invalid path '{}': must be relative and stay within the working directory",
+                    path
+                )));
+            }
+        }
+    }
+
+    if normalized.as_os_str().is_empty() {
+        return Err(DomainError::InvalidRequest(format!(
+            "Invalid path '{}': must not be empty",
+            path
+        )));
+    }
+
+    Ok(normalized)
+}
+
+/// A shell-style glob matched against paths relative to the box root.
+///
+/// `*` and `?` match within a path segment, `**` across segments and `[...]` a character
+/// class. A pattern without `/` is matched against the file name only, so `*.csv` finds CSV
+/// files in every directory.
+pub struct Glob {
+    regex: Regex,
+    match_name: bool,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> DomainResult<Self> {
+        let mut regex = String::from("^");
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    if chars.peek() == Some(&'/') {
+                        // `**/` also matches no directory at all
+                        chars.next();
+                        regex.push_str("(?:.*/)?");
+                    } else {
+                        regex.push_str(".*");
+                    }
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                '[' => {
+                    let mut class = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == ']' && !class.is_empty() {
+                            closed = true;
+                            break;
+                        }
+                        class.push(c);
+                    }
+                    if !closed {
+                        return Err(DomainError::InvalidRequest(format!(
+                            "Invalid pattern '{}': unclosed character class",
+                            pattern
+                        )));
+                    }
+                    let class = match class.strip_prefix('!') {
+                        Some(negated) => format!("^{}", negated),
+                        None => class,
+                    };
+                    regex.push('[');
+                    regex.push_str(&class.replace('\\', "\\\\").replace('[', "\\["));
+                    regex.push(']');
+                }
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+
+        let regex = Regex::new(&regex).map_err(|e| {
+            DomainError::InvalidRequest(format!("Invalid pattern '{}': {}", pattern, e))
+        })?;
+        Ok(Self {
+            regex,
+            match_name: !pattern.contains('/'),
+        })
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        let subject = if self.match_name {
+            path.rsplit('/').next().unwrap_or(path)
+        } else {
+            path
+        };
+        self.regex.is_match(subject)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_relative_path_accepts_relative_paths() {
+        let cases = [
+            ("main.py", "main.py"),
+            ("lib/utils.py", "lib/utils.py"),
+            ("./main.py", "main.py"),
+            ("lib/./utils.py", "lib/utils.py"),
+            ("lib//utils.py", "lib/utils.py"),
+            ("lib/utils.py/", "lib/utils.py"),
+            ("..data", "..data"),
+            ("data..", "data.."),
+        ];
+
+        for (path, expected) in cases {
+            assert_eq!(
+                normalize_relative_path(path).unwrap(),
+                PathBuf::from(expected),
+                "{:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_relative_path_rejects_unsafe_paths() {
+        let cases = [
+            "",
+            ".",
+            "./",
+            "..",
+            "../etc/passwd",
+            "lib/../../etc/passwd",
+            "lib/..",
+            "/etc/passwd",
+            "//etc/passwd",
+            "main\0.py",
+            "\0",
+        ];
+
+        for path in cases {
+            assert!(
+                matches!(
+                    normalize_relative_path(path),
+                    Err(DomainError::InvalidRequest(_))
+                ),
+                "{:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn glob_matches() {
+        let cases = [
+            ("*.csv", "data.csv", true),
+            ("*.csv", "out/2024/data.csv", true),
+            ("*.csv", "data.csv.bak", false),
+            ("*", ".hidden", true),
+            ("out/*.txt", "out/a.txt", true),
+            ("out/*.txt", "out/sub/a.txt", false),
+            ("out/*.txt", "a.txt", false),
+            ("out/**", "out/sub/a.txt", true),
+            ("out/**/*.txt", "out/a.txt", true),
+            ("out/**/*.txt", "out/x/y/a.txt", true),
+            ("out/**/*.txt", "other/a.txt", false),
+            ("**/a.txt", "a.txt", true),
+            ("**/a.txt", "x/y/a.txt", true),
+            ("file?.txt", "file1.txt", true),
+            ("file?.txt", "file10.txt", false),
+            ("dir?name", "dir/name", false),
+            ("file[0-9].txt", "file7.txt", true),
+            ("file[0-9].txt", "fileA.txt", false),
+            ("file[!0-9].txt", "fileA.txt", true),
+            ("file[!0-9].txt", "file7.txt", false),
+            ("[]].txt", "].txt", true),
+            ("a+b(1).txt", "a+b(1).txt", true),
+            ("a.txt", "abtxt", false),
+        ];
+
+        for (pattern, path, expected) in cases {
+            let glob = Glob::new(pattern).unwrap();
+            assert_eq!(glob.is_match(path), expected, "{:?} ~ {:?}", pattern, path);
+        }
+    }
+
+    #[test]
+    fn glob_rejects_unclosed_class() {
+        assert!(matches!(
+            Glob::new("file[0-9.txt"),
+            Err(DomainError::InvalidRequest(_))
+        ));
+    }
+}
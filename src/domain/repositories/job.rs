@@ -0,0 +1,15 @@
+use crate::domain::entities::Job;
+use crate::domain::error::DomainResult;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+#[async_trait]
+pub trait JobRepository: Send + Sync {
+    async fn insert(&self, job: Job) -> DomainResult<()>;
+    async fn find_by_id(&self, id: &str) -> DomainResult<Job>;
+    /// Replace a stored job. Jobs that already reached a terminal status are left
+    /// untouched, so a late update from a running task cannot undo a cancellation.
+    async fn update(&self, job: Job) -> DomainResult<()>;
+    /// Remove terminal jobs last updated before `cutoff`
+    async fn delete_finished_before(&self, cutoff: DateTime<Utc>) -> DomainResult<usize>;
+}
@@ -1,4 +1,8 @@
+pub mod job;
 pub mod language;
+pub mod webhook;
 
+pub use job::JobRepository;
 pub use language::LanguageRepository;
+pub use webhook::WebhookDeliveryRepository;
 
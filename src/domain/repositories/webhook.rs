@@ -0,0 +1,15 @@
+use crate::domain::entities::WebhookDelivery;
+use crate::domain::error::DomainResult;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+#[async_trait]
+pub trait WebhookDeliveryRepository: Send + Sync {
+    async fn insert(&self, delivery: WebhookDelivery) -> DomainResult<()>;
+    async fn find_by_id(&self, id: &str) -> DomainResult<WebhookDelivery>;
+    async fn update(&self, delivery: WebhookDelivery) -> DomainResult<()>;
+    /// List the deliveries of `tenant`, newest first, optionally only those of one job
+    async fn list(&self, tenant: &str, job_id: Option<&str>) -> DomainResult<Vec<WebhookDelivery>>;
+    /// Remove delivered or failed deliveries last updated before `cutoff`
+    async fn delete_completed_before(&self, cutoff: DateTime<Utc>) -> DomainResult<usize>;
+}
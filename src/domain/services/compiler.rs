@@ -1,15 +1,40 @@
 use crate::domain::entities::Language;
 use crate::domain::error::DomainResult;
 use async_trait::async_trait;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Counters of a cache of compiled artifacts
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompileCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    /// Total size of the cached artifacts in bytes
+    pub size: u64,
+}
+
+/// Outcome of a compiler run that finished within its limits
+#[derive(Debug, Clone)]
+pub struct CompileOutput {
+    /// Compiled binary, `None` when the compiler rejected the program
+    pub binary_path: Option<PathBuf>,
+    pub exit_code: i32,
+    /// Compiler messages with the paths of the submitted sources masked
+    pub stderr: String,
+}
 
 #[async_trait]
 pub trait CompilerService: Send + Sync {
     async fn compile(
         &self,
         language: &Language,
-        source_path: &PathBuf,
-        output_dir: &PathBuf,
-    ) -> DomainResult<PathBuf>;
+        source_path: &Path,
+        output_dir: &Path,
+    ) -> DomainResult<CompileOutput>;
+
+    /// Counters of the artifact cache, if compilations are cached
+    fn cache_stats(&self) -> Option<CompileCacheStats> {
+        None
+    }
 }
 
@@ -1,6 +1,10 @@
 pub mod compiler;
 pub mod sandbox;
+pub mod webhook;
 
-pub use compiler::CompilerService;
-pub use sandbox::{SandboxExecutionConfig, SandboxExecutionResult, SandboxService};
-
+pub use compiler::{CompileCacheStats, CompileOutput, CompilerService};
+pub use sandbox::{
+    is_reserved_box_path, FileReader, InteractiveExecutionResult, SandboxExecutionConfig,
+    SandboxExecutionResult, SandboxFile, SandboxService, RESERVED_ENV_VARS,
+};
+pub use webhook::WebhookSender;
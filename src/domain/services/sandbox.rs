@@ -1,31 +1,179 @@
-use crate::domain::entities::{ExecutionMetadata, Language};
-use crate::domain::error::DomainResult;
+use crate::domain::entities::{
+    ArchiveFormat, BoxEntry, ExecutionMetadata, Language, OutputChunk, OutputSize,
+    ResourceLimitOverrides, ResourceLimits, SchedulingContext,
+};
+use crate::domain::error::{DomainError, DomainResult};
 use async_trait::async_trait;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::io::AsyncRead;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Box root entries managed by the sandbox service that clients may not supply
+pub const RESERVED_BOX_PATHS: &[&str] = &["bin", "runner", ".stdin", ".stdout", ".stderr"];
+
+/// Whether a box path lies under one of the `RESERVED_BOX_PATHS`
+pub fn is_reserved_box_path(path: &Path) -> bool {
+    path.components()
+        .next()
+        .and_then(|c| c.as_os_str().to_str())
+        .is_some_and(|first| RESERVED_BOX_PATHS.contains(&first))
+}
+
+/// Content of a file read out of a box
+pub type FileReader = Pin<Box<dyn AsyncRead + Send>>;
+
+/// Environment variables set by the sandbox service that clients may not override
+pub const RESERVED_ENV_VARS: &[&str] = &["HOME", "PYTHONPATH", "LIBC_FATAL_STDERR_"];
+
+/// A file copied into the box before execution
+#[derive(Debug, Clone)]
+pub struct SandboxFile {
+    pub host_path: PathBuf,
+    pub box_path: PathBuf,
+}
 
 pub struct SandboxExecutionConfig {
     pub box_id: u32,
     pub binary_path: PathBuf,
     pub runner_path: PathBuf,
+    pub files: Vec<SandboxFile>,
     pub language: Language,
     pub metadata_path: PathBuf,
+    pub stdin_path: Option<PathBuf>,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub limits: ResourceLimits,
+    /// Stops the run, cleans up the box and fails it with `Cancelled` when triggered
+    pub cancel: CancellationToken,
+    /// Run in the box as left by earlier runs instead of initializing it afresh
+    pub reuse_box: bool,
 }
 
 pub struct SandboxExecutionResult {
-    pub stdout: String,
-    pub stderr: String,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub stdout_size: OutputSize,
+    pub stderr_size: OutputSize,
     pub metadata: ExecutionMetadata,
     pub box_id: u32,
 }
 
+/// Results of a program and an interactor run with connected standard streams
+pub struct InteractiveExecutionResult {
+    pub program: SandboxExecutionResult,
+    pub interactor: SandboxExecutionResult,
+}
+
 #[async_trait]
 pub trait SandboxService: Send + Sync {
+    fn resolve_limits(&self, overrides: &ResourceLimitOverrides) -> DomainResult<ResourceLimits>;
     async fn execute(&self, config: SandboxExecutionConfig) -> DomainResult<SandboxExecutionResult>;
-    async fn acquire_box_id(&self) -> DomainResult<u32>;
+    /// Execute like `execute`, but send output to `output` as it is produced instead of
+    /// collecting it into the result
+    async fn execute_streaming(
+        &self,
+        config: SandboxExecutionConfig,
+        output: mpsc::Sender<OutputChunk>,
+    ) -> DomainResult<SandboxExecutionResult>;
+    async fn execute_interactive(
+        &self,
+        program: SandboxExecutionConfig,
+        interactor: SandboxExecutionConfig,
+    ) -> DomainResult<InteractiveExecutionResult>;
+    /// Take a free box, waiting in line according to the scheduling context if none is free
+    async fn acquire_box_id(&self, scheduling: &SchedulingContext) -> DomainResult<u32>;
+    /// Take `count` free boxes at once, so a request never holds one box while waiting
+    /// for another
+    async fn acquire_box_ids(
+        &self,
+        scheduling: &SchedulingContext,
+        count: usize,
+    ) -> DomainResult<Vec<u32>>;
     async fn release_box_id(&self, box_id: u32) -> DomainResult<()>;
-    async fn list_files(&self, box_id: u32) -> DomainResult<Vec<String>>;
+    /// Tenant holding `box_id`, if the box is in use
+    fn box_holder(&self, box_id: u32) -> Option<String>;
+    /// Make sure `tenant` holds `box_id`. Boxes of other tenants are reported as not found,
+    /// like boxes no one holds, so tenants cannot tell which boxes the others use.
+    fn check_box_owner(&self, box_id: u32, tenant: &str) -> DomainResult<()> {
+        match self.box_holder(box_id) {
+            Some(holder) if holder == tenant => Ok(()),
+            _ => Err(DomainError::BoxNotFound(box_id)),
+        }
+    }
+    /// Initialize an empty box, ready to run programs in with `reuse_box`
+    async fn init(&self, box_id: u32) -> DomainResult<()>;
+    /// List the entries of an initialized box sorted by path, descending into
+    /// subdirectories when `recursive`
+    async fn list_entries(&self, box_id: u32, recursive: bool) -> DomainResult<Vec<BoxEntry>>;
+    /// SHA-256 of regular files in a box, keyed by their paths relative to the box root.
+    /// Files that cannot be read are left out.
+    async fn hash_files(
+        &self,
+        box_id: u32,
+        paths: &[String],
+    ) -> DomainResult<HashMap<String, String>>;
+    /// Write an archive of entries of a box (paths relative to its root) to the host file
+    /// `output`. Directories are archived without their contents, entries that vanished are
+    /// skipped.
+    async fn archive(
+        &self,
+        box_id: u32,
+        paths: &[String],
+        format: ArchiveFormat,
+        output: &Path,
+    ) -> DomainResult<()>;
     async fn get_file_base64(&self, box_id: u32, filename: &str) -> DomainResult<String>;
+    /// Metadata of an entry in an initialized box, following symlinks that stay inside it.
+    /// The returned path is the resolved one.
+    async fn stat_path(&self, box_id: u32, path: &Path) -> DomainResult<BoxEntry>;
+    /// Stream `length` bytes of a regular file in a box, starting at byte `offset`
+    async fn read_file(
+        &self,
+        box_id: u32,
+        path: &Path,
+        offset: u64,
+        length: u64,
+    ) -> DomainResult<FileReader>;
+    /// Copy a host file into an initialized box, creating parent directories as needed
+    async fn write_file(&self, box_id: u32, path: &Path, source: &Path) -> DomainResult<()>;
     async fn delete_file(&self, box_id: u32, filename: &str) -> DomainResult<()>;
+    /// Delete a file, symlink or, with `recursive`, a directory and its contents from an
+    /// initialized box
+    async fn remove_path(&self, box_id: u32, path: &Path, recursive: bool) -> DomainResult<()>;
     async fn cleanup(&self, box_id: u32) -> DomainResult<()>;
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserved_box_paths() {
+        let cases = [
+            ("bin", true),
+            ("runner", true),
+            (".stdin", true),
+            (".stdout", true),
+            (".stderr", true),
+            ("bin/helper", true),
+            ("runner/data.txt", true),
+            ("main.py", false),
+            ("lib/bin", false),
+            ("binary", false),
+            (".stdin.txt", false),
+            ("data/.stdout", false),
+        ];
+
+        for (path, reserved) in cases {
+            assert_eq!(
+                is_reserved_box_path(Path::new(path)),
+                reserved,
+                "{:?}",
+                path
+            );
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use crate::domain::error::DomainResult;
+use async_trait::async_trait;
+
+#[async_trait]
+pub trait WebhookSender: Send + Sync {
+    /// Reject a callback URL that payloads must not be sent to, such as one whose host
+    /// resolves to an internal address
+    async fn check_url(&self, url: &str) -> DomainResult<()>;
+
+
+    /// POST a signed payload to `url` once, returning the HTTP status of the response.
+    ///
+    /// Errors are reserved for failures to get a response at all.
+    async fn send(&self, url: &str, delivery_id: &str, payload: &[u8]) -> DomainResult<u16>;
+}
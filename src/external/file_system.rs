@@ -1,5 +1,5 @@
 use crate::domain::error::{DomainError, DomainResult};
-use std::path::PathBuf;
+use std::path::Path;
 use tokio::fs;
 
 pub struct FileSystem;
@@ -9,27 +9,11 @@ impl FileSystem {
         Self
     }
 
-    pub async fn copy_file(&self, from: &PathBuf, to: &PathBuf) -> DomainResult<()> {
-        fs::copy(from, to)
-            .await
-            .map_err(|e| DomainError::Internal(format!("Failed to copy file: {}", e)))?;
-        Ok(())
-    }
-
-    pub async fn read_to_string(&self, path: &PathBuf) -> DomainResult<String> {
+    pub async fn read_to_string(&self, path: &Path) -> DomainResult<String> {
         fs::read_to_string(path)
             .await
             .map_err(|e| DomainError::Internal(format!("Failed to read file: {}", e)))
     }
-
-    pub async fn ensure_directory_exists(&self, path: &PathBuf) -> DomainResult<()> {
-        if !path.exists() {
-            fs::create_dir_all(path)
-                .await
-                .map_err(|e| DomainError::Internal(format!("Failed to create directory: {}", e)))?;
-        }
-        Ok(())
-    }
 }
 
 impl Default for FileSystem {
@@ -37,4 +21,3 @@ impl Default for FileSystem {
         Self::new()
     }
 }
-
@@ -1,6 +1,15 @@
+use crate::domain::entities::{OutputChunk, OutputSize, OutputStream};
 use crate::domain::error::{DomainError, DomainResult};
+use std::pin::Pin;
 use std::process::Stdio;
-use tokio::process::Command;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Size of the buffer used to read output of streamed commands
+const STREAM_CHUNK_SIZE: usize = 8192;
 
 pub struct ProcessExecutor;
 
@@ -18,6 +27,7 @@ impl ProcessExecutor {
             .args(args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
+            .kill_on_drop(true)
             .output()
             .await
             .map_err(|e| DomainError::Internal(format!("Failed to execute command: {}", e)))?;
@@ -29,6 +39,218 @@ impl ProcessExecutor {
         Ok((stdout, stderr, exit_code))
     }
 
+    /// Run a command like `execute_command`, returning stdout as raw bytes
+    pub async fn execute_command_raw(
+        &self,
+        program: &str,
+        args: &[&str],
+    ) -> DomainResult<(Vec<u8>, String, i32)> {
+        let output = Command::new(program)
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|e| DomainError::Internal(format!("Failed to execute command: {}", e)))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        let exit_code = output.status.code().unwrap_or(-1);
+
+        Ok((output.stdout, stderr, exit_code))
+    }
+
+    /// Run a command like `execute_command`, terminating it when `cancel` is triggered.
+    ///
+    /// Fails with `Cancelled` once the terminated command has exited.
+    pub async fn execute_cancellable(
+        &self,
+        program: &str,
+        args: &[&str],
+        cancel: &CancellationToken,
+    ) -> DomainResult<(String, String, i32)> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| DomainError::Internal(format!("Failed to execute command: {}", e)))?;
+
+        let (stdout, stderr) = take_output_pipes(&mut child)?;
+        let pid = child.id();
+
+        let run = async {
+            tokio::join!(read_lossy(stdout), read_lossy(stderr), child.wait())
+        };
+        let (stdout, stderr, status) = wait_or_terminate(run, &[pid], cancel).await?;
+
+        let status =
+            status.map_err(|e| DomainError::Internal(format!("Failed to wait for command: {}", e)))?;
+
+        Ok((stdout, stderr, status.code().unwrap_or(-1)))
+    }
+
+    /// Run two commands with the stdout of each connected to the stdin of the other.
+    ///
+    /// Waits for both to exit and returns up to `max_stderr` bytes (0 = unlimited) of
+    /// their stderr, with its total size. Both are terminated when `cancel` is triggered,
+    /// failing with `Cancelled` once they have exited.
+    pub async fn execute_connected(
+        &self,
+        first: (&str, &[&str]),
+        second: (&str, &[&str]),
+        max_stderr: u64,
+        cancel: &CancellationToken,
+    ) -> DomainResult<((Vec<u8>, OutputSize), (Vec<u8>, OutputSize))> {
+        let mut first_child = spawn_piped(first.0, first.1)?;
+        let mut second_child = spawn_piped(second.0, second.1)?;
+
+        let (first_stdin, first_stdout, first_stderr) = take_pipes(&mut first_child)?;
+        let (second_stdin, second_stdout, second_stderr) = take_pipes(&mut second_child)?;
+        let pids = [first_child.id(), second_child.id()];
+
+        let run = async {
+            tokio::join!(
+                forward(first_stdout, second_stdin),
+                forward(second_stdout, first_stdin),
+                read_bounded(first_stderr, max_stderr),
+                read_bounded(second_stderr, max_stderr),
+                first_child.wait(),
+                second_child.wait(),
+            )
+        };
+        let (_, _, first_stderr, second_stderr, first_status, second_status) =
+            wait_or_terminate(run, &pids, cancel).await?;
+
+        first_status
+            .and(second_status)
+            .map_err(|e| DomainError::Internal(format!("Failed to wait for command: {}", e)))?;
+
+        Ok((first_stderr, second_stderr))
+    }
+
+    /// Run a command, sending its stdout and stderr to `output` as they are produced.
+    ///
+    /// At most `max_output` bytes (0 = unlimited) of each stream are sent; the rest is
+    /// drained and discarded. Returns the exit code and the size of both streams once the
+    /// command has exited and both streams are drained.
+    pub async fn execute_streaming(
+        &self,
+        program: &str,
+        args: &[&str],
+        output: mpsc::Sender<OutputChunk>,
+        max_output: u64,
+        cancel: &CancellationToken,
+    ) -> DomainResult<(i32, OutputSize, OutputSize)> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| DomainError::Internal(format!("Failed to execute command: {}", e)))?;
+
+        let (stdout, stderr) = take_output_pipes(&mut child)?;
+        let pid = child.id();
+
+        let run = async {
+            tokio::join!(
+                send_chunks(stdout, OutputStream::Stdout, output.clone(), max_output),
+                send_chunks(stderr, OutputStream::Stderr, output, max_output),
+                child.wait(),
+            )
+        };
+        let (stdout_size, stderr_size, status) = wait_or_terminate(run, &[pid], cancel).await?;
+
+        let status =
+            status.map_err(|e| DomainError::Internal(format!("Failed to wait for command: {}", e)))?;
+
+        Ok((status.code().unwrap_or(-1), stdout_size, stderr_size))
+    }
+
+    /// Read up to `limit` bytes (0 = unlimited) of the regular file at `path` through
+    /// `program`, e.g. `sudo`, returning the content and the size of the whole file.
+    ///
+    /// Returns `None` when there is no regular file at `path`. A symlink is never followed,
+    /// so a sandboxed program cannot swap a file it wrote for a link to one it cannot read.
+    pub async fn read_regular_file(
+        &self,
+        program: &str,
+        path: &str,
+        limit: u64,
+    ) -> DomainResult<Option<(Vec<u8>, OutputSize)>> {
+        // `find` does not follow symlinks, so only a regular file matches
+        let (size, _, exit_code) = self
+            .execute_command(
+                program,
+                &[
+                    "find",
+                    path,
+                    "-maxdepth",
+                    "0",
+                    "-type",
+                    "f",
+                    "-printf",
+                    "%s",
+                ],
+            )
+            .await?;
+        if exit_code != 0 || size.is_empty() {
+            return Ok(None);
+        }
+        let total: u64 = size
+            .parse()
+            .map_err(|_| DomainError::Internal(format!("Invalid size of {}: {}", path, size)))?;
+
+        // The file may still be replaced before it is opened, which `nofollow` turns into
+        // an error instead of reading the link target
+        let input = format!("if={}", path);
+        let count = format!("count={}", limit);
+        let mut args = vec!["dd", &input, "bs=65536", "status=none"];
+        if limit > 0 {
+            args.extend(["iflag=count_bytes,nofollow", &count]);
+        } else {
+            args.push("iflag=nofollow");
+        }
+        let (content, stderr, exit_code) = self.execute_command_raw(program, &args).await?;
+        if exit_code != 0 {
+            return Err(DomainError::Internal(format!(
+                "Failed to read {}: {}",
+                path,
+                stderr.trim()
+            )));
+        }
+
+        let truncated = limit > 0 && total > limit;
+        Ok(Some((content, OutputSize { total, truncated })))
+    }
+
+    /// Start a command and return a reader of its stdout, discarding its stderr.
+    ///
+    /// Dropping the reader closes the pipe, so a command still writing is ended by `SIGPIPE`.
+    pub fn spawn_reader(&self, program: &str, args: &[&str]) -> DomainResult<ProcessReader> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| DomainError::Internal(format!("Failed to execute command: {}", e)))?;
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| DomainError::Internal("Failed to capture process pipes".to_string()))?;
+
+        Ok(ProcessReader {
+            _child: child,
+            stdout,
+        })
+    }
+
     pub async fn execute_script(&self, script_path: &str) -> DomainResult<()> {
         let output = Command::new("bash")
             .arg(script_path)
@@ -50,9 +272,272 @@ impl ProcessExecutor {
     }
 }
 
+/// Stdout of a command started by `ProcessExecutor::spawn_reader`
+pub struct ProcessReader {
+    /// Kept so the command is reaped once the reader is dropped
+    _child: Child,
+    stdout: tokio::process::ChildStdout,
+}
+
+impl AsyncRead for ProcessReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().stdout).poll_read(cx, buf)
+    }
+}
+
+fn spawn_piped(program: &str, args: &[&str]) -> DomainResult<Child> {
+    Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| DomainError::Internal(format!("Failed to execute command: {}", e)))
+}
+
+type ChildPipes = (
+    tokio::process::ChildStdin,
+    tokio::process::ChildStdout,
+    tokio::process::ChildStderr,
+);
+
+fn take_pipes(child: &mut Child) -> DomainResult<ChildPipes> {
+    match (child.stdin.take(), child.stdout.take(), child.stderr.take()) {
+        (Some(stdin), Some(stdout), Some(stderr)) => Ok((stdin, stdout, stderr)),
+        _ => Err(DomainError::Internal("Failed to capture process pipes".to_string())),
+    }
+}
+
+fn take_output_pipes(
+    child: &mut Child,
+) -> DomainResult<(tokio::process::ChildStdout, tokio::process::ChildStderr)> {
+    match (child.stdout.take(), child.stderr.take()) {
+        (Some(stdout), Some(stderr)) => Ok((stdout, stderr)),
+        _ => Err(DomainError::Internal("Failed to capture process pipes".to_string())),
+    }
+}
+
+/// Await `run`, sending SIGTERM to `pids` if `cancel` is triggered first.
+///
+/// The command runs as root through `sudo`, which relays the signal to `isolate` so that it
+/// kills everything in the box. `run` is still awaited afterwards so the pipes are drained and
+/// the process is reaped before `Cancelled` is returned.
+async fn wait_or_terminate<F: std::future::Future>(
+    run: F,
+    pids: &[Option<u32>],
+    cancel: &CancellationToken,
+) -> DomainResult<F::Output> {
+    tokio::pin!(run);
+
+    tokio::select! {
+        output = &mut run => return Ok(output),
+        _ = cancel.cancelled() => {}
+    }
+
+    for pid in pids.iter().flatten() {
+        log::debug!("Terminating cancelled process {}", pid);
+        let pid = pid.to_string();
+        let status = Command::new("sudo")
+            .args(["kill", "-TERM", &pid])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .await;
+        if let Err(e) = status {
+            log::warn!("Failed to terminate process {}: {}", pid, e);
+        }
+    }
+
+    run.await;
+    Err(DomainError::Cancelled)
+}
+
+/// Copy everything from `reader` to `writer`, closing `writer` once the reader hits EOF
+async fn forward<R, W>(mut reader: R, mut writer: W)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    // The peer exiting early closes the pipe, which is expected and not an error
+    if let Err(e) = tokio::io::copy(&mut reader, &mut writer).await {
+        log::debug!("Stopped forwarding process output: {}", e);
+    }
+}
+
+async fn read_lossy<R: AsyncRead + Unpin>(mut reader: R) -> String {
+    let mut buffer = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut buffer).await {
+        log::debug!("Failed to read process output: {}", e);
+    }
+    String::from_utf8_lossy(&buffer).to_string()
+}
+
+/// Read `reader` until EOF, keeping at most `limit` bytes (0 = unlimited).
+///
+/// The rest is drained and only counted, so the process never blocks on a full pipe.
+async fn read_bounded<R: AsyncRead + Unpin>(mut reader: R, limit: u64) -> (Vec<u8>, OutputSize) {
+    let mut kept = Vec::new();
+    let mut total = 0;
+    let mut buffer = vec![0; STREAM_CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(n) => {
+                kept.extend_from_slice(&buffer[..keep_count(total, n, limit)]);
+                total += n as u64;
+            }
+            Err(e) => {
+                log::debug!("Failed to read process output: {}", e);
+                break;
+            }
+        }
+    }
+
+    let truncated = limit > 0 && total > limit;
+    (kept, OutputSize { total, truncated })
+}
+
+/// Read `reader` until EOF, sending at most `limit` bytes (0 = unlimited) to `output`
+async fn send_chunks<R: AsyncRead + Unpin>(
+    mut reader: R,
+    stream: OutputStream,
+    output: mpsc::Sender<OutputChunk>,
+    limit: u64,
+) -> OutputSize {
+    let mut total = 0;
+    let mut buffer = vec![0; STREAM_CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(n) => {
+                let keep = keep_count(total, n, limit);
+                total += n as u64;
+                if keep == 0 {
+                    continue;
+                }
+                let chunk = OutputChunk {
+                    stream,
+                    data: buffer[..keep].to_vec(),
+                };
+                // Keep draining the pipe even if nobody is listening so the process never blocks
+                if output.send(chunk).await.is_err() {
+                    log::debug!("Output receiver dropped, discarding {:?} output", stream);
+                }
+            }
+            Err(e) => {
+                log::debug!("Failed to read process output: {}", e);
+                break;
+            }
+        }
+    }
+
+    OutputSize {
+        total,
+        truncated: limit > 0 && total > limit,
+    }
+}
+
+/// Number of bytes of an `n` byte read to keep after `total` bytes were read before
+fn keep_count(total: u64, n: usize, limit: u64) -> usize {
+    if limit == 0 {
+        return n;
+    }
+    limit.saturating_sub(total).min(n as u64) as usize
+}
+
 impl Default for ProcessExecutor {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn read_bounded_keeps_up_to_limit() {
+        let input = vec![b'x'; STREAM_CHUNK_SIZE * 2 + 10];
+        let cases = [
+            (0, input.len(), false),
+            (5, 5, true),
+            (STREAM_CHUNK_SIZE as u64 + 1, STREAM_CHUNK_SIZE + 1, true),
+            (input.len() as u64, input.len(), false),
+            (input.len() as u64 + 1, input.len(), false),
+        ];
+
+        for (limit, kept, truncated) in cases {
+            let (output, size) = read_bounded(input.as_slice(), limit).await;
+            assert_eq!(output.len(), kept, "limit {}", limit);
+            assert_eq!(
+                size,
+                OutputSize {
+                    total: input.len() as u64,
+                    truncated
+                },
+                "limit {}",
+                limit
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn send_chunks_stops_sending_at_limit() {
+        let input = vec![b'x'; STREAM_CHUNK_SIZE + 10];
+        let (sender, mut receiver) = mpsc::channel(16);
+
+        let size = send_chunks(input.as_slice(), OutputStream::Stdout, sender, 100).await;
+
+        let mut sent = Vec::new();
+        while let Some(chunk) = receiver.recv().await {
+            sent.extend(chunk.data);
+        }
+        assert_eq!(sent.len(), 100);
+        assert_eq!(
+            size,
+            OutputSize {
+                total: input.len() as u64,
+                truncated: true
+            }
+        );
+    }
+
+    /// Read a file the way the sandbox does, with `env` in place of `sudo`
+    async fn read_file(path: &std::path::Path, limit: u64) -> Option<(Vec<u8>, OutputSize)> {
+        ProcessExecutor::new()
+            .read_regular_file("env", path.to_str().unwrap(), limit)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn read_regular_file_does_not_follow_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let secret = dir.path().join("secret");
+        std::fs::write(&secret, "do not leak").unwrap();
+        let output = dir.path().join(".stdout");
+        std::fs::write(&output, "hello world").unwrap();
+        let link = dir.path().join(".stderr");
+        std::os::unix::fs::symlink(&secret, &link).unwrap();
+
+        let size = |total, truncated| OutputSize { total, truncated };
+        assert_eq!(
+            read_file(&output, 0).await,
+            Some((b"hello world".to_vec(), size(11, false)))
+        );
+        assert_eq!(
+            read_file(&output, 5).await,
+            Some((b"hello".to_vec(), size(11, true)))
+        );
+
+        // Replacing the output with a link to a file the program cannot read leaks nothing
+        assert_eq!(read_file(&link, 0).await, None);
+        assert_eq!(read_file(dir.path(), 0).await, None);
+        assert_eq!(read_file(&dir.path().join("missing"), 0).await, None);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::domain::entities::Job;
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::repositories::JobRepository;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+/// Job store kept in process memory; jobs do not survive a restart
+pub struct InMemoryJobRepository {
+    jobs: RwLock<HashMap<String, Job>>,
+}
+
+impl InMemoryJobRepository {
+    pub fn new() -> Self {
+        Self {
+            jobs: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for InMemoryJobRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl JobRepository for InMemoryJobRepository {
+    async fn insert(&self, job: Job) -> DomainResult<()> {
+        self.jobs.write().await.insert(job.id.clone(), job);
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &str) -> DomainResult<Job> {
+        self.jobs
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .ok_or_else(|| DomainError::JobNotFound(id.to_string()))
+    }
+
+    async fn update(&self, job: Job) -> DomainResult<()> {
+        let mut jobs = self.jobs.write().await;
+        let stored = jobs
+            .get_mut(&job.id)
+            .ok_or_else(|| DomainError::JobNotFound(job.id.clone()))?;
+
+        if stored.status.is_terminal() {
+            log::debug!(
+                "Ignoring update of job {} in terminal status {:?}",
+                job.id,
+                stored.status
+            );
+            return Ok(());
+        }
+
+        *stored = job;
+        Ok(())
+    }
+
+    async fn delete_finished_before(&self, cutoff: DateTime<Utc>) -> DomainResult<usize> {
+        let mut jobs = self.jobs.write().await;
+        let before = jobs.len();
+        jobs.retain(|_, job| !(job.status.is_terminal() && job.updated_at < cutoff));
+        Ok(before - jobs.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::JobStatus;
+
+    #[tokio::test]
+    async fn update_follows_the_job_until_it_is_terminal() {
+        let repository = InMemoryJobRepository::new();
+        let mut job = Job::new("job".to_string(), "tenant".to_string());
+        repository.insert(job.clone()).await.unwrap();
+
+        let statuses = [
+            JobStatus::Compiling,
+            JobStatus::Running,
+            JobStatus::Finished,
+        ];
+        for status in statuses {
+            job.set_status(status);
+            repository.update(job.clone()).await.unwrap();
+            assert_eq!(repository.find_by_id("job").await.unwrap().status, status);
+        }
+    }
+
+    #[tokio::test]
+    async fn update_never_overwrites_terminal_jobs() {
+        let terminal = [JobStatus::Finished, JobStatus::Failed, JobStatus::Cancelled];
+        for stored in terminal {
+            let repository = InMemoryJobRepository::new();
+            let mut job = Job::new("job".to_string(), "tenant".to_string());
+            job.set_status(stored);
+            repository.insert(job.clone()).await.unwrap();
+
+            // A late update from the job task, e.g. after the job was cancelled
+            for status in [JobStatus::Running, JobStatus::Finished, JobStatus::Failed] {
+                job.set_status(status);
+                job.error = Some("late".to_string());
+                repository.update(job.clone()).await.unwrap();
+            }
+
+            let job = repository.find_by_id("job").await.unwrap();
+            assert_eq!(job.status, stored);
+            assert_eq!(job.error, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn update_of_unknown_job_fails() {
+        let repository = InMemoryJobRepository::new();
+        let job = Job::new("job".to_string(), "tenant".to_string());
+
+        let result = repository.update(job).await;
+        assert!(matches!(result, Err(DomainError::JobNotFound(_))));
+    }
+}
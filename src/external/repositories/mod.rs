@@ -1,4 +1,8 @@
+pub mod job;
 pub mod language;
+pub mod webhook;
 
+pub use job::InMemoryJobRepository;
 pub use language::FileSystemLanguageRepository;
+pub use webhook::InMemoryWebhookDeliveryRepository;
 
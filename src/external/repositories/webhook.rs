@@ -0,0 +1,122 @@
+use crate::domain::entities::{DeliveryStatus, WebhookDelivery};
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::repositories::WebhookDeliveryRepository;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+
+/// Delivery store kept in process memory; records do not survive a restart
+pub struct InMemoryWebhookDeliveryRepository {
+    deliveries: RwLock<HashMap<String, WebhookDelivery>>,
+}
+
+impl InMemoryWebhookDeliveryRepository {
+    pub fn new() -> Self {
+        Self {
+            deliveries: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for InMemoryWebhookDeliveryRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl WebhookDeliveryRepository for InMemoryWebhookDeliveryRepository {
+    async fn insert(&self, delivery: WebhookDelivery) -> DomainResult<()> {
+        self.deliveries
+            .write()
+            .await
+            .insert(delivery.id.clone(), delivery);
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: &str) -> DomainResult<WebhookDelivery> {
+        self.deliveries
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .ok_or_else(|| DomainError::DeliveryNotFound(id.to_string()))
+    }
+
+    async fn update(&self, delivery: WebhookDelivery) -> DomainResult<()> {
+        let mut deliveries = self.deliveries.write().await;
+        let stored = deliveries
+            .get_mut(&delivery.id)
+            .ok_or_else(|| DomainError::DeliveryNotFound(delivery.id.clone()))?;
+        *stored = delivery;
+        Ok(())
+    }
+
+    async fn list(&self, tenant: &str, job_id: Option<&str>) -> DomainResult<Vec<WebhookDelivery>> {
+        let mut deliveries: Vec<WebhookDelivery> = self
+            .deliveries
+            .read()
+            .await
+            .values()
+            .filter(|delivery| delivery.tenant == tenant)
+            .filter(|delivery| job_id.is_none() || delivery.job_id.as_deref() == job_id)
+            .cloned()
+            .collect();
+        deliveries.sort_by_key(|delivery| Reverse(delivery.created_at));
+        Ok(deliveries)
+    }
+
+    async fn delete_completed_before(&self, cutoff: DateTime<Utc>) -> DomainResult<usize> {
+        let mut deliveries = self.deliveries.write().await;
+        let before = deliveries.len();
+        deliveries.retain(|_, delivery| {
+            delivery.status == DeliveryStatus::Pending || delivery.updated_at >= cutoff
+        });
+        Ok(before - deliveries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delivery(id: &str, tenant: &str, job_id: Option<&str>) -> WebhookDelivery {
+        WebhookDelivery::new(
+            id.to_string(),
+            tenant.to_string(),
+            "https://example.com/hook".to_string(),
+            job_id.map(str::to_string),
+        )
+    }
+
+    #[tokio::test]
+    async fn list_only_returns_deliveries_of_the_tenant() {
+        let repository = InMemoryWebhookDeliveryRepository::new();
+        repository.insert(delivery("a-1", "a", None)).await.unwrap();
+        repository
+            .insert(delivery("a-2", "a", Some("job")))
+            .await
+            .unwrap();
+        repository
+            .insert(delivery("b-1", "b", Some("job")))
+            .await
+            .unwrap();
+
+        let ids = |deliveries: Vec<WebhookDelivery>| {
+            let mut ids: Vec<String> = deliveries.into_iter().map(|delivery| delivery.id).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(
+            ids(repository.list("a", None).await.unwrap()),
+            ["a-1", "a-2"]
+        );
+        assert_eq!(
+            ids(repository.list("b", Some("job")).await.unwrap()),
+            ["b-1"]
+        );
+        assert!(repository.list("c", None).await.unwrap().is_empty());
+    }
+}
@@ -1,44 +1,524 @@
+use crate::domain::entities::{Priority, SchedulingContext};
 use crate::domain::error::{DomainError, DomainResult};
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// A request waiting for boxes
+struct Waiter {
+    sender: oneshot::Sender<Vec<u32>>,
+    tenant: String,
+    priority: Priority,
+    /// Number of boxes the request needs, all handed over at once
+    count: usize,
+}
+
+struct PoolState {
+    available: VecDeque<u32>,
+    /// Requests waiting for a box, oldest first
+    waiters: VecDeque<Waiter>,
+    /// Tenant holding each box that is in use
+    holders: HashMap<u32, String>,
+    /// Number of boxes held by each tenant
+    held: HashMap<String, u32>,
+    /// Share of the pool each tenant is entitled to, relative to the others
+    weights: HashMap<String, u32>,
+}
+
+impl PoolState {
+    fn grant(&mut self, box_id: u32, tenant: &str) {
+        self.holders.insert(box_id, tenant.to_string());
+        *self.held.entry(tenant.to_string()).or_default() += 1;
+    }
+
+    /// Take a box back from its holder, returning whether anyone held it
+    fn reclaim(&mut self, box_id: u32) -> bool {
+        let Some(tenant) = self.holders.remove(&box_id) else {
+            return false;
+        };
+        if let Some(held) = self.held.get_mut(&tenant) {
+            *held = held.saturating_sub(1);
+        }
+        true
+    }
+
+    /// Index of the waiter to serve next.
+    ///
+    /// Higher priority classes go first. Within a class, the tenant holding the fewest
+    /// boxes relative to its weight goes first, and each tenant is served in FIFO order.
+    fn next_waiter(&self) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        for (index, waiter) in self.waiters.iter().enumerate() {
+            let better = match best {
+                None => true,
+                Some(best) => {
+                    let current = &self.waiters[best];
+                    waiter.priority > current.priority
+                        || (waiter.priority == current.priority
+                            && self.usage(&waiter.tenant) < self.usage(&current.tenant))
+                }
+            };
+            if better {
+                best = Some(index);
+            }
+        }
+        best
+    }
+
+    /// Boxes held by a tenant per unit of weight
+    fn usage(&self, tenant: &str) -> f64 {
+        let held = self.held.get(tenant).copied().unwrap_or(0);
+        let weight = self.weights.get(tenant).copied().unwrap_or(1).max(1);
+        f64::from(held) / f64::from(weight)
+    }
+}
 
 pub struct BoxPool {
-    available: Arc<Mutex<VecDeque<u32>>>,
-    size: u32,
+    state: Arc<Mutex<PoolState>>,
+    box_ids: Range<u32>,
+    max_queue_length: usize,
+    max_wait: Duration,
 }
 
 impl BoxPool {
-    pub fn new(size: u32) -> Self {
-        let mut available = VecDeque::new();
-        for i in 0..size {
-            available.push_back(i);
-        }
+    /// Create a pool handing out the boxes in `box_ids`
+    pub fn new(
+        box_ids: Range<u32>,
+        max_queue_length: usize,
+        max_wait: Duration,
+        weights: HashMap<String, u32>,
+    ) -> Self {
+        let available = box_ids.clone().collect();
 
         Self {
-            available: Arc::new(Mutex::new(available)),
-            size,
+            state: Arc::new(Mutex::new(PoolState {
+                available,
+                waiters: VecDeque::new(),
+                holders: HashMap::new(),
+                held: HashMap::new(),
+                weights,
+            })),
+            box_ids,
+            max_queue_length,
+            max_wait,
         }
     }
 
-    pub async fn acquire(&self) -> DomainResult<u32> {
-        let mut available = self.available.lock().await;
-        available
-            .pop_front()
-            .ok_or(DomainError::BoxPoolExhausted)
+    /// Take a free box, waiting in line for one to be released if none is available.
+    ///
+    /// Fails with `BoxPoolExhausted` when the wait queue is full or no box frees up
+    /// within the maximum wait time.
+    pub async fn acquire(&self, scheduling: &SchedulingContext) -> DomainResult<u32> {
+        let box_ids = self.acquire_many(scheduling, 1).await?;
+        Ok(box_ids[0])
+    }
+
+    /// Take `count` boxes at once, waiting in line until that many are free.
+    ///
+    /// The boxes are handed over together, so a request never holds some boxes while
+    /// waiting for the rest, which would deadlock when every request does so.
+    pub async fn acquire_many(
+        &self,
+        scheduling: &SchedulingContext,
+        count: usize,
+    ) -> DomainResult<Vec<u32>> {
+        if count > self.box_ids.len() {
+            return Err(DomainError::InvalidRequest(format!(
+                "Request needs {} boxes but the pool only has {}",
+                count,
+                self.box_ids.len()
+            )));
+        }
+
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+
+            // Forget requests that stopped waiting
+            state.waiters.retain(|waiter| !waiter.sender.is_closed());
+            if state.waiters.is_empty() && state.available.len() >= count {
+                let box_ids: Vec<u32> = state.available.drain(..count).collect();
+                for &box_id in &box_ids {
+                    state.grant(box_id, &scheduling.tenant);
+                }
+                return Ok(box_ids);
+            }
+
+            if state.waiters.len() >= self.max_queue_length {
+                log::debug!("Box pool wait queue is full");
+                return Err(self.exhausted());
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            state.waiters.push_back(Waiter {
+                sender,
+                tenant: scheduling.tenant.clone(),
+                priority: scheduling.priority,
+                count,
+            });
+            log::debug!(
+                "Tenant {} waiting for {} box(es) with {:?} priority, {} request(s) in line",
+                scheduling.tenant,
+                count,
+                scheduling.priority,
+                state.waiters.len()
+            );
+            // Boxes may be free while others wait for more than are available
+            dispatch(&mut state);
+            PendingBox {
+                receiver,
+                state: self.state.clone(),
+            }
+        };
+
+        receiver.wait(self.max_wait).await.ok_or_else(|| {
+            log::debug!("Timed out waiting for a box");
+            self.exhausted()
+        })
     }
 
+    /// Return a box to the pool. Releasing a box no one holds does nothing, so a box
+    /// released twice is never handed out to two requests.
     pub async fn release(&self, box_id: u32) -> DomainResult<()> {
-        if box_id >= self.size {
+        if !self.box_ids.contains(&box_id) {
             return Err(DomainError::Internal(format!(
                 "Invalid box ID: {}",
                 box_id
             )));
         }
 
-        let mut available = self.available.lock().await;
-        available.push_back(box_id);
+        release_to(&self.state, box_id);
         Ok(())
     }
+
+    /// Tenant holding `box_id`, if the box is in use
+    pub fn holder(&self, box_id: u32) -> Option<String> {
+        self.state.lock().unwrap().holders.get(&box_id).cloned()
+    }
+
+    fn exhausted(&self) -> DomainError {
+        DomainError::BoxPoolExhausted {
+            retry_after: self.max_wait.as_secs().max(1),
+        }
+    }
+}
+
+/// Put a held box back in the pool and hand free boxes to waiting requests
+fn release_to(state: &Mutex<PoolState>, box_id: u32) {
+    let mut state = state.lock().unwrap();
+    if !state.reclaim(box_id) {
+        log::warn!("Ignoring release of box ID {} which is not in use", box_id);
+        return;
+    }
+    state.available.push_back(box_id);
+    dispatch(&mut state);
+}
+
+/// Serve waiting requests in scheduling order while enough boxes are free.
+///
+/// The next request in line keeps its place until enough boxes are free for it, so
+/// requests for several boxes are not starved by requests for one.
+fn dispatch(state: &mut PoolState) {
+    state.waiters.retain(|waiter| !waiter.sender.is_closed());
+
+    while let Some(index) = state.next_waiter() {
+        if state.available.len() < state.waiters[index].count {
+            break;
+        }
+        let Some(waiter) = state.waiters.remove(index) else {
+            break;
+        };
+        let box_ids: Vec<u32> = state.available.drain(..waiter.count).collect();
+        // Account before sending, so boxes returned by a request that stopped waiting
+        // are reclaimed from the right tenant
+        for &box_id in &box_ids {
+            state.grant(box_id, &waiter.tenant);
+        }
+        // The request gave up waiting, try the next one
+        if let Err(returned) = waiter.sender.send(box_ids) {
+            for box_id in returned {
+                state.reclaim(box_id);
+                state.available.push_back(box_id);
+            }
+        }
+    }
+}
+
+/// A place in the wait queue. Boxes handed over after the request stopped waiting are
+/// returned to the pool instead of being lost.
+struct PendingBox {
+    receiver: oneshot::Receiver<Vec<u32>>,
+    state: Arc<Mutex<PoolState>>,
+}
+
+impl PendingBox {
+    async fn wait(mut self, max_wait: Duration) -> Option<Vec<u32>> {
+        match tokio::time::timeout(max_wait, &mut self.receiver).await {
+            Ok(received) => received.ok(),
+            Err(_) => {
+                // Boxes may have been handed over just as the deadline passed
+                self.receiver.close();
+                self.receiver.try_recv().ok()
+            }
+        }
+    }
+}
+
+impl Drop for PendingBox {
+    fn drop(&mut self) {
+        self.receiver.close();
+        if let Ok(box_ids) = self.receiver.try_recv() {
+            for box_id in box_ids {
+                release_to(&self.state, box_id);
+            }
+        }
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(size: u32, max_queue_length: usize, max_wait: Duration) -> Arc<BoxPool> {
+        Arc::new(BoxPool::new(
+            0..size,
+            max_queue_length,
+            max_wait,
+            HashMap::new(),
+        ))
+    }
+
+    fn context(tenant: &str, priority: Priority) -> SchedulingContext {
+        SchedulingContext {
+            tenant: tenant.to_string(),
+            priority,
+        }
+    }
+
+    /// Start acquiring `count` boxes in the background
+    fn spawn_acquire(
+        pool: &Arc<BoxPool>,
+        tenant: &str,
+        priority: Priority,
+        count: usize,
+    ) -> tokio::task::JoinHandle<DomainResult<Vec<u32>>> {
+        let pool = pool.clone();
+        let scheduling = context(tenant, priority);
+        tokio::spawn(async move { pool.acquire_many(&scheduling, count).await })
+    }
+
+    /// Wait until `count` requests are in line
+    async fn wait_for_waiters(pool: &BoxPool, count: usize) {
+        while pool.state.lock().unwrap().waiters.len() != count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    fn available(pool: &BoxPool) -> Vec<u32> {
+        pool.state
+            .lock()
+            .unwrap()
+            .available
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn released_box_goes_to_waiting_request() {
+        let pool = pool(1, 4, Duration::from_secs(5));
+        let box_id = pool.acquire(&context("a", Priority::Normal)).await.unwrap();
+
+        let waiter = spawn_acquire(&pool, "a", Priority::Normal, 1);
+        wait_for_waiters(&pool, 1).await;
+        pool.release(box_id).await.unwrap();
+
+        assert_eq!(waiter.await.unwrap().unwrap(), vec![box_id]);
+        assert!(available(&pool).is_empty());
+    }
+
+    #[tokio::test]
+    async fn full_queue_is_rejected() {
+        let pool = pool(1, 1, Duration::from_secs(5));
+        let box_id = pool.acquire(&context("a", Priority::Normal)).await.unwrap();
+
+        let waiter = spawn_acquire(&pool, "a", Priority::Normal, 1);
+        wait_for_waiters(&pool, 1).await;
+
+        let rejected = pool.acquire(&context("b", Priority::Interactive)).await;
+        assert!(matches!(
+            rejected,
+            Err(DomainError::BoxPoolExhausted { .. })
+        ));
+
+        pool.release(box_id).await.unwrap();
+        assert_eq!(waiter.await.unwrap().unwrap(), vec![box_id]);
+    }
+
+    #[tokio::test]
+    async fn wait_times_out() {
+        let pool = pool(1, 4, Duration::from_millis(20));
+        let box_id = pool.acquire(&context("a", Priority::Normal)).await.unwrap();
+
+        let timed_out = pool.acquire(&context("a", Priority::Normal)).await;
+        assert!(matches!(
+            timed_out,
+            Err(DomainError::BoxPoolExhausted { retry_after: 1 })
+        ));
+
+        // The abandoned place in line does not swallow the box
+        pool.release(box_id).await.unwrap();
+        assert_eq!(available(&pool), vec![box_id]);
+    }
+
+    #[tokio::test]
+    async fn box_handed_to_dropped_request_returns_to_pool() {
+        let pool = pool(1, 4, Duration::from_secs(5));
+        let box_id = pool.acquire(&context("a", Priority::Normal)).await.unwrap();
+
+        let waiter = spawn_acquire(&pool, "b", Priority::Normal, 1);
+        wait_for_waiters(&pool, 1).await;
+
+        // Hand the box over, then drop the request before it picks the box up
+        pool.release(box_id).await.unwrap();
+        assert!(available(&pool).is_empty());
+        waiter.abort();
+        assert!(waiter.await.unwrap_err().is_cancelled());
+
+        assert_eq!(available(&pool), vec![box_id]);
+        let state = pool.state.lock().unwrap();
+        assert!(state.holders.is_empty());
+        assert_eq!(state.held.get("b").copied().unwrap_or(0), 0);
+    }
+
+    #[tokio::test]
+    async fn boxes_are_acquired_together() {
+        let pool = pool(2, 4, Duration::from_secs(5));
+        let held = pool.acquire(&context("a", Priority::Normal)).await.unwrap();
+
+        // One box is free, but the pair request is first in line and keeps it
+        let pair = spawn_acquire(&pool, "b", Priority::Normal, 2);
+        wait_for_waiters(&pool, 1).await;
+        let single = spawn_acquire(&pool, "c", Priority::Normal, 1);
+        wait_for_waiters(&pool, 2).await;
+        assert_eq!(available(&pool).len(), 1);
+
+        pool.release(held).await.unwrap();
+        let pair = pair.await.unwrap().unwrap();
+        assert_eq!(pair.len(), 2);
+        wait_for_waiters(&pool, 1).await;
+
+        pool.release(pair[0]).await.unwrap();
+        assert_eq!(single.await.unwrap().unwrap(), vec![pair[0]]);
+    }
+
+    #[tokio::test]
+    async fn releasing_a_free_box_does_nothing() {
+        let pool = pool(2, 4, Duration::from_secs(5));
+        let box_id = pool.acquire(&context("a", Priority::Normal)).await.unwrap();
+
+        pool.release(box_id).await.unwrap();
+        pool.release(box_id).await.unwrap();
+        // Never acquired
+        pool.release(1).await.unwrap();
+
+        assert_eq!(available(&pool), [1, 0]);
+        let pair = pool.acquire_many(&context("a", Priority::Normal), 2).await;
+        assert_eq!(pair.unwrap(), [1, 0]);
+    }
+
+    #[tokio::test]
+    async fn more_boxes_than_the_pool_has_are_rejected() {
+        let pool = pool(2, 4, Duration::from_secs(5));
+        let result = pool.acquire_many(&context("a", Priority::Normal), 3).await;
+        assert!(matches!(result, Err(DomainError::InvalidRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn higher_priority_is_served_first() {
+        let pool = pool(1, 8, Duration::from_secs(5));
+        let box_id = pool.acquire(&context("a", Priority::Normal)).await.unwrap();
+
+        let waiters = [
+            ("batch", Priority::Batch),
+            ("normal-1", Priority::Normal),
+            ("interactive", Priority::Interactive),
+            ("normal-2", Priority::Normal),
+        ];
+        for (index, (tenant, priority)) in waiters.into_iter().enumerate() {
+            spawn_acquire(&pool, tenant, priority, 1);
+            wait_for_waiters(&pool, index + 1).await;
+        }
+
+        // Pass the box along without the waiters using it
+        let mut order = Vec::new();
+        for _ in waiters {
+            pool.release(box_id).await.unwrap();
+            order.push(pool.holder(box_id).unwrap());
+        }
+        assert_eq!(order, ["interactive", "normal-1", "normal-2", "batch"]);
+    }
+
+    #[tokio::test]
+    async fn tenants_are_served_by_weighted_usage() {
+        // Each tenant holds one box once `released` is back. With twice the weight `a` is
+        // further below its share and goes first; with equal weights `b` asked first.
+        let cases = [(2, "a"), (1, "b")];
+
+        for (weight, expected) in cases {
+            let weights = HashMap::from([("a".to_string(), weight)]);
+            let pool = Arc::new(BoxPool::new(0..3, 8, Duration::from_secs(5), weights));
+            pool.acquire(&context("a", Priority::Normal)).await.unwrap();
+            let released = pool.acquire(&context("a", Priority::Normal)).await.unwrap();
+            pool.acquire(&context("b", Priority::Normal)).await.unwrap();
+
+            spawn_acquire(&pool, "b", Priority::Normal, 1);
+            wait_for_waiters(&pool, 1).await;
+            spawn_acquire(&pool, "a", Priority::Normal, 1);
+            wait_for_waiters(&pool, 2).await;
+
+            pool.release(released).await.unwrap();
+            assert_eq!(pool.holder(released).unwrap(), expected, "weight {}", weight);
+        }
+    }
+
+    #[tokio::test]
+    async fn priority_outranks_fair_share() {
+        let pool = pool(2, 8, Duration::from_secs(5));
+        pool.acquire(&context("a", Priority::Normal)).await.unwrap();
+        let released = pool.acquire(&context("b", Priority::Normal)).await.unwrap();
+
+        // `a` holds a box and `c` none, but `a` asks with a higher priority
+        spawn_acquire(&pool, "c", Priority::Normal, 1);
+        wait_for_waiters(&pool, 1).await;
+        spawn_acquire(&pool, "a", Priority::Interactive, 1);
+        wait_for_waiters(&pool, 2).await;
+
+        pool.release(released).await.unwrap();
+        assert_eq!(pool.holder(released).unwrap(), "a");
+    }
+
+    #[tokio::test]
+    async fn tenants_at_the_same_priority_take_turns() {
+        let pool = pool(2, 8, Duration::from_secs(5));
+        let pair = pool
+            .acquire_many(&context("a", Priority::Normal), 2)
+            .await
+            .unwrap();
+
+        // `a` asks first, but keeps getting a box only once `b` got as many
+        for (index, tenant) in ["a", "a", "b", "b"].into_iter().enumerate() {
+            spawn_acquire(&pool, tenant, Priority::Normal, 1);
+            wait_for_waiters(&pool, index + 1).await;
+        }
+
+        // Pass the boxes along without the waiters using them
+        let mut order = Vec::new();
+        for box_id in [pair[0], pair[1], pair[0], pair[1]] {
+            pool.release(box_id).await.unwrap();
+            order.push(pool.holder(box_id).unwrap());
+        }
+        assert_eq!(order, ["b", "a", "b", "a"]);
+    }
+}
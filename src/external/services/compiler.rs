@@ -1,37 +1,246 @@
-use crate::domain::entities::Language;
+use crate::config::CompileConfig;
+use crate::domain::entities::{Language, SchedulingContext};
 use crate::domain::error::{DomainError, DomainResult};
-use crate::domain::services::CompilerService;
+use crate::domain::services::{CompileOutput, CompilerService};
 use crate::external::process::ProcessExecutor;
+use crate::external::services::box_pool::BoxPool;
 use async_trait::async_trait;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tempfile::TempDir;
 
+/// Name of the compiler script inside the compile box
+const COMPILER_FILE_NAME: &str = ".compiler";
+
+/// Names of the files inside the compile box that compiler output is redirected to
+const STDOUT_FILE_NAME: &str = ".compile-stdout";
+const STDERR_FILE_NAME: &str = ".compile-stderr";
+
+/// Search path for the compiler script, since isolate clears the environment
+const COMPILE_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+/// Compiles programs inside their own isolate boxes, with dedicated limits
+#[derive(Clone)]
 pub struct CompilerServiceImpl {
-    process_executor: ProcessExecutor,
+    box_pool: Arc<BoxPool>,
+    config: CompileConfig,
 }
 
 impl CompilerServiceImpl {
-    pub fn new() -> Self {
-        Self {
-            process_executor: ProcessExecutor::new(),
-        }
+    pub fn new(box_pool: Arc<BoxPool>, config: CompileConfig) -> Self {
+        Self { box_pool, config }
     }
 
-    fn sanitize_stderr(&self, stderr: &str, source_path: &PathBuf) -> String {
+    fn sanitize_stderr(&self, stderr: &str, source_path: &Path, output_dir: &Path) -> String {
         let source_str = source_path.to_str().unwrap_or("");
-        
+
         // Replace file paths with *** in error messages, keeping only the extension
-        if let Some(extension) = source_path.extension() {
+        let sanitized = if let Some(extension) = source_path.extension() {
             let replacement = format!("*******.{}", extension.to_string_lossy());
             stderr.replace(source_str, &replacement)
         } else {
             stderr.replace(source_str, "*******")
+        };
+
+        // Mask the working directory of any other submitted file
+        match output_dir.to_str() {
+            Some(dir_str) if !dir_str.is_empty() => sanitized.replace(dir_str, "*******"),
+            _ => sanitized,
         }
     }
-}
 
-impl Default for CompilerServiceImpl {
-    fn default() -> Self {
-        Self::new()
+    async fn run_command(&self, args: &[&str], action: &str) -> DomainResult<()> {
+        let (_, stderr, exit_code) = ProcessExecutor::new().execute_command("sudo", args).await?;
+
+        if exit_code != 0 {
+            return Err(DomainError::SandboxError(format!(
+                "Failed to {}: {}",
+                action, stderr
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Read up to `max_output_size` bytes of a compiler output file in the box
+    async fn read_output(&self, box_root: &str, name: &str) -> String {
+        let path = format!("{}/{}", box_root, name);
+        match ProcessExecutor::new()
+            .read_regular_file("sudo", &path, self.config.max_output_size)
+            .await
+        {
+            Ok(Some((output, _))) => String::from_utf8_lossy(&output).into_owned(),
+            _ => String::new(),
+        }
+    }
+
+    /// Build the `isolate --run` command line compiling `source` (relative to the box root)
+    fn build_compile_args(&self, box_id: u32, meta_path: &str, source: &str) -> Vec<String> {
+        let config = &self.config;
+        let mut args = vec![
+            "isolate".to_string(),
+            "-b".to_string(),
+            box_id.to_string(),
+            "--cg".to_string(),
+            "--silent".to_string(),
+        ];
+
+        if config.cg_mem > 0 {
+            args.push(format!("--cg-mem={}", config.cg_mem));
+        }
+        if config.time > 0 {
+            args.push(format!("--time={}", config.time));
+        }
+        if config.wall_time > 0 {
+            args.push(format!("--wall-time={}", config.wall_time));
+        }
+        if config.fsize > 0 {
+            args.push(format!("--fsize={}", config.fsize));
+        }
+        if config.processes > 0 {
+            args.push(format!("--processes={}", config.processes));
+        } else {
+            args.push("--processes".to_string());
+        }
+
+        args.extend([
+            "--open-files=0".to_string(),
+            format!("--env=PATH={}", COMPILE_PATH),
+            "--env=HOME=/box".to_string(),
+            format!("--stdout={}", STDOUT_FILE_NAME),
+            format!("--stderr={}", STDERR_FILE_NAME),
+            format!("--meta={}", meta_path),
+            "--run".to_string(),
+            "--".to_string(),
+            "/usr/bin/bash".to_string(),
+            COMPILER_FILE_NAME.to_string(),
+            format!("/box/{}", source),
+            "/box".to_string(),
+        ]);
+
+        args
+    }
+
+    /// Copy the sources into an initialized box, compile them and copy the binary out
+    async fn compile_in_box(
+        &self,
+        box_id: u32,
+        language: &Language,
+        source: &Path,
+        output_dir: &Path,
+    ) -> DomainResult<CompileOutput> {
+        let box_id_str = box_id.to_string();
+        let box_root = format!("/var/lib/isolate/{}/box", box_id);
+        let output_str = output_dir
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid output path".to_string()))?;
+        let source_str = source
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid source path".to_string()))?;
+        let compiler_path = language.compiler_path();
+        let compiler_str = compiler_path
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid compiler path".to_string()))?;
+
+        log::debug!("Initializing compile box ID: {}", box_id);
+        self.run_command(
+            &["isolate", "-b", &box_id_str, "--cg", "--init"],
+            "initialize compile box",
+        )
+        .await?;
+
+        // At this point the output directory only holds the submitted sources
+        self.run_command(
+            &["cp", "-r", &format!("{}/.", output_str), &box_root],
+            "copy sources to compile box",
+        )
+        .await?;
+        self.run_command(
+            &[
+                "cp",
+                compiler_str,
+                &format!("{}/{}", box_root, COMPILER_FILE_NAME),
+            ],
+            "copy compiler to compile box",
+        )
+        .await?;
+
+        let meta_dir = TempDir::new()?;
+        let meta_path = meta_dir.path().join("meta.txt");
+        let meta_str = meta_path
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid metadata path".to_string()))?;
+
+        log::debug!("Compiling {} in box ID: {}", source_str, box_id);
+        let args = self.build_compile_args(box_id, meta_str, source_str);
+        ProcessExecutor::new()
+            .execute_command("sudo", &args.iter().map(|s| s.as_str()).collect::<Vec<_>>())
+            .await?;
+
+        let meta = parse_meta(
+            &tokio::fs::read_to_string(&meta_path)
+                .await
+                .unwrap_or_default(),
+        );
+        let exit_code: i32 = meta
+            .get("exitcode")
+            .and_then(|code| code.parse().ok())
+            .unwrap_or(0);
+        if let Some(error) = stopped_compilation(&meta, exit_code) {
+            return Err(error);
+        }
+
+        // Compiler messages refer to the sources by their paths inside the box
+        let box_source = PathBuf::from(format!("/box/{}", source_str));
+        let stderr = self.read_output(&box_root, STDERR_FILE_NAME).await;
+        let stderr = self.sanitize_stderr(&stderr, &box_source, Path::new("/box"));
+
+        if exit_code != 0 {
+            return Ok(CompileOutput {
+                binary_path: None,
+                exit_code,
+                stderr,
+            });
+        }
+
+        // The compiler script outputs the compiled binary to the box root as bin
+        let binary_path = output_dir.join("bin");
+        let binary_str = binary_path
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid binary path".to_string()))?;
+        // Copy a link left in place of bin as a link, and only accept a regular file
+        let copied = self
+            .run_command(
+                &[
+                    "cp",
+                    "--no-dereference",
+                    &format!("{}/bin", box_root),
+                    binary_str,
+                ],
+                "copy binary",
+            )
+            .await
+            .is_ok();
+        let is_regular = copied
+            && tokio::fs::symlink_metadata(&binary_path)
+                .await
+                .map(|metadata| metadata.file_type().is_file())
+                .unwrap_or(false);
+        if !is_regular {
+            let _ = tokio::fs::remove_file(&binary_path).await;
+            let stdout = self.read_output(&box_root, STDOUT_FILE_NAME).await;
+            return Err(DomainError::CompilationFailed(format!(
+                "Compiled binary not found. Stdout: {}, Stderr: {}",
+                stdout, stderr
+            )));
+        }
+
+        Ok(CompileOutput {
+            binary_path: Some(binary_path),
+            exit_code,
+            stderr,
+        })
     }
 }
 
@@ -40,11 +249,11 @@ impl CompilerService for CompilerServiceImpl {
     async fn compile(
         &self,
         language: &Language,
-        source_path: &PathBuf,
-        output_dir: &PathBuf,
-    ) -> DomainResult<PathBuf> {
+        source_path: &Path,
+        output_dir: &Path,
+    ) -> DomainResult<CompileOutput> {
         let compiler_path = language.compiler_path();
-        
+
         if !compiler_path.exists() {
             return Err(DomainError::CompilationFailed(format!(
                 "Compiler not found: {}",
@@ -52,39 +261,214 @@ impl CompilerService for CompilerServiceImpl {
             )));
         }
 
-        let source_str = source_path
-            .to_str()
-            .ok_or_else(|| DomainError::Internal("Invalid source path".to_string()))?;
+        let source = source_path
+            .strip_prefix(output_dir)
+            .map_err(|_| {
+                DomainError::Internal("Source is outside the output directory".to_string())
+            })?
+            .to_path_buf();
 
-        let (stdout, stderr, exit_code) = self
-            .process_executor
-            .execute_command(
-                compiler_path.to_str().unwrap(),
-                &[source_str],
-            )
-            .await?;
+        // Compile in a separate task so the box is always cleaned up and released, even when
+        // the caller stops waiting
+        let compiler = self.clone();
+        let language = language.clone();
+        let output_dir = output_dir.to_path_buf();
+        tokio::spawn(async move {
+            let box_id = compiler
+                .box_pool
+                .acquire(&SchedulingContext::default())
+                .await?;
+            let result = compiler
+                .compile_in_box(box_id, &language, &source, &output_dir)
+                .await;
 
-        if exit_code != 0 {
-            let sanitized_stderr = self.sanitize_stderr(&stderr, source_path);
-            return Err(DomainError::CompilationFailed(format!(
-                "Compilation failed with exit code {}: {}",
-                exit_code, sanitized_stderr
-            )));
+            let box_id_str = box_id.to_string();
+            if let Err(e) = compiler
+                .run_command(
+                    &["isolate", "-b", &box_id_str, "--cg", "--cleanup"],
+                    "clean up compile box",
+                )
+                .await
+            {
+                log::warn!("{}", e);
+            }
+            compiler.box_pool.release(box_id).await?;
+
+            result
+        })
+        .await
+        .map_err(|e| DomainError::Internal(format!("Compile task failed: {}", e)))?
+    }
+}
+
+/// Parse the `key:value` lines of an isolate meta file
+fn parse_meta(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Error for a compiler run that the sandbox stopped instead of letting it exit, if any
+fn stopped_compilation(meta: &HashMap<String, String>, exit_code: i32) -> Option<DomainError> {
+    let message = meta.get("message").map(String::as_str);
+    match meta.get("status").map(String::as_str) {
+        Some("XX") => Some(DomainError::SandboxError(format!(
+            "Compile box failed: {}",
+            message.unwrap_or("unknown error")
+        ))),
+        Some("TO") => Some(DomainError::CompilationFailed(
+            "Compilation exceeded the time limit".to_string(),
+        )),
+        Some("SG") if exit_code == 0 => Some(DomainError::CompilationFailed(format!(
+            "Compiler was killed: {}",
+            message.unwrap_or("unknown signal")
+        ))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Options setting the limits of the compile command
+    const LIMIT_OPTIONS: [&str; 5] = [
+        "--cg-mem",
+        "--time",
+        "--wall-time",
+        "--fsize",
+        "--processes",
+    ];
+
+    fn config() -> CompileConfig {
+        CompileConfig {
+            box_count: 1,
+            cg_mem: 524288,
+            time: 10,
+            wall_time: 20,
+            processes: 64,
+            fsize: 102400,
+            max_output_size: 1024,
         }
+    }
 
-        // The compiler script outputs the compiled binary to output_dir/bin
-        let binary_path = output_dir.join("bin");
-        
-        if !binary_path.exists() {
-            return Err(DomainError::CompilationFailed(format!(
-                "Compiled binary not found at: {}. Stdout: {}, Stderr: {}",
-                binary_path.display(),
-                stdout,
-                stderr
-            )));
+    fn compiler(config: CompileConfig) -> CompilerServiceImpl {
+        let box_pool = BoxPool::new(0..1, 1, Duration::from_secs(1), HashMap::new());
+        CompilerServiceImpl::new(Arc::new(box_pool), config)
+    }
+
+    /// Limit options of the compile command, in order
+    fn limit_args(compiler: &CompilerServiceImpl) -> Vec<String> {
+        compiler
+            .build_compile_args(7, "/tmp/meta.txt", "main.c")
+            .into_iter()
+            .filter(|arg| LIMIT_OPTIONS.contains(&arg.split('=').next().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn build_compile_args_sets_configured_limits() {
+        let compiler = compiler(config());
+        assert_eq!(
+            limit_args(&compiler),
+            [
+                "--cg-mem=524288",
+                "--time=10",
+                "--wall-time=20",
+                "--fsize=102400",
+                "--processes=64",
+            ]
+        );
+
+        let args = compiler.build_compile_args(7, "/tmp/meta.txt", "src/main.c");
+        assert_eq!(args[..3], ["isolate", "-b", "7"]);
+        assert!(args.contains(&"--meta=/tmp/meta.txt".to_string()));
+        assert_eq!(args[args.len() - 2..], ["/box/src/main.c", "/box"]);
+    }
+
+    #[test]
+    fn build_compile_args_leaves_out_unlimited_limits() {
+        // Each case sets one limit to unlimited
+        type Unlimit = fn(&mut CompileConfig);
+        let cases: [(&str, Unlimit); 4] = [
+            ("--cg-mem", |config| config.cg_mem = 0),
+            ("--time", |config| config.time = 0),
+            ("--wall-time", |config| config.wall_time = 0),
+            ("--fsize", |config| config.fsize = 0),
+        ];
+
+        for (option, unlimit) in cases {
+            let mut config = config();
+            unlimit(&mut config);
+            let args = limit_args(&compiler(config));
+            let expected: Vec<&str> = LIMIT_OPTIONS
+                .iter()
+                .filter(|&&limit| limit != option)
+                .copied()
+                .collect();
+            let options: Vec<&str> = args
+                .iter()
+                .map(|arg| arg.split('=').next().unwrap())
+                .collect();
+            assert_eq!(options, expected, "{:?}", args);
         }
+    }
 
-        Ok(binary_path)
+    #[test]
+    fn build_compile_args_allows_any_processes_without_a_limit() {
+        // isolate takes `--processes` without a value as no limit
+        let args = limit_args(&compiler(CompileConfig {
+            processes: 0,
+            ..config()
+        }));
+        assert_eq!(args.last().map(String::as_str), Some("--processes"));
     }
-}
 
+    #[test]
+    fn parse_meta_reads_key_value_lines() {
+        let meta = parse_meta(
+            "time:0.5\nstatus:SG\nexitsig:9\nmessage:Caught fatal signal 9: Killed\n\nbogus\n",
+        );
+
+        assert_eq!(meta.len(), 4);
+        assert_eq!(meta["time"], "0.5");
+        assert_eq!(meta["status"], "SG");
+        assert_eq!(meta["exitsig"], "9");
+        assert_eq!(meta["message"], "Caught fatal signal 9: Killed");
+    }
+
+    #[test]
+    fn stopped_compilation_maps_sandbox_statuses() {
+        let timed_out = parse_meta("status:TO\nmessage:Time limit exceeded\n");
+        assert!(matches!(
+            stopped_compilation(&timed_out, 0),
+            Some(DomainError::CompilationFailed(message)) if message.contains("time limit")
+        ));
+
+        let killed = parse_meta("status:SG\nexitsig:9\nmessage:Caught fatal signal 9\n");
+        assert!(matches!(
+            stopped_compilation(&killed, 0),
+            Some(DomainError::CompilationFailed(message)) if message.contains("fatal signal 9")
+        ));
+
+        let failed = parse_meta("status:XX\nmessage:Cannot run proxy\n");
+        assert!(matches!(
+            stopped_compilation(&failed, 0),
+            Some(DomainError::SandboxError(_))
+        ));
+    }
+
+    #[test]
+    fn stopped_compilation_leaves_compiler_exits_to_the_caller() {
+        let exited = parse_meta("status:RE\nexitcode:1\n");
+        assert!(stopped_compilation(&exited, 1).is_none());
+        assert!(stopped_compilation(&HashMap::new(), 0).is_none());
+
+        // A signal with an exit code is reported by the compiler script itself
+        let signalled = parse_meta("status:SG\nexitcode:1\n");
+        assert!(stopped_compilation(&signalled, 1).is_none());
+    }
+}
@@ -0,0 +1,454 @@
+use crate::config::CompileConfig;
+use crate::domain::entities::Language;
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::services::{CompileCacheStats, CompileOutput, CompilerService};
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tokio::fs;
+use uuid::Uuid;
+
+/// Compiler decorator reusing the binaries of previous compilations of identical programs.
+///
+/// Artifacts are keyed by a hash of the language, its compiler script, the compile limits,
+/// the entry point and every source file, and evicted once they are older than `max_age`
+/// or, least recently used first, when the cache grows beyond `max_size` bytes.
+pub struct CachingCompilerService {
+    inner: Arc<dyn CompilerService>,
+    /// Compile settings of `inner`, which can change the outcome of a compilation
+    compile_config: CompileConfig,
+    /// Private directory holding the artifacts, removed with everything in it on drop
+    dir: TempDir,
+    max_size: u64,
+    max_age: Duration,
+    index: Mutex<CacheIndex>,
+}
+
+#[derive(Default)]
+struct CacheIndex {
+    entries: HashMap<String, CacheEntry>,
+    size: u64,
+    hits: u64,
+    misses: u64,
+}
+
+struct CacheEntry {
+    size: u64,
+    /// Compiler messages of the cached compilation, such as warnings
+    stderr: String,
+    created_at: Instant,
+    last_used: Instant,
+}
+
+impl CacheIndex {
+    fn remove(&mut self, key: &str) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.size -= entry.size;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drop expired entries, then the least recently used ones until the cache fits
+    /// `max_size`, returning the keys of the removed entries
+    fn evict(&mut self, max_size: u64, max_age: Duration) -> Vec<String> {
+        let mut evicted: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.created_at.elapsed() > max_age)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &evicted {
+            self.remove(key);
+        }
+
+        while self.size > max_size {
+            let Some(key) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.remove(&key);
+            evicted.push(key);
+        }
+
+        evicted
+    }
+}
+
+impl CachingCompilerService {
+    /// Create the cache in a new private directory (mode 0700) inside `dir`.
+    ///
+    /// Nothing else in `dir` is touched, so it may be shared or even writable by others.
+    pub fn new(
+        inner: Arc<dyn CompilerService>,
+        compile_config: CompileConfig,
+        dir: PathBuf,
+        max_size: u64,
+        max_age: Duration,
+    ) -> DomainResult<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let dir = tempfile::Builder::new()
+            .prefix("compile-cache-")
+            .permissions(std::fs::Permissions::from_mode(0o700))
+            .tempdir_in(&dir)?;
+        log::debug!("Caching compiled artifacts in {}", dir.path().display());
+
+        Ok(Self {
+            inner,
+            compile_config,
+            dir,
+            max_size,
+            max_age,
+            index: Mutex::new(CacheIndex::default()),
+        })
+    }
+
+    /// Compiler messages of the unexpired artifact cached under `key`, counting the hit or
+    /// miss
+    fn lookup(&self, key: &str) -> Option<String> {
+        let mut index = self.index.lock().unwrap();
+        let max_age = self.max_age;
+        let found = match index.entries.get_mut(key) {
+            Some(entry) if entry.created_at.elapsed() <= max_age => {
+                entry.last_used = Instant::now();
+                Some(entry.stderr.clone())
+            }
+            _ => None,
+        };
+
+        if found.is_some() {
+            index.hits += 1;
+        } else {
+            index.misses += 1;
+        }
+        found
+    }
+
+    /// Path of a file in the cache directory
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.path().join(name)
+    }
+
+    /// Copy a freshly compiled binary into the cache
+    async fn store(&self, key: &str, binary_path: &Path, stderr: &str) -> DomainResult<()> {
+        // Write under a temporary name so a concurrent hit never copies a partial file
+        let temp_path = self.path(&format!("{}.tmp-{}", key, Uuid::new_v4()));
+        let size = fs::copy(binary_path, &temp_path).await?;
+        if size > self.max_size {
+            fs::remove_file(&temp_path).await?;
+            return Ok(());
+        }
+        fs::rename(&temp_path, self.path(key)).await?;
+
+        let evicted = {
+            let mut index = self.index.lock().unwrap();
+            index.remove(key);
+            let now = Instant::now();
+            index.entries.insert(
+                key.to_string(),
+                CacheEntry {
+                    size,
+                    stderr: stderr.to_string(),
+                    created_at: now,
+                    last_used: now,
+                },
+            );
+            index.size += size;
+            index.evict(self.max_size, self.max_age)
+        };
+
+        for key in evicted {
+            log::debug!("Evicting compiled artifact {}", key);
+            if let Err(e) = fs::remove_file(self.path(&key)).await {
+                log::warn!("Failed to remove compiled artifact {}: {}", key, e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CompilerService for CachingCompilerService {
+    async fn compile(
+        &self,
+        language: &Language,
+        source_path: &Path,
+        output_dir: &Path,
+    ) -> DomainResult<CompileOutput> {
+        let key = {
+            let language = language.clone();
+            let compile_config = self.compile_config.clone();
+            let source_path = source_path.to_path_buf();
+            let output_dir = output_dir.to_path_buf();
+            tokio::task::spawn_blocking(move || {
+                artifact_key(&language, &compile_config, &source_path, &output_dir)
+            })
+            .await
+            .map_err(|e| DomainError::Internal(format!("Failed to hash sources: {}", e)))?
+        };
+
+        // Without a key the program is still compiled, just not cached
+        let key = match key {
+            Ok(key) => key,
+            Err(e) => {
+                log::warn!("Failed to compute compile cache key: {}", e);
+                return self.inner.compile(language, source_path, output_dir).await;
+            }
+        };
+
+        if let Some(stderr) = self.lookup(&key) {
+            let binary_path = output_dir.join("bin");
+            match fs::copy(self.path(&key), &binary_path).await {
+                Ok(_) => {
+                    log::debug!("Reusing compiled artifact {}", key);
+                    return Ok(CompileOutput {
+                        binary_path: Some(binary_path),
+                        exit_code: 0,
+                        stderr,
+                    });
+                }
+                Err(e) => {
+                    log::warn!("Failed to reuse compiled artifact {}: {}", key, e);
+                    self.index.lock().unwrap().remove(&key);
+                }
+            }
+        }
+
+        // Only successful compilations are cached, rejected programs are compiled again
+        let output = self
+            .inner
+            .compile(language, source_path, output_dir)
+            .await?;
+        if let Some(binary_path) = &output.binary_path {
+            if let Err(e) = self.store(&key, binary_path, &output.stderr).await {
+                log::warn!("Failed to cache compiled artifact {}: {}", key, e);
+            }
+        }
+
+        Ok(output)
+    }
+
+    fn cache_stats(&self) -> Option<CompileCacheStats> {
+        let index = self.index.lock().unwrap();
+        Some(CompileCacheStats {
+            hits: index.hits,
+            misses: index.misses,
+            entries: index.entries.len(),
+            size: index.size,
+        })
+    }
+}
+
+/// Hash everything that determines the output of a compilation
+fn artifact_key(
+    language: &Language,
+    compile_config: &CompileConfig,
+    source_path: &Path,
+    output_dir: &Path,
+) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    hash_field(&mut hasher, language.name.as_bytes());
+    hash_field(&mut hasher, &std::fs::read(language.compiler_path())?);
+
+    // Limits decide whether a compilation succeeds, the output size what stderr is kept
+    let limits = [
+        compile_config.cg_mem,
+        compile_config.time,
+        compile_config.wall_time,
+        compile_config.processes,
+        compile_config.fsize,
+    ];
+    for limit in limits {
+        hash_field(&mut hasher, &limit.to_le_bytes());
+    }
+    hash_field(&mut hasher, &compile_config.max_output_size.to_le_bytes());
+
+    let entry_point = source_path.strip_prefix(output_dir).unwrap_or(source_path);
+    hash_field(&mut hasher, entry_point.to_string_lossy().as_bytes());
+
+    let mut files = Vec::new();
+    collect_files(output_dir, output_dir, &mut files)?;
+    files.sort();
+    for file in files {
+        hash_field(&mut hasher, file.to_string_lossy().as_bytes());
+        hash_field(&mut hasher, &std::fs::read(output_dir.join(&file))?);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hash a length-prefixed field so that adjacent fields cannot run into each other
+fn hash_field(hasher: &mut Sha256, data: &[u8]) {
+    hasher.update((data.len() as u64).to_le_bytes());
+    hasher.update(data);
+}
+
+/// Collect the paths of all files below `dir`, relative to `root`
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Compiler writing a fixed binary and counting its compilations
+    #[derive(Default)]
+    struct FakeCompiler {
+        compilations: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl CompilerService for FakeCompiler {
+        async fn compile(
+            &self,
+            _language: &Language,
+            _source_path: &Path,
+            output_dir: &Path,
+        ) -> DomainResult<CompileOutput> {
+            self.compilations.fetch_add(1, Ordering::SeqCst);
+            let binary_path = output_dir.join("bin");
+            fs::write(&binary_path, b"binary").await?;
+            Ok(CompileOutput {
+                binary_path: Some(binary_path),
+                exit_code: 0,
+                stderr: "warning".to_string(),
+            })
+        }
+    }
+
+    fn compile_config() -> CompileConfig {
+        CompileConfig {
+            box_count: 4,
+            cg_mem: 524288,
+            time: 10,
+            wall_time: 20,
+            processes: 64,
+            fsize: 102400,
+            max_output_size: 65536,
+        }
+    }
+
+    /// A language with a compiler script and a program to compile
+    fn program(root: &Path) -> (Language, PathBuf, PathBuf) {
+        let language = Language::new("c".to_string(), "c".to_string(), root.join("c"));
+        std::fs::create_dir_all(&language.config_dir).unwrap();
+        std::fs::write(language.compiler_path(), "gcc main.c").unwrap();
+
+        let source_dir = root.join("source");
+        std::fs::create_dir_all(&source_dir).unwrap();
+        let source_path = source_dir.join("main.c");
+        std::fs::write(&source_path, "int main() {}").unwrap();
+        (language, source_path, source_dir)
+    }
+
+    #[test]
+    fn cache_directory_is_private_and_leaves_others_alone() {
+        let parent = tempfile::tempdir().unwrap();
+        let other = parent.path().join("other");
+        std::fs::write(&other, "not ours").unwrap();
+
+        let cache = CachingCompilerService::new(
+            Arc::new(FakeCompiler::default()),
+            compile_config(),
+            parent.path().to_path_buf(),
+            1024,
+            Duration::from_secs(60),
+        )
+        .unwrap();
+        let dir = cache.dir.path().to_path_buf();
+        assert_eq!(dir.parent(), Some(parent.path()));
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        drop(cache);
+        assert!(!dir.exists());
+        assert!(other.exists());
+    }
+
+    #[test]
+    fn artifact_key_depends_on_compile_limits() {
+        let root = tempfile::tempdir().unwrap();
+        let (language, source_path, source_dir) = program(root.path());
+        let key = |config: &CompileConfig| {
+            artifact_key(&language, config, &source_path, &source_dir).unwrap()
+        };
+
+        let base = key(&compile_config());
+        let changes: [fn(&mut CompileConfig); 6] = [
+            |config| config.cg_mem += 1,
+            |config| config.time += 1,
+            |config| config.wall_time += 1,
+            |config| config.processes += 1,
+            |config| config.fsize += 1,
+            |config| config.max_output_size += 1,
+        ];
+        for (index, change) in changes.iter().enumerate() {
+            let mut config = compile_config();
+            change(&mut config);
+            assert_ne!(key(&config), base, "change {}", index);
+        }
+
+        // The number of compile boxes does not affect the result
+        let mut config = compile_config();
+        config.box_count += 1;
+        assert_eq!(key(&config), base);
+
+        std::fs::write(&source_path, "int main() { return 1; }").unwrap();
+        assert_ne!(key(&compile_config()), base);
+    }
+
+    #[tokio::test]
+    async fn identical_programs_are_compiled_once() {
+        let root = tempfile::tempdir().unwrap();
+        let (language, source_path, _) = program(root.path());
+        let inner = Arc::new(FakeCompiler::default());
+        let cache = CachingCompilerService::new(
+            inner.clone(),
+            compile_config(),
+            root.path().join("cache"),
+            1024,
+            Duration::from_secs(60),
+        )
+        .unwrap();
+
+        for _ in 0..2 {
+            let output_dir = tempfile::tempdir().unwrap();
+            std::fs::copy(&source_path, output_dir.path().join("main.c")).unwrap();
+            let source = output_dir.path().join("main.c");
+            let output = cache
+                .compile(&language, &source, output_dir.path())
+                .await
+                .unwrap();
+            let binary = std::fs::read(output.binary_path.unwrap()).unwrap();
+            assert_eq!(binary, b"binary");
+            assert_eq!(output.stderr, "warning");
+        }
+
+        assert_eq!(inner.compilations.load(Ordering::SeqCst), 1);
+        let stats = cache.cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+    }
+}
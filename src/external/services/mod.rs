@@ -1,7 +1,11 @@
 pub mod box_pool;
 pub mod compiler;
+pub mod compiler_cache;
 pub mod sandbox;
+pub mod webhook;
 
 pub use compiler::CompilerServiceImpl;
+pub use compiler_cache::CachingCompilerService;
 pub use sandbox::IsolateSandboxService;
+pub use webhook::HttpWebhookSender;
 
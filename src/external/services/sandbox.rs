@@ -1,14 +1,34 @@
-use crate::domain::entities::ExecutionMetadata;
+use crate::domain::entities::{
+    ArchiveFormat, BoxEntry, BoxEntryKind, ExecutionMetadata, OutputChunk, OutputSize,
+    ResourceLimitOverrides, ResourceLimits, SchedulingContext,
+};
 use crate::domain::error::{DomainError, DomainResult};
-use crate::domain::services::{SandboxExecutionConfig, SandboxExecutionResult, SandboxService};
+use crate::domain::services::{
+    FileReader, InteractiveExecutionResult, SandboxExecutionConfig, SandboxExecutionResult,
+    SandboxService,
+};
 use crate::external::file_system::FileSystem;
 use crate::external::process::ProcessExecutor;
 use crate::external::services::box_pool::BoxPool;
 use crate::config::SandboxConfig;
 use async_trait::async_trait;
+use chrono::{DateTime, Datelike, Timelike};
 use regex::Regex;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tar::EntryType;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Name of the file inside the box that standard input is redirected from
+const STDIN_FILE_NAME: &str = ".stdin";
+
+/// Names of the files inside the box that standard output and error are redirected to
+const STDOUT_FILE_NAME: &str = ".stdout";
+const STDERR_FILE_NAME: &str = ".stderr";
 
 pub struct IsolateSandboxService {
     box_pool: Arc<BoxPool>,
@@ -18,9 +38,9 @@ pub struct IsolateSandboxService {
 }
 
 impl IsolateSandboxService {
-    pub fn new(pool_size: u32, config: SandboxConfig) -> Self {
+    pub fn new(box_pool: Arc<BoxPool>, config: SandboxConfig) -> Self {
         Self {
-            box_pool: Arc::new(BoxPool::new(pool_size)),
+            box_pool,
             process_executor: ProcessExecutor::new(),
             file_system: FileSystem::new(),
             config,
@@ -58,14 +78,92 @@ impl IsolateSandboxService {
         Ok(())
     }
 
+    /// Fail with `BoxNotFound` unless the box has been initialized
+    async fn ensure_box(&self, box_id: u32) -> DomainResult<()> {
+        let box_path = format!("/var/lib/isolate/{}/box", box_id);
+        let (_, _, exit_code) = self
+            .process_executor
+            .execute_command("sudo", &["test", "-d", &box_path])
+            .await?;
+
+        if exit_code != 0 {
+            return Err(DomainError::BoxNotFound(box_id));
+        }
+
+        Ok(())
+    }
+
+    /// Resolve a path inside the box, following symlinks, to a path relative to the box root.
+    ///
+    /// File operations run as root, so a symlink left in the box by a program must not lead
+    /// them outside of it.
+    async fn resolve_box_path(&self, box_id: u32, path: &str) -> DomainResult<String> {
+        let box_root = format!("/var/lib/isolate/{}/box", box_id);
+        let (resolved, stderr, exit_code) = self
+            .process_executor
+            .execute_command("sudo", &["realpath", "-m", "--", &format!("{}/{}", box_root, path)])
+            .await?;
+
+        if exit_code != 0 {
+            return Err(DomainError::SandboxError(format!(
+                "Failed to resolve path in box: {}",
+                stderr
+            )));
+        }
+
+        let resolved = resolved.trim_end_matches('\n');
+        match resolved.strip_prefix(&box_root) {
+            Some("") => Ok(String::new()),
+            Some(relative) if relative.starts_with('/') => Ok(relative[1..].to_string()),
+            _ => Err(DomainError::InvalidRequest(format!(
+                "Path '{}' leads outside the box",
+                path
+            ))),
+        }
+    }
+
+    /// Copy a host file into the box as `dest_name`, relative to the box root.
+    ///
+    /// Files left by earlier runs of a reused box are untrusted: the parent directory is
+    /// resolved and checked to stay inside the box before it is created, and an existing
+    /// destination is removed rather than written through, so links never redirect the copy.
     async fn copy_to_sandbox(
         &self,
         box_id: u32,
-        source: &PathBuf,
+        source: &Path,
         dest_name: &str,
     ) -> DomainResult<()> {
-        let sandbox_path = PathBuf::from(format!("/var/lib/isolate/{}/box/{}", box_id, dest_name));
-        
+        let dest = Path::new(dest_name);
+        let name = dest
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| DomainError::Internal("Invalid dest path".to_string()))?;
+
+        let mut sandbox_path = PathBuf::from(format!("/var/lib/isolate/{}/box", box_id));
+        if let Some(parent) = dest.parent().and_then(|parent| parent.to_str()) {
+            if !parent.is_empty() {
+                sandbox_path.push(self.resolve_box_path(box_id, parent).await?);
+            }
+        }
+
+        // Create intermediate directories for nested destinations
+        if dest_name.contains('/') {
+            if let Some(parent) = sandbox_path.to_str() {
+                let (_, stderr, exit_code) = self
+                    .process_executor
+                    .execute_command("sudo", &["mkdir", "-p", "--", parent])
+                    .await?;
+
+                if exit_code != 0 {
+                    return Err(DomainError::SandboxError(format!(
+                        "Failed to create directory in sandbox: {}",
+                        stderr
+                    )));
+                }
+            }
+        }
+        sandbox_path.push(name);
+
         let source_str = source
             .to_str()
             .ok_or_else(|| DomainError::Internal("Invalid source path".to_string()))?;
@@ -73,9 +171,21 @@ impl IsolateSandboxService {
             .to_str()
             .ok_or_else(|| DomainError::Internal("Invalid dest path".to_string()))?;
 
+        // An existing directory is refused, anything else is replaced instead of opened
         let (_, stderr, exit_code) = self
             .process_executor
-            .execute_command("sudo", &["cp", source_str, dest_str])
+            .execute_command(
+                "sudo",
+                &[
+                    "cp",
+                    "--no-dereference",
+                    "--remove-destination",
+                    "--no-target-directory",
+                    "--",
+                    source_str,
+                    dest_str,
+                ],
+            )
             .await?;
 
         if exit_code != 0 {
@@ -124,12 +234,43 @@ impl IsolateSandboxService {
 
     async fn run_in_sandbox(
         &self,
-        box_id: u32,
+        config: &SandboxExecutionConfig,
         site_packages: &str,
-        metadata_path: &PathBuf,
     ) -> DomainResult<(String, String, i32)> {
-        let box_id_str = box_id.to_string();
-        let meta_path_str = metadata_path
+        let args = self.build_run_args(config, site_packages, true)?;
+
+        self.process_executor
+            .execute_cancellable(
+                "sudo",
+                &args.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+                &config.cancel,
+            )
+            .await
+    }
+
+    /// Reset a box whose run was cancelled, killing anything left behind in it
+    async fn discard_cancelled(&self, box_id: u32) -> DomainError {
+        log::debug!("Execution in box ID {} was cancelled, cleaning up", box_id);
+        if let Err(e) = self.cleanup_sandbox(box_id).await {
+            log::warn!("Failed to clean up cancelled box {}: {}", box_id, e);
+        }
+        DomainError::Cancelled
+    }
+
+    /// Build the `isolate --run` command line (without the leading `sudo`).
+    ///
+    /// With `redirect_output`, the program writes stdout and stderr to files in the box
+    /// instead of the pipes of the `isolate` process.
+    fn build_run_args(
+        &self,
+        config: &SandboxExecutionConfig,
+        site_packages: &str,
+        redirect_output: bool,
+    ) -> DomainResult<Vec<String>> {
+        let limits = &config.limits;
+        let box_id_str = config.box_id.to_string();
+        let meta_path_str = config
+            .metadata_path
             .to_str()
             .ok_or_else(|| DomainError::Internal("Invalid metadata path".to_string()))?;
 
@@ -137,105 +278,92 @@ impl IsolateSandboxService {
         let meta_arg = format!("--meta={}", meta_path_str);
 
         // Build base arguments
+        // Status lines go to the meta file only, so the isolate stderr is the program's
         let mut args: Vec<String> = vec![
             "isolate".to_string(),
             "-b".to_string(),
             box_id_str,
             "--cg".to_string(),
+            "--silent".to_string(),
         ];
 
         // Add resource limits conditionally
-        if self.config.default_cg_mem > 0 {
-            args.push(format!("--cg-mem={}", self.config.default_cg_mem));
+        if limits.cg_mem > 0 {
+            args.push(format!("--cg-mem={}", limits.cg_mem));
         }
-        if self.config.default_mem > 0 {
-            args.push(format!("--mem={}", self.config.default_mem));
+        if limits.mem > 0 {
+            args.push(format!("--mem={}", limits.mem));
         }
-        if self.config.default_time > 0 {
-            args.push(format!("--time={}", self.config.default_time));
+        if limits.time > 0 {
+            args.push(format!("--time={}", limits.time));
         }
-        if self.config.default_wall_time > 0 {
-            args.push(format!("--wall-time={}", self.config.default_wall_time));
+        if limits.wall_time > 0 {
+            args.push(format!("--wall-time={}", limits.wall_time));
         }
-        if self.config.default_extra_time > 0 {
-            args.push(format!("--extra-time={}", self.config.default_extra_time));
+        if limits.extra_time > 0 {
+            args.push(format!("--extra-time={}", limits.extra_time));
         }
-        if self.config.default_stack > 0 {
-            args.push(format!("--stack={}", self.config.default_stack));
+        if limits.stack > 0 {
+            args.push(format!("--stack={}", limits.stack));
         }
-        if self.config.default_fsize > 0 {
-            args.push(format!("--fsize={}", self.config.default_fsize));
+        if limits.fsize > 0 {
+            args.push(format!("--fsize={}", limits.fsize));
         }
-        if self.config.default_open_files > 0 {
-            args.push(format!("--open-files={}", self.config.default_open_files));
+        if limits.open_files > 0 {
+            args.push(format!("--open-files={}", limits.open_files));
         } else {
             args.push("--open-files=0".to_string()); // unlimited files
         }
 
         // Special handling for processes: 0 means unlimited (use --processes without value)
-        if self.config.default_processes > 0 {
-            args.push(format!("--processes={}", self.config.default_processes));
+        if limits.processes > 0 {
+            args.push(format!("--processes={}", limits.processes));
         } else {
             args.push("--processes".to_string()); // unlimited processes
         }
 
-        // Add remaining arguments
+        if config.stdin_path.is_some() {
+            args.push(format!("--stdin={}", STDIN_FILE_NAME));
+        }
+        if redirect_output {
+            args.push(format!("--stdout={}", STDOUT_FILE_NAME));
+            args.push(format!("--stderr={}", STDERR_FILE_NAME));
+        }
+
         args.extend([
             packages_arg,
             "--env=HOME=/box".to_string(),
             "--env=PYTHONPATH=/packages".to_string(),
+        ]);
+
+        // Add per-request environment variables
+        args.extend(config.env.iter().map(|(name, value)| format!("--env={}={}", name, value)));
+
+        // Add remaining arguments, forwarding program arguments to the runner
+        args.extend([
             meta_arg,
             "--run".to_string(),
             "--".to_string(),
             "runner".to_string(),
         ]);
+        args.extend(config.args.iter().cloned());
 
-        self.process_executor
-            .execute_command("sudo", &args.iter().map(|s| s.as_str()).collect::<Vec<_>>())
-            .await
+        Ok(args)
     }
 
-    fn parse_metadata(&self, metadata_content: &str) -> ExecutionMetadata {
-        let mut metadata = ExecutionMetadata::new();
-
-        let time_regex = Regex::new(r"time:([\d.]+)").unwrap();
-        let time_wall_regex = Regex::new(r"time-wall:([\d.]+)").unwrap();
-        let memory_regex = Regex::new(r"cg-mem:(\d+)").unwrap();
-        let status_regex = Regex::new(r"status:(\w+)").unwrap();
-        let exitcode_regex = Regex::new(r"exitcode:(\d+)").unwrap();
-
-        if let Some(cap) = time_regex.captures(metadata_content) {
-            metadata.time = cap[1].parse().unwrap_or(0.0);
-        }
-
-        if let Some(cap) = time_wall_regex.captures(metadata_content) {
-            metadata.time_wall = cap[1].parse().unwrap_or(0.0);
-        }
-
-        if let Some(cap) = memory_regex.captures(metadata_content) {
-            metadata.memory = cap[1].parse().unwrap_or(0);
-        }
-
-        if let Some(cap) = status_regex.captures(metadata_content) {
-            metadata.status = cap[1].to_string();
-        }
-
-        if let Some(cap) = exitcode_regex.captures(metadata_content) {
-            metadata.exit_code = cap[1].parse().unwrap_or(0);
+    /// Initialize the box and copy in everything the run needs.
+    ///
+    /// Returns the site packages path to mount into the box.
+    async fn prepare_sandbox(&self, config: &SandboxExecutionConfig) -> DomainResult<String> {
+        // Initialize sandbox, unless files of earlier runs must be kept
+        if config.reuse_box {
+            log::debug!("Reusing initialized sandbox for box ID: {}", config.box_id);
+        } else {
+            log::debug!("Initializing sandbox for box ID: {}", config.box_id);
+            self.init_sandbox(config.box_id).await?;
+            log::debug!("Sandbox initialized successfully for box ID: {}", config.box_id);
         }
 
-        metadata
-    }
-}
-
-#[async_trait]
-impl SandboxService for IsolateSandboxService {
-    async fn execute(&self, config: SandboxExecutionConfig) -> DomainResult<SandboxExecutionResult> {
-        // Initialize sandbox
-        log::debug!("Initializing sandbox for box ID: {}", config.box_id);
-        self.init_sandbox(config.box_id).await?;
-        log::debug!("Sandbox initialized successfully for box ID: {}", config.box_id);
-
         // Copy binary and runner to sandbox
         log::debug!("Copying binary to sandbox: {:?}", config.binary_path);
         self.copy_to_sandbox(config.box_id, &config.binary_path, "bin")
@@ -247,31 +375,114 @@ impl SandboxService for IsolateSandboxService {
             .await?;
         log::debug!("Runner copied successfully");
 
+        for file in &config.files {
+            log::debug!("Copying source file to sandbox: {:?}", file.box_path);
+            let box_path = file
+                .box_path
+                .to_str()
+                .ok_or_else(|| DomainError::Internal("Invalid box path".to_string()))?;
+            self.copy_to_sandbox(config.box_id, &file.host_path, box_path)
+                .await?;
+        }
+
+        if let Some(stdin_path) = &config.stdin_path {
+            log::debug!("Copying stdin to sandbox: {:?}", stdin_path);
+            self.copy_to_sandbox(config.box_id, stdin_path, STDIN_FILE_NAME)
+                .await?;
+            log::debug!("Stdin copied successfully");
+        }
+
         // Get site packages path
         log::debug!("Getting site packages path for language: {}", config.language.name);
         let site_packages = self.get_site_packages_path(&config.language.name)?;
         log::debug!("Found site packages path: {}", site_packages);
 
-        // Execute in sandbox
-        log::debug!("Running code in sandbox with box ID: {}", config.box_id);
-        let (stdout, stderr, _exit_code) = self
-            .run_in_sandbox(config.box_id, &site_packages, &config.metadata_path)
-            .await?;
-        log::debug!("Code execution completed in sandbox");
+        Ok(site_packages)
+    }
+
+    /// Read back an output file written by the program and remove it from the box.
+    ///
+    /// At most `max_output_size` bytes are read, so runaway output never reaches memory.
+    /// The program can replace the file, so anything but a regular file counts as no output.
+    async fn take_output(&self, box_id: u32, name: &str) -> DomainResult<(Vec<u8>, OutputSize)> {
+        let path = format!("/var/lib/isolate/{}/box/{}", box_id, name);
+
+        let limit = self.config.max_output_size;
+        let output = self
+            .process_executor
+            .read_regular_file("sudo", &path, limit)
+            .await
+            .map_err(|e| {
+                DomainError::SandboxError(format!("Failed to read program output: {}", e))
+            })?;
+        self.delete_file(box_id, name).await?;
 
+        let Some((content, size)) = output else {
+            // The program never started, e.g. because the run failed to set up, or
+            // replaced the file with something else
+            log::debug!("No regular {} written in box {}", name, box_id);
+            return Ok((Vec::new(), OutputSize::default()));
+        };
+
+        if size.truncated {
+            log::debug!(
+                "Truncated {} of box {} from {} to {} bytes",
+                name,
+                box_id,
+                size.total,
+                limit
+            );
+        }
+        Ok((content, size))
+    }
+
+    /// Parse the run metadata and remove runtime files from the box
+    async fn finish_sandbox(&self, config: &SandboxExecutionConfig) -> DomainResult<ExecutionMetadata> {
         // Parse metadata
         log::debug!("Parsing execution metadata from: {:?}", config.metadata_path);
         let metadata_content = self.file_system.read_to_string(&config.metadata_path).await?;
-        let metadata = self.parse_metadata(&metadata_content);
+        let metadata = parse_metadata(&metadata_content);
         log::debug!("Metadata parsed: time={}s, time_wall={}s, memory={}KB, status={}", 
                    metadata.time, metadata.time_wall, metadata.memory, metadata.status);
 
-        // Clean up runtime files (bin and runner) while preserving user-created files
+        // Clean up runtime files (bin, runner and stdin) while preserving user-created files
         log::debug!("Cleaning up runtime files in sandbox");
         self.delete_file(config.box_id, "bin").await?;
         self.delete_file(config.box_id, "runner").await?;
+        if config.stdin_path.is_some() {
+            self.delete_file(config.box_id, STDIN_FILE_NAME).await?;
+        }
         log::debug!("Runtime files cleaned up");
 
+        Ok(metadata)
+    }
+}
+
+#[async_trait]
+impl SandboxService for IsolateSandboxService {
+    fn resolve_limits(&self, overrides: &ResourceLimitOverrides) -> DomainResult<ResourceLimits> {
+        self.config
+            .default_limits()
+            .resolve(&self.config.max_limits(), overrides)
+    }
+
+    async fn execute(&self, config: SandboxExecutionConfig) -> DomainResult<SandboxExecutionResult> {
+        let site_packages = self.prepare_sandbox(&config).await?;
+
+        // Execute in sandbox
+        log::debug!("Running code in sandbox with box ID: {}", config.box_id);
+        let result = self.run_in_sandbox(&config, &site_packages).await;
+        if let Err(DomainError::Cancelled) = result {
+            return Err(self.discard_cancelled(config.box_id).await);
+        }
+        result?;
+        log::debug!("Code execution completed in sandbox");
+
+        let (stdout, stdout_size) = self.take_output(config.box_id, STDOUT_FILE_NAME).await?;
+        let (stderr, stderr_size) = self.take_output(config.box_id, STDERR_FILE_NAME).await?;
+
+        let metadata = self.finish_sandbox(&config).await?;
+
         // Note: Full sandbox cleanup must be called explicitly via cleanup endpoint
 
         log::debug!("Sandbox execution result ready with {} bytes stdout, {} bytes stderr", 
@@ -279,29 +490,148 @@ impl SandboxService for IsolateSandboxService {
         Ok(SandboxExecutionResult {
             stdout,
             stderr,
+            stdout_size,
+            stderr_size,
+            metadata,
+            box_id: config.box_id,
+        })
+    }
+
+    async fn execute_streaming(
+        &self,
+        config: SandboxExecutionConfig,
+        output: mpsc::Sender<OutputChunk>,
+    ) -> DomainResult<SandboxExecutionResult> {
+        let site_packages = self.prepare_sandbox(&config).await?;
+        let args = self.build_run_args(&config, &site_packages, false)?;
+
+        log::debug!("Running code in sandbox with box ID: {} (streaming)", config.box_id);
+        let result = self
+            .process_executor
+            .execute_streaming(
+                "sudo",
+                &args.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+                output,
+                self.config.max_output_size,
+                &config.cancel,
+            )
+            .await;
+        if let Err(DomainError::Cancelled) = result {
+            return Err(self.discard_cancelled(config.box_id).await);
+        }
+        let (_, stdout_size, stderr_size) = result?;
+        log::debug!("Code execution completed in sandbox");
+
+        let metadata = self.finish_sandbox(&config).await?;
+
+        // Output was already delivered through the channel
+        Ok(SandboxExecutionResult {
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            stdout_size,
+            stderr_size,
             metadata,
             box_id: config.box_id,
         })
     }
 
-    async fn acquire_box_id(&self) -> DomainResult<u32> {
-        self.box_pool.acquire().await
+    async fn execute_interactive(
+        &self,
+        program: SandboxExecutionConfig,
+        interactor: SandboxExecutionConfig,
+    ) -> DomainResult<InteractiveExecutionResult> {
+        let program_packages = self.prepare_sandbox(&program).await?;
+        let interactor_packages = self.prepare_sandbox(&interactor).await?;
+
+        let program_args = self.build_run_args(&program, &program_packages, false)?;
+        let interactor_args = self.build_run_args(&interactor, &interactor_packages, false)?;
+
+        log::debug!(
+            "Running interactive session in box IDs: {} (program), {} (interactor)",
+            program.box_id,
+            interactor.box_id
+        );
+        // Both runs belong to the same test case, so the program's token stops them both
+        let result = self
+            .process_executor
+            .execute_connected(
+                ("sudo", &program_args.iter().map(|s| s.as_str()).collect::<Vec<_>>()),
+                ("sudo", &interactor_args.iter().map(|s| s.as_str()).collect::<Vec<_>>()),
+                self.config.max_output_size,
+                &program.cancel,
+            )
+            .await;
+        if let Err(DomainError::Cancelled) = result {
+            self.discard_cancelled(interactor.box_id).await;
+            return Err(self.discard_cancelled(program.box_id).await);
+        }
+        let ((program_stderr, program_stderr_size), (interactor_stderr, interactor_stderr_size)) =
+            result?;
+        log::debug!("Interactive session completed");
+
+        let program_metadata = self.finish_sandbox(&program).await?;
+        let interactor_metadata = self.finish_sandbox(&interactor).await?;
+
+        // Both stdout streams were consumed by the other process
+        Ok(InteractiveExecutionResult {
+            program: SandboxExecutionResult {
+                stdout: Vec::new(),
+                stderr: program_stderr,
+                stdout_size: OutputSize::default(),
+                stderr_size: program_stderr_size,
+                metadata: program_metadata,
+                box_id: program.box_id,
+            },
+            interactor: SandboxExecutionResult {
+                stdout: Vec::new(),
+                stderr: interactor_stderr,
+                stdout_size: OutputSize::default(),
+                stderr_size: interactor_stderr_size,
+                metadata: interactor_metadata,
+                box_id: interactor.box_id,
+            },
+        })
+    }
+
+    async fn acquire_box_id(&self, scheduling: &SchedulingContext) -> DomainResult<u32> {
+        self.box_pool.acquire(scheduling).await
+    }
+
+    async fn acquire_box_ids(
+        &self,
+        scheduling: &SchedulingContext,
+        count: usize,
+    ) -> DomainResult<Vec<u32>> {
+        self.box_pool.acquire_many(scheduling, count).await
     }
 
     async fn release_box_id(&self, box_id: u32) -> DomainResult<()> {
         self.box_pool.release(box_id).await
     }
 
-    async fn list_files(&self, box_id: u32) -> DomainResult<Vec<String>> {
-        let box_path = PathBuf::from(format!("/var/lib/isolate/{}/box", box_id));
-        
-        let box_path_str = box_path
-            .to_str()
-            .ok_or_else(|| DomainError::Internal("Invalid box path".to_string()))?;
+    fn box_holder(&self, box_id: u32) -> Option<String> {
+        self.box_pool.holder(box_id)
+    }
+
+    async fn init(&self, box_id: u32) -> DomainResult<()> {
+        self.init_sandbox(box_id).await
+    }
+
+    async fn list_entries(&self, box_id: u32, recursive: bool) -> DomainResult<Vec<BoxEntry>> {
+        self.ensure_box(box_id).await?;
+        let box_path = format!("/var/lib/isolate/{}/box", box_id);
+
+        // One NUL terminated record per entry: type, size, mtime and the relative path last,
+        // so paths may contain any character but NUL
+        let mut args = vec!["find", box_path.as_str(), "-mindepth", "1"];
+        if !recursive {
+            args.extend(["-maxdepth", "1"]);
+        }
+        args.extend(["-printf", "%y\\t%s\\t%T@\\t%P\\0"]);
 
         let (stdout, stderr, exit_code) = self
             .process_executor
-            .execute_command("sudo", &["ls", "-1", box_path_str])
+            .execute_command_raw("sudo", &args)
             .await?;
 
         if exit_code != 0 {
@@ -311,16 +641,122 @@ impl SandboxService for IsolateSandboxService {
             )));
         }
 
-        let files: Vec<String> = stdout
-            .lines()
-            .filter(|line| !line.is_empty())
-            .map(|line| line.to_string())
+        let mut entries: Vec<BoxEntry> = stdout
+            .split(|&b| b == 0)
+            .filter(|record| !record.is_empty())
+            .filter_map(|record| parse_find_record(&String::from_utf8_lossy(record)))
             .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
 
-        Ok(files)
+        Ok(entries)
+    }
+
+    async fn hash_files(
+        &self,
+        box_id: u32,
+        paths: &[String],
+    ) -> DomainResult<HashMap<String, String>> {
+        if paths.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let box_path = format!("/var/lib/isolate/{}/box/", box_id);
+        let files: Vec<String> = paths
+            .iter()
+            .map(|path| format!("{}{}", box_path, path))
+            .collect();
+        let mut args = vec!["sha256sum", "-z", "--"];
+        args.extend(files.iter().map(String::as_str));
+
+        // Files that vanished or cannot be read are left out rather than failing the listing
+        let (stdout, stderr, _) = self
+            .process_executor
+            .execute_command_raw("sudo", &args)
+            .await?;
+        if !stderr.is_empty() {
+            log::warn!("Failed to hash some files in box {}: {}", box_id, stderr.trim());
+        }
+
+        Ok(stdout
+            .split(|&b| b == 0)
+            .filter_map(|record| {
+                let record = String::from_utf8_lossy(record);
+                let (hash, path) = record.split_once("  ")?;
+                let path = path.strip_prefix(&box_path)?;
+                Some((path.to_string(), hash.to_string()))
+            })
+            .collect())
+    }
+
+    async fn archive(
+        &self,
+        box_id: u32,
+        paths: &[String],
+        format: ArchiveFormat,
+        output: &Path,
+    ) -> DomainResult<()> {
+        self.ensure_box(box_id).await?;
+        let box_path = format!("/var/lib/isolate/{}/box", box_id);
+
+        // Pass the paths through a NUL separated list, as there may be too many for the
+        // command line
+        let temp_dir = TempDir::new()?;
+        let list_path = temp_dir.path().join("paths");
+        let mut list = Vec::new();
+        for path in paths {
+            list.extend_from_slice(path.as_bytes());
+            list.push(0);
+        }
+        tokio::fs::write(&list_path, list).await?;
+
+        // Zip archives are repacked from a plain tarball, as isolate hosts only ship tar
+        let tar_path = match format {
+            ArchiveFormat::TarGz => output.to_path_buf(),
+            ArchiveFormat::Zip => temp_dir.path().join("archive.tar"),
+        };
+        let tar_str = tar_path
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid archive path".to_string()))?;
+        let list_str = list_path
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid path list path".to_string()))?;
+
+        let mut args = vec!["tar", "-c"];
+        if format == ArchiveFormat::TarGz {
+            args.push("-z");
+        }
+        args.extend([
+            "-f",
+            tar_str,
+            "-C",
+            box_path.as_str(),
+            "--no-recursion",
+            "--ignore-failed-read",
+            "--null",
+            "-T",
+            list_str,
+        ]);
+
+        let (_, stderr, exit_code) = self.process_executor.execute_command("sudo", &args).await?;
+        if exit_code != 0 {
+            return Err(DomainError::SandboxError(format!(
+                "Failed to archive box: {}",
+                stderr
+            )));
+        }
+
+        if format == ArchiveFormat::Zip {
+            let output = output.to_path_buf();
+            tokio::task::spawn_blocking(move || tar_to_zip(&tar_path, &output))
+                .await
+                .map_err(|e| DomainError::Internal(format!("Archive task failed: {}", e)))??;
+        }
+
+        Ok(())
     }
 
     async fn get_file_base64(&self, box_id: u32, filename: &str) -> DomainResult<String> {
+        let filename = self.resolve_box_path(box_id, filename).await?;
         let file_path = PathBuf::from(format!("/var/lib/isolate/{}/box/{}", box_id, filename));
         
         let file_path_str = file_path
@@ -342,6 +778,124 @@ impl SandboxService for IsolateSandboxService {
         Ok(base64_content)
     }
 
+    async fn stat_path(&self, box_id: u32, path: &Path) -> DomainResult<BoxEntry> {
+        self.ensure_box(box_id).await?;
+
+        let path = path
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid box path".to_string()))?;
+        let resolved = self.resolve_box_path(box_id, path).await?;
+        let file_path = format!("/var/lib/isolate/{}/box/{}", box_id, resolved);
+
+        let (stdout, _, exit_code) = self
+            .process_executor
+            .execute_command(
+                "sudo",
+                &["find", &file_path, "-maxdepth", "0", "-printf", "%y\\t%s\\t%T@\\t"],
+            )
+            .await?;
+        if exit_code != 0 {
+            return Err(DomainError::FileNotFound(path.to_string()));
+        }
+
+        let mut entry = parse_find_record(&stdout).ok_or_else(|| {
+            DomainError::SandboxError(format!("Unexpected file metadata: {}", stdout))
+        })?;
+        entry.path = resolved;
+        Ok(entry)
+    }
+
+    async fn read_file(
+        &self,
+        box_id: u32,
+        path: &Path,
+        offset: u64,
+        length: u64,
+    ) -> DomainResult<FileReader> {
+        let path = path
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid box path".to_string()))?;
+        let file_path = format!("/var/lib/isolate/{}/box/{}", box_id, path);
+
+        // The path was resolved beforehand, refuse it if it has since been replaced by a
+        // symlink
+        let reader = self.process_executor.spawn_reader(
+            "sudo",
+            &[
+                "dd",
+                &format!("if={}", file_path),
+                "bs=65536",
+                "iflag=skip_bytes,count_bytes,nofollow",
+                &format!("skip={}", offset),
+                &format!("count={}", length),
+                "status=none",
+            ],
+        )?;
+
+        Ok(Box::pin(reader))
+    }
+
+    async fn write_file(&self, box_id: u32, path: &Path, source: &Path) -> DomainResult<()> {
+        self.ensure_box(box_id).await?;
+
+        let path = path
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid box path".to_string()))?;
+        let dest_name = self.resolve_box_path(box_id, path).await?;
+        if dest_name.is_empty() {
+            return Err(DomainError::InvalidRequest(format!(
+                "Path '{}' is the box root",
+                path
+            )));
+        }
+        self.copy_to_sandbox(box_id, source, &dest_name).await
+    }
+
+    async fn remove_path(&self, box_id: u32, path: &Path, recursive: bool) -> DomainResult<()> {
+        self.ensure_box(box_id).await?;
+
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid box path".to_string()))?;
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| DomainError::InvalidRequest(format!("Invalid path '{}'", path_str)))?;
+
+        // Resolve only the parent, so a symlink itself is removed rather than its target
+        let mut target = PathBuf::from(format!("/var/lib/isolate/{}/box", box_id));
+        if let Some(parent) = path.parent().and_then(|parent| parent.to_str()) {
+            if !parent.is_empty() {
+                target.push(self.resolve_box_path(box_id, parent).await?);
+            }
+        }
+        target.push(name);
+        let target = target
+            .to_str()
+            .ok_or_else(|| DomainError::Internal("Invalid box path".to_string()))?;
+
+        let (kind, _, exit_code) = self
+            .process_executor
+            .execute_command("sudo", &["stat", "-c", "%F", "--", target])
+            .await?;
+        let kind = (exit_code == 0).then(|| kind.trim().to_string());
+        let flags = removal_flags(path_str, kind.as_deref(), recursive)?;
+
+        let (_, stderr, exit_code) = self
+            .process_executor
+            .execute_command("sudo", &["rm", flags, "--", target])
+            .await?;
+        if exit_code != 0 {
+            return Err(DomainError::SandboxError(format!(
+                "Failed to delete {} from box: {}",
+                path_str, stderr
+            )));
+        }
+
+        log::debug!("Deleted {} from box ID: {}", path_str, box_id);
+        Ok(())
+    }
+
     async fn delete_file(&self, box_id: u32, filename: &str) -> DomainResult<()> {
         let file_path = PathBuf::from(format!("/var/lib/isolate/{}/box/{}", box_id, filename));
         
@@ -366,3 +920,318 @@ impl SandboxService for IsolateSandboxService {
     }
 }
 
+/// Repack a tarball as a zip archive, keeping its regular files, directories and symlinks
+fn tar_to_zip(tar_path: &Path, zip_path: &Path) -> std::io::Result<()> {
+    let mut archive = tar::Archive::new(std::fs::File::open(tar_path)?);
+    let mut zip = ZipWriter::new(std::fs::File::create(zip_path)?);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().to_string();
+        let header = entry.header();
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .unix_permissions(header.mode()?)
+            .last_modified_time(zip_time(header.mtime()?))
+            .large_file(header.size()? >= u32::MAX as u64);
+
+        match header.entry_type() {
+            EntryType::Regular => {
+                zip.start_file(path, options)?;
+                std::io::copy(&mut entry, &mut zip)?;
+            }
+            EntryType::Directory => zip.add_directory(path, options)?,
+            EntryType::Symlink => {
+                if let Some(target) = entry.link_name()? {
+                    zip.add_symlink(path, target.to_string_lossy(), options)?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Convert a Unix timestamp to a zip timestamp, which only covers 1980 to 2107
+fn zip_time(timestamp: u64) -> zip::DateTime {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .and_then(|time| {
+            zip::DateTime::from_date_and_time(
+                time.year() as u16,
+                time.month() as u8,
+                time.day() as u8,
+                time.hour() as u8,
+                time.minute() as u8,
+                time.second() as u8,
+            )
+            .ok()
+        })
+        .unwrap_or_default()
+}
+
+/// Flags for `rm` deleting an entry of the given kind (as printed by `stat -c %F`, `None`
+/// when it does not exist). Directories are only deleted with their contents on request.
+fn removal_flags(path: &str, kind: Option<&str>, recursive: bool) -> DomainResult<&'static str> {
+    match kind {
+        None => Err(DomainError::FileNotFound(path.to_string())),
+        Some("directory") if !recursive => Err(DomainError::InvalidRequest(format!(
+            "'{}' is a directory, delete it recursively to remove its contents",
+            path
+        ))),
+        Some(_) if recursive => Ok("-rf"),
+        Some(_) => Ok("-f"),
+    }
+}
+
+/// Parse the meta file isolate writes with `--meta`
+fn parse_metadata(metadata_content: &str) -> ExecutionMetadata {
+    let mut metadata = ExecutionMetadata::new();
+
+    let time_regex = Regex::new(r"time:([\d.]+)").unwrap();
+    let time_wall_regex = Regex::new(r"time-wall:([\d.]+)").unwrap();
+    let memory_regex = Regex::new(r"cg-mem:(\d+)").unwrap();
+    let status_regex = Regex::new(r"status:(\w+)").unwrap();
+    let exitcode_regex = Regex::new(r"exitcode:(\d+)").unwrap();
+    let message_regex = Regex::new(r"(?m)^message:(.*)$").unwrap();
+
+    if let Some(cap) = time_regex.captures(metadata_content) {
+        metadata.time = cap[1].parse().unwrap_or(0.0);
+    }
+
+    if let Some(cap) = time_wall_regex.captures(metadata_content) {
+        metadata.time_wall = cap[1].parse().unwrap_or(0.0);
+    }
+
+    if let Some(cap) = memory_regex.captures(metadata_content) {
+        metadata.memory = cap[1].parse().unwrap_or(0);
+    }
+
+    if let Some(cap) = status_regex.captures(metadata_content) {
+        metadata.status = cap[1].to_string();
+    }
+
+    if let Some(cap) = exitcode_regex.captures(metadata_content) {
+        metadata.exit_code = cap[1].parse().unwrap_or(0);
+    }
+
+    if let Some(cap) = message_regex.captures(metadata_content) {
+        metadata.message = Some(cap[1].trim().to_string());
+    }
+
+    metadata
+}
+
+/// Parse a `type\tsize\tmtime\tpath` record printed by `find -printf`
+fn parse_find_record(record: &str) -> Option<BoxEntry> {
+    let mut fields = record.splitn(4, '\t');
+    let kind = match fields.next()? {
+        "f" => BoxEntryKind::File,
+        "d" => BoxEntryKind::Directory,
+        "l" => BoxEntryKind::Symlink,
+        _ => BoxEntryKind::Other,
+    };
+    let size = fields.next()?.parse().ok()?;
+    let modified = fields.next()?.parse::<f64>().ok()?;
+    let path = fields.next()?.to_string();
+
+    Some(BoxEntry {
+        path,
+        kind,
+        size,
+        modified_at: DateTime::from_timestamp_millis((modified * 1000.0) as i64)?,
+        sha256: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_find_record_reads_fields() {
+        let entry = parse_find_record("f\t42\t1700000000.5000000000\tout/data.csv").unwrap();
+        assert_eq!(entry.path, "out/data.csv");
+        assert_eq!(entry.kind, BoxEntryKind::File);
+        assert_eq!(entry.size, 42);
+        assert_eq!(entry.modified_at.timestamp_millis(), 1_700_000_000_500);
+        assert!(entry.sha256.is_none());
+
+        let kinds = [
+            ("d", BoxEntryKind::Directory),
+            ("l", BoxEntryKind::Symlink),
+            ("p", BoxEntryKind::Other),
+        ];
+        for (kind, expected) in kinds {
+            let record = format!("{}\t0\t0\tentry", kind);
+            assert_eq!(parse_find_record(&record).unwrap().kind, expected);
+        }
+    }
+
+    #[test]
+    fn parse_find_record_keeps_tabs_and_newlines_in_paths() {
+        let cases = [
+            "with\ttab.txt",
+            "with\nnewline.txt",
+            "dir\t1/\n\t/file",
+            "trailing\t",
+        ];
+
+        for path in cases {
+            let record = format!("f\t1\t1700000000.0\t{}", path);
+            assert_eq!(parse_find_record(&record).unwrap().path, path);
+        }
+    }
+
+    #[test]
+    fn parse_find_record_rejects_malformed_records() {
+        let cases = [
+            "",
+            "f",
+            "f\t1\t1700000000.0",
+            "f\tx\t0\tfile",
+            "f\t1\tnow\tfile",
+        ];
+
+        for record in cases {
+            assert!(parse_find_record(record).is_none(), "{:?}", record);
+        }
+    }
+
+    #[test]
+    fn parse_metadata_keeps_colons_in_message() {
+        let meta = "time:1.204\n\
+                    time-wall:1.530\n\
+                    max-rss:5120\n\
+                    cg-mem:4096\n\
+                    exitcode:1\n\
+                    status:RE\n\
+                    message:execve(\"./run\"): No such file or directory\n";
+
+        let metadata = parse_metadata(meta);
+        assert_eq!(metadata.time, 1.204);
+        assert_eq!(metadata.time_wall, 1.53);
+        assert_eq!(metadata.memory, 4096);
+        assert_eq!(metadata.exit_code, 1);
+        assert_eq!(metadata.status, "RE");
+        assert_eq!(
+            metadata.message.as_deref(),
+            Some("execve(\"./run\"): No such file or directory")
+        );
+    }
+
+    #[test]
+    fn parse_metadata_without_message() {
+        let meta = "time:0.012\ntime-wall:0.034\ncg-mem:1536\nexitcode:0\n";
+
+        let metadata = parse_metadata(meta);
+        assert_eq!(metadata.time, 0.012);
+        assert_eq!(metadata.time_wall, 0.034);
+        assert_eq!(metadata.memory, 1536);
+        assert_eq!(metadata.exit_code, 0);
+        // isolate only writes a status for runs that did not end normally
+        assert_eq!(metadata.status, "OK");
+        assert!(metadata.message.is_none());
+    }
+
+    #[test]
+    fn removal_flags_depend_on_the_entry_kind() {
+        let cases = [
+            ("regular file", false, "-f"),
+            ("regular empty file", true, "-rf"),
+            ("symbolic link", false, "-f"),
+            ("directory", true, "-rf"),
+        ];
+        for (kind, recursive, flags) in cases {
+            assert_eq!(
+                removal_flags("entry", Some(kind), recursive).unwrap(),
+                flags,
+                "{} recursive={}",
+                kind,
+                recursive
+            );
+        }
+    }
+
+    #[test]
+    fn removal_flags_refuse_missing_entries_and_plain_directory_deletes() {
+        for recursive in [false, true] {
+            assert!(matches!(
+                removal_flags("missing.txt", None, recursive),
+                Err(DomainError::FileNotFound(path)) if path == "missing.txt"
+            ));
+        }
+
+        assert!(matches!(
+            removal_flags("out", Some("directory"), false),
+            Err(DomainError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn tar_to_zip_keeps_files_directories_and_symlinks() {
+        let dir = TempDir::new().unwrap();
+        let tar_path = dir.path().join("box.tar");
+        let zip_path = dir.path().join("box.zip");
+
+        let mut builder = tar::Builder::new(std::fs::File::create(&tar_path).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(EntryType::Directory);
+        header.set_mode(0o755);
+        header.set_size(0);
+        builder
+            .append_data(&mut header, "out/", std::io::empty())
+            .unwrap();
+
+        let content = b"hello from the box\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_mode(0o640);
+        header.set_mtime(1_700_000_000);
+        header.set_size(content.len() as u64);
+        builder
+            .append_data(&mut header, "out/log.txt", &content[..])
+            .unwrap();
+
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(EntryType::Symlink);
+        header.set_mode(0o777);
+        header.set_size(0);
+        builder
+            .append_link(&mut header, "out/latest", "log.txt")
+            .unwrap();
+        builder.into_inner().unwrap();
+
+        tar_to_zip(&tar_path, &zip_path).unwrap();
+
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(&zip_path).unwrap()).unwrap();
+        let names: Vec<_> = zip.file_names().collect();
+        assert_eq!(names, ["out/", "out/log.txt", "out/latest"]);
+
+        let mut file = zip.by_name("out/log.txt").unwrap();
+        assert_eq!(file.unix_mode().unwrap() & 0o777, 0o640);
+        assert_eq!(file.last_modified().unwrap().year(), 2023);
+        let mut read = Vec::new();
+        std::io::Read::read_to_end(&mut file, &mut read).unwrap();
+        assert_eq!(read, content);
+        drop(file);
+
+        assert!(zip.by_name("out/").unwrap().is_dir());
+        let mut link = zip.by_name("out/latest").unwrap();
+        assert!(link.is_symlink());
+        let mut target = String::new();
+        std::io::Read::read_to_string(&mut link, &mut target).unwrap();
+        assert_eq!(target, "log.txt");
+    }
+
+    #[test]
+    fn zip_time_clamps_to_the_zip_range() {
+        let time = zip_time(1_700_000_000);
+        assert_eq!(
+            (time.year(), time.month(), time.day(), time.hour()),
+            (2023, 11, 14, 22)
+        );
+        // Timestamps before 1980 cannot be represented and fall back to the zip epoch
+        assert_eq!(zip_time(0), zip::DateTime::default());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::services::WebhookSender;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use sha2::Sha256;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Header carrying the hex encoded HMAC-SHA256 of the request body
+const SIGNATURE_HEADER: &str = "X-Isolate-Signature";
+
+/// Header carrying the delivery ID, identical across retries of the same delivery
+const DELIVERY_HEADER: &str = "X-Isolate-Delivery";
+
+pub struct HttpWebhookSender {
+    client: reqwest::Client,
+    secret: String,
+    /// Deliver to loopback, private and link-local addresses too
+    allow_private: bool,
+}
+
+impl HttpWebhookSender {
+    pub fn new(secret: String, timeout: Duration, allow_private: bool) -> DomainResult<Self> {
+        // Redirects are not followed, so a receiver cannot point deliveries elsewhere
+        let mut builder = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none());
+        if !allow_private {
+            // Resolve again when connecting, as the host may have changed its addresses since
+            // the callback URL was checked
+            builder = builder.dns_resolver(Arc::new(PublicResolver));
+        }
+        let client = builder
+            .build()
+            .map_err(|e| DomainError::Internal(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            secret,
+            allow_private,
+        })
+    }
+
+    /// Signature of `payload` in the form `sha256=<hex digest>`
+    fn sign(&self, payload: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+}
+
+#[async_trait]
+impl WebhookSender for HttpWebhookSender {
+    async fn check_url(&self, url: &str) -> DomainResult<()> {
+        let url = reqwest::Url::parse(url).map_err(|e| {
+            DomainError::InvalidRequest(format!("callback_url is not a valid URL: {}", e))
+        })?;
+        if self.allow_private {
+            return Ok(());
+        }
+
+        let host = url.host_str().unwrap_or_default();
+        // IPv6 hosts are written in brackets
+        let addresses = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+            Ok(address) => vec![address],
+            Err(_) => tokio::net::lookup_host((host, url.port_or_known_default().unwrap_or(0)))
+                .await
+                .map_err(|_| {
+                    DomainError::InvalidRequest(format!("callback_url host {} is unknown", host))
+                })?
+                .map(|address| address.ip())
+                .collect(),
+        };
+
+        if addresses.is_empty() || !addresses.into_iter().all(is_public) {
+            return Err(DomainError::InvalidRequest(
+                "callback_url must not point to a loopback, private or link-local address"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn send(&self, url: &str, delivery_id: &str, payload: &[u8]) -> DomainResult<u16> {
+        // Addresses in the URL itself are not resolved, so check them here
+        self.check_url(url).await?;
+
+        let response = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, self.sign(payload))
+            .header(DELIVERY_HEADER, delivery_id)
+            .body(payload.to_vec())
+            .send()
+            .await
+            .map_err(|e| DomainError::Internal(format!("Failed to send webhook: {}", e)))?;
+
+        Ok(response.status().as_u16())
+    }
+}
+
+/// Resolves host names to their public addresses only, so deliveries never reach the
+/// internal network
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|address| is_public(address.ip()))
+                .collect();
+            if addresses.is_empty() {
+                return Err(format!("{} has no public address", name.as_str()).into());
+            }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Whether `address` is reachable on the public internet, rather than being a loopback,
+/// private, link-local or otherwise reserved address
+fn is_public(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => is_public_v4(address),
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(mapped) => is_public_v4(mapped),
+            None => is_public_v6(address),
+        },
+    }
+}
+
+fn is_public_v4(address: Ipv4Addr) -> bool {
+    let [first, second, ..] = address.octets();
+    !(address.is_unspecified()
+        || address.is_loopback()
+        || address.is_private()
+        || address.is_link_local()
+        || address.is_broadcast()
+        || address.is_documentation()
+        || address.is_multicast()
+        // "This network"
+        || first == 0
+        // Shared address space of carrier-grade NAT
+        || (first == 100 && (64..128).contains(&second))
+        // Reserved for future use
+        || first >= 240)
+}
+
+fn is_public_v6(address: Ipv6Addr) -> bool {
+    let first = address.segments()[0];
+    !(address.is_unspecified()
+        || address.is_loopback()
+        || address.is_multicast()
+        // Unique local
+        || (first & 0xfe00) == 0xfc00
+        // Link-local
+        || (first & 0xffc0) == 0xfe80)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn sign_uses_hmac_sha256() {
+        // RFC 4231 test case 2
+        let sender =
+            HttpWebhookSender::new("Jefe".to_string(), Duration::from_secs(1), false).unwrap();
+        assert_eq!(
+            sender.sign(b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn send_delivers_signed_payload() {
+        let (received_tx, mut received_rx) = mpsc::channel(1);
+        let app = Router::new().route(
+            "/hook",
+            post(
+                move |headers: HeaderMap, body: axum::body::Bytes| async move {
+                    received_tx.send((headers, body)).await.unwrap();
+                    StatusCode::ACCEPTED
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let secret = "webhook-secret";
+        let payload = br#"{"stdout":"hello\n"}"#;
+        // The receiver listens on loopback
+        let sender =
+            HttpWebhookSender::new(secret.to_string(), Duration::from_secs(5), true).unwrap();
+        let status = sender.send(&url, "delivery-1", payload).await.unwrap();
+        assert_eq!(status, 202);
+
+        let (headers, body) = received_rx.recv().await.unwrap();
+        assert_eq!(body.as_ref(), payload);
+        assert_eq!(headers["content-type"], "application/json");
+        assert_eq!(headers[DELIVERY_HEADER], "delivery-1");
+
+        // Verify the signature the way a receiver would
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+        let digest = hex::decode(signature.strip_prefix("sha256=").unwrap()).unwrap();
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(&body);
+        mac.verify_slice(&digest).unwrap();
+    }
+
+    #[test]
+    fn is_public_rejects_internal_addresses() {
+        let internal = [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:10.0.0.1",
+        ];
+        for address in internal {
+            assert!(!is_public(address.parse().unwrap()), "{}", address);
+        }
+
+        for address in ["93.184.216.34", "8.8.8.8", "2606:4700::1111"] {
+            assert!(is_public(address.parse().unwrap()), "{}", address);
+        }
+    }
+
+    #[tokio::test]
+    async fn check_url_rejects_internal_hosts() {
+        let sender =
+            HttpWebhookSender::new("secret".to_string(), Duration::from_secs(1), false).unwrap();
+        let urls = [
+            "http://127.0.0.1:8080/hook",
+            "http://localhost/hook",
+            "https://10.0.0.5/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hook",
+            "http://[::ffff:192.168.0.1]/hook",
+            "not a url",
+        ];
+
+        for url in urls {
+            assert!(
+                matches!(
+                    sender.check_url(url).await,
+                    Err(DomainError::InvalidRequest(_))
+                ),
+                "{}",
+                url
+            );
+        }
+        assert!(sender.check_url("https://93.184.216.34/hook").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn send_refuses_internal_hosts() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let sender =
+            HttpWebhookSender::new("secret".to_string(), Duration::from_secs(1), false).unwrap();
+        assert!(sender.send(&url, "delivery-1", b"{}").await.is_err());
+
+        let sender =
+            HttpWebhookSender::new("secret".to_string(), Duration::from_secs(1), true).unwrap();
+        assert!(sender.check_url(&url).await.is_ok());
+    }
+}
@@ -7,14 +7,26 @@ mod use_cases;
 use adapters::api::{create_router, AppState};
 use config::Config;
 use domain::repositories::LanguageRepository;
-use external::repositories::FileSystemLanguageRepository;
-use external::services::{CompilerServiceImpl, IsolateSandboxService};
+use domain::services::{CompilerService, WebhookSender};
+use external::repositories::{
+    FileSystemLanguageRepository, InMemoryJobRepository, InMemoryWebhookDeliveryRepository,
+};
+use external::services::box_pool::BoxPool;
+use external::services::{
+    CachingCompilerService, CompilerServiceImpl, HttpWebhookSender, IsolateSandboxService,
+};
 use use_cases::{
-    CleanupBoxUseCase, ExecuteCodeUseCase, GetBoxFileUseCase, HealthCheckUseCase,
-    ListBoxFilesUseCase, ListLanguagesUseCase,
+    CancelExecutionUseCase, CancelJobUseCase, CancellationRegistry, CleanupBoxUseCase,
+    CompileCodeUseCase, CreateSessionUseCase, DeleteBoxFileUseCase, DownloadBoxArchiveUseCase,
+    DownloadBoxFileUseCase, ExecuteCodeUseCase, ExecuteInSessionUseCase, GetBoxFileUseCase,
+    GetCompileCacheStatsUseCase, GetJobUseCase, GetWebhookDeliveryUseCase, HealthCheckUseCase,
+    ListBoxFilesUseCase, ListLanguagesUseCase, ListWebhookDeliveriesUseCase, RunTestCasesUseCase,
+    SessionRegistry, SubmitJobUseCase, UploadBoxFileUseCase, WebhookNotifier,
 };
 
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -34,6 +46,8 @@ async fn main() -> anyhow::Result<()> {
     // Initialize repositories
     log::info!("Initializing language repository...");
     let language_repo = Arc::new(FileSystemLanguageRepository::new(config.config_dir.clone()));
+    let job_repo = Arc::new(InMemoryJobRepository::new());
+    let delivery_repo = Arc::new(InMemoryWebhookDeliveryRepository::new());
 
     // Setup all languages
     log::info!("Setting up languages...");
@@ -51,31 +65,150 @@ async fn main() -> anyhow::Result<()> {
 
     // Initialize services
     log::info!("Initializing services...");
-    let compiler_service = Arc::new(CompilerServiceImpl::new());
-    let sandbox_service = Arc::new(IsolateSandboxService::new(config.box_pool_size, config.sandbox));
+    let box_pool = Arc::new(BoxPool::new(
+        0..config.box_pool_size,
+        config.box_queue_length,
+        Duration::from_secs(config.box_max_wait),
+        config.tenant_weights.clone(),
+    ));
+    // Compile boxes are numbered after the execution boxes so the two never collide
+    let compile_box_pool = Arc::new(BoxPool::new(
+        config.box_pool_size..config.box_pool_size + config.compile.box_count,
+        config.box_queue_length,
+        Duration::from_secs(config.box_max_wait),
+        HashMap::new(),
+    ));
+    let compiler = Arc::new(CompilerServiceImpl::new(
+        compile_box_pool,
+        config.compile.clone(),
+    ));
+    let compiler_service: Arc<dyn CompilerService> = if config.compile_cache.max_size > 0 {
+        Arc::new(CachingCompilerService::new(
+            compiler,
+            config.compile.clone(),
+            config.compile_cache.dir.clone(),
+            config.compile_cache.max_size,
+            Duration::from_secs(config.compile_cache.max_age),
+        )?)
+    } else {
+        log::info!("Compile cache disabled");
+        compiler
+    };
+    let max_upload_size = config.sandbox.max_upload_size;
+    let max_archive_size = config.sandbox.max_archive_size;
+    let sandbox_service = Arc::new(IsolateSandboxService::new(box_pool, config.sandbox));
+    let webhook_sender: Option<Arc<dyn WebhookSender>> = match config.webhook.secret.clone() {
+        Some(secret) => Some(Arc::new(HttpWebhookSender::new(
+            secret,
+            Duration::from_secs(config.webhook.timeout),
+            config.webhook.allow_private,
+        )?)),
+        None => {
+            log::info!("Webhook secret not set, callback URLs will be rejected");
+            None
+        }
+    };
 
     // Initialize use cases
     log::info!("Initializing use cases...");
     let execute_code_use_case = Arc::new(ExecuteCodeUseCase::new(
         language_repo.clone(),
-        compiler_service,
+        compiler_service.clone(),
+        sandbox_service.clone(),
+    ));
+    let run_test_cases_use_case = Arc::new(RunTestCasesUseCase::new(
+        language_repo.clone(),
+        compiler_service.clone(),
+        sandbox_service.clone(),
+    ));
+    let compile_code_use_case = Arc::new(CompileCodeUseCase::new(
+        language_repo.clone(),
+        compiler_service.clone(),
+    ));
+    let get_compile_cache_stats_use_case =
+        Arc::new(GetCompileCacheStatsUseCase::new(compiler_service));
+    let webhooks = Arc::new(WebhookNotifier::new(
+        webhook_sender,
+        delivery_repo.clone(),
+        config.webhook.max_attempts,
+        Duration::from_millis(config.webhook.retry_delay),
+        Duration::from_secs(config.webhook.retention),
+    ));
+    let executions = Arc::new(CancellationRegistry::new());
+    let cancel_execution_use_case = Arc::new(CancelExecutionUseCase::new(executions.clone()));
+    let job_cancellations = Arc::new(CancellationRegistry::new());
+    let submit_job_use_case = Arc::new(SubmitJobUseCase::new(
+        execute_code_use_case.clone(),
         sandbox_service.clone(),
+        job_repo.clone(),
+        job_cancellations.clone(),
+        webhooks.clone(),
+        Duration::from_secs(config.job_retention),
     ));
+    let get_job_use_case = Arc::new(GetJobUseCase::new(job_repo.clone()));
+    let cancel_job_use_case = Arc::new(CancelJobUseCase::new(
+        job_repo,
+        job_cancellations,
+        webhooks.clone(),
+    ));
+    let list_webhook_deliveries_use_case =
+        Arc::new(ListWebhookDeliveriesUseCase::new(delivery_repo.clone()));
+    let get_webhook_delivery_use_case = Arc::new(GetWebhookDeliveryUseCase::new(delivery_repo));
     let list_languages_use_case = Arc::new(ListLanguagesUseCase::new(language_repo));
     let health_check_use_case = Arc::new(HealthCheckUseCase::new());
     let list_box_files_use_case = Arc::new(ListBoxFilesUseCase::new(sandbox_service.clone()));
     let get_box_file_use_case = Arc::new(GetBoxFileUseCase::new(sandbox_service.clone()));
-    let cleanup_box_use_case = Arc::new(CleanupBoxUseCase::new(sandbox_service));
+    let upload_box_file_use_case = Arc::new(UploadBoxFileUseCase::new(
+        sandbox_service.clone(),
+        max_upload_size,
+    ));
+    let sessions = Arc::new(SessionRegistry::new(
+        sandbox_service.clone(),
+        Duration::from_secs(config.session_idle_timeout),
+        config.session_max_per_tenant,
+    ));
+    sessions.spawn_reaper();
+    let create_session_use_case = Arc::new(CreateSessionUseCase::new(sessions.clone()));
+    let execute_in_session_use_case = Arc::new(ExecuteInSessionUseCase::new(
+        execute_code_use_case.clone(),
+        sessions.clone(),
+    ));
+    let delete_box_file_use_case = Arc::new(DeleteBoxFileUseCase::new(sandbox_service.clone()));
+    let download_box_file_use_case = Arc::new(DownloadBoxFileUseCase::new(sandbox_service.clone()));
+    let download_box_archive_use_case = Arc::new(DownloadBoxArchiveUseCase::new(
+        sandbox_service.clone(),
+        max_archive_size,
+    ));
+    let cleanup_box_use_case = Arc::new(CleanupBoxUseCase::new(sandbox_service, sessions.clone()));
 
     // Create app state
     let app_state = Arc::new(AppState {
         execute_code_use_case,
+        run_test_cases_use_case,
+        cancel_execution_use_case,
+        executions,
+        submit_job_use_case,
+        get_job_use_case,
+        cancel_job_use_case,
+        list_webhook_deliveries_use_case,
+        get_webhook_delivery_use_case,
+        webhooks,
         list_languages_use_case,
+        compile_code_use_case,
+        get_compile_cache_stats_use_case,
         health_check_use_case,
         list_box_files_use_case,
         get_box_file_use_case,
+        upload_box_file_use_case,
+        delete_box_file_use_case,
+        download_box_file_use_case,
+        download_box_archive_use_case,
         cleanup_box_use_case,
-        api_key: config.api_key.clone(),
+        create_session_use_case,
+        execute_in_session_use_case,
+        sessions,
+        api_keys: Arc::new(config.api_keys.clone()),
+        max_priorities: Arc::new(config.tenant_max_priorities.clone()),
     });
 
     // Create router
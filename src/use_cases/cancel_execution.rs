@@ -0,0 +1,22 @@
+use crate::domain::error::{DomainError, DomainResult};
+use crate::use_cases::cancellation::CancellationRegistry;
+use std::sync::Arc;
+
+pub struct CancelExecutionUseCase {
+    executions: Arc<CancellationRegistry>,
+}
+
+impl CancelExecutionUseCase {
+    pub fn new(executions: Arc<CancellationRegistry>) -> Self {
+        Self { executions }
+    }
+
+    /// Cancel a running synchronous execution of `tenant` by the ID it was started with
+    pub async fn execute(&self, tenant: &str, execution_id: &str) -> DomainResult<()> {
+        if !self.executions.cancel(tenant, execution_id) {
+            return Err(DomainError::ExecutionNotFound(execution_id.to_string()));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use crate::domain::entities::{Job, JobStatus};
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::repositories::JobRepository;
+use crate::use_cases::cancellation::CancellationRegistry;
+use crate::use_cases::submit_job::notify_job;
+use crate::use_cases::WebhookNotifier;
+use std::sync::Arc;
+
+pub struct CancelJobUseCase {
+    jobs: Arc<dyn JobRepository>,
+    cancellations: Arc<CancellationRegistry>,
+    webhooks: Arc<WebhookNotifier>,
+}
+
+impl CancelJobUseCase {
+    pub fn new(
+        jobs: Arc<dyn JobRepository>,
+        cancellations: Arc<CancellationRegistry>,
+        webhooks: Arc<WebhookNotifier>,
+    ) -> Self {
+        Self {
+            jobs,
+            cancellations,
+            webhooks,
+        }
+    }
+
+    /// Cancel a job of `tenant`. Jobs of other tenants are reported as not found.
+    pub async fn execute(&self, tenant: &str, job_id: &str) -> DomainResult<Job> {
+        let mut job = self.jobs.find_by_id(job_id).await?;
+        if job.tenant != tenant {
+            return Err(DomainError::JobNotFound(job_id.to_string()));
+        }
+        if job.status.is_terminal() {
+            return Err(DomainError::InvalidRequest(format!(
+                "Job {} has already completed",
+                job_id
+            )));
+        }
+
+        // The job task kills a running program, then cleans up and releases its box
+        self.cancellations.cancel(tenant, job_id);
+
+        job.set_status(JobStatus::Cancelled);
+        self.jobs.update(job).await?;
+
+        // The job may have completed before it could be cancelled, in which case it
+        // already sent its own webhook
+        let job = self.jobs.find_by_id(job_id).await?;
+        if job.status == JobStatus::Cancelled {
+            notify_job(&self.webhooks, &job);
+        }
+
+        Ok(job)
+    }
+}
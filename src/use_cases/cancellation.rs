@@ -0,0 +1,142 @@
+use crate::domain::error::{DomainError, DomainResult};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Owning tenant and job or execution ID of a registered execution. IDs are chosen by
+/// clients, so each tenant has its own.
+type ExecutionKey = (String, String);
+
+/// Cancellation tokens of in-flight executions, keyed by tenant and job or execution ID
+#[derive(Default)]
+pub struct CancellationRegistry {
+    tokens: Mutex<HashMap<ExecutionKey, (u64, CancellationToken)>>,
+    /// Tells apart registrations that reuse the ID of an earlier, cancelled one
+    next_serial: AtomicU64,
+}
+
+impl CancellationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new execution of `tenant` under `id`, which must not already be in flight
+    /// for that tenant.
+    ///
+    /// The execution stays registered, and can be cancelled, until the returned
+    /// registration is dropped.
+    pub fn register(self: &Arc<Self>, tenant: &str, id: &str) -> DomainResult<Registration> {
+        let key = (tenant.to_string(), id.to_string());
+        let mut tokens = self.tokens.lock().unwrap();
+        if tokens.contains_key(&key) {
+            return Err(DomainError::InvalidRequest(format!(
+                "Execution {} is already running",
+                id
+            )));
+        }
+
+        let serial = self.next_serial.fetch_add(1, Ordering::Relaxed);
+        let token = CancellationToken::new();
+        tokens.insert(key.clone(), (serial, token.clone()));
+        Ok(Registration {
+            registry: self.clone(),
+            key,
+            serial,
+            token,
+        })
+    }
+
+    /// Cancel the execution `tenant` registered under `id`, returning whether there was one
+    pub fn cancel(&self, tenant: &str, id: &str) -> bool {
+        let key = (tenant.to_string(), id.to_string());
+        match self.tokens.lock().unwrap().remove(&key) {
+            Some((_, token)) => {
+                log::debug!("Cancelling execution {}", id);
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// An execution registered for cancellation.
+///
+/// Dropping it deregisters the execution and cancels its token, so work that outlives its
+/// owner, e.g. a request whose client disconnected, is stopped.
+pub struct Registration {
+    registry: Arc<CancellationRegistry>,
+    key: ExecutionKey,
+    serial: u64,
+    token: CancellationToken,
+}
+
+impl Registration {
+    pub fn id(&self) -> &str {
+        &self.key.1
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut tokens = self.registry.tokens.lock().unwrap();
+        // The entry is already gone if the execution was cancelled through the registry
+        if tokens
+            .get(&self.key)
+            .is_some_and(|(serial, _)| *serial == self.serial)
+        {
+            tokens.remove(&self.key);
+        }
+        self.token.cancel();
+    }
+}
+
+/// Await `future`, failing with `Cancelled` if `cancel` is triggered first
+pub(crate) async fn cancellable<F, T>(cancel: &CancellationToken, future: F) -> DomainResult<T>
+where
+    F: Future<Output = DomainResult<T>>,
+{
+    tokio::select! {
+        result = future => result,
+        _ = cancel.cancelled() => Err(DomainError::Cancelled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn executions_are_scoped_to_tenants() {
+        let registry = Arc::new(CancellationRegistry::new());
+        let a = registry.register("a", "run-1").unwrap();
+        let b = registry.register("b", "run-1").unwrap();
+        assert!(matches!(
+            registry.register("a", "run-1"),
+            Err(DomainError::InvalidRequest(_))
+        ));
+
+        assert!(!registry.cancel("c", "run-1"));
+        assert!(registry.cancel("b", "run-1"));
+        assert!(b.token().is_cancelled());
+        assert!(!a.token().is_cancelled());
+    }
+
+    #[test]
+    fn dropped_registration_frees_its_id() {
+        let registry = Arc::new(CancellationRegistry::new());
+        let registration = registry.register("a", "run-1").unwrap();
+        let token = registration.token().clone();
+        drop(registration);
+
+        assert!(token.is_cancelled());
+        assert!(!registry.cancel("a", "run-1"));
+        assert!(registry.register("a", "run-1").is_ok());
+    }
+}
@@ -1,17 +1,24 @@
 use crate::domain::error::DomainResult;
 use crate::domain::services::SandboxService;
+use crate::use_cases::sessions::SessionRegistry;
 use std::sync::Arc;
 
 pub struct CleanupBoxUseCase {
     sandbox: Arc<dyn SandboxService>,
+    sessions: Arc<SessionRegistry>,
 }
 
 impl CleanupBoxUseCase {
-    pub fn new(sandbox: Arc<dyn SandboxService>) -> Self {
-        Self { sandbox }
+    pub fn new(sandbox: Arc<dyn SandboxService>, sessions: Arc<SessionRegistry>) -> Self {
+        Self { sandbox, sessions }
     }
 
-    pub async fn execute(&self, box_id: u32) -> DomainResult<()> {
+    pub async fn execute(&self, box_id: u32, tenant: &str) -> DomainResult<()> {
+        // End the session held in the box, if any, refusing boxes of other tenants' sessions
+        if self.sessions.close(box_id, tenant)? {
+            log::debug!("Closed session in box ID: {}", box_id);
+        }
+
         // Cleanup the sandbox
         self.sandbox.cleanup(box_id).await?;
         
@@ -0,0 +1,40 @@
+use crate::domain::diagnostics::parse_diagnostics;
+use crate::domain::entities::{CompileReport, ProgramSource};
+use crate::domain::error::DomainResult;
+use crate::domain::repositories::LanguageRepository;
+use crate::domain::services::CompilerService;
+use crate::use_cases::program::write_program;
+use std::sync::Arc;
+
+pub struct CompileCodeUseCase {
+    language_repo: Arc<dyn LanguageRepository>,
+    compiler: Arc<dyn CompilerService>,
+}
+
+impl CompileCodeUseCase {
+    pub fn new(
+        language_repo: Arc<dyn LanguageRepository>,
+        compiler: Arc<dyn CompilerService>,
+    ) -> Self {
+        Self {
+            language_repo,
+            compiler,
+        }
+    }
+
+    /// Compile a program without running it, reporting the compiler diagnostics.
+    ///
+    /// A program rejected by the compiler is reported as unsuccessful rather than as an
+    /// error; compilations killed for exceeding their limits still fail.
+    pub async fn execute(&self, source: ProgramSource) -> DomainResult<CompileReport> {
+        let sources = write_program(self.language_repo.as_ref(), &source).await?;
+        let output = sources.compile(self.compiler.as_ref()).await?;
+
+        Ok(CompileReport {
+            diagnostics: parse_diagnostics(&sources.language.name, &output.stderr),
+            success: output.binary_path.is_some(),
+            exit_code: output.exit_code,
+            output: output.stderr,
+        })
+    }
+}
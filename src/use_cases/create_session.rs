@@ -0,0 +1,19 @@
+use crate::domain::entities::SchedulingContext;
+use crate::domain::error::DomainResult;
+use crate::use_cases::sessions::SessionRegistry;
+use std::sync::Arc;
+
+pub struct CreateSessionUseCase {
+    sessions: Arc<SessionRegistry>,
+}
+
+impl CreateSessionUseCase {
+    pub fn new(sessions: Arc<SessionRegistry>) -> Self {
+        Self { sessions }
+    }
+
+    /// Allocate a box for a session, returning its ID
+    pub async fn execute(&self, scheduling: &SchedulingContext) -> DomainResult<u32> {
+        self.sessions.open(scheduling).await
+    }
+}
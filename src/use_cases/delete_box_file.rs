@@ -0,0 +1,102 @@
+use crate::domain::error::DomainResult;
+use crate::domain::paths::normalize_relative_path;
+use crate::domain::services::SandboxService;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub struct DeleteBoxFileUseCase {
+    sandbox: Arc<dyn SandboxService>,
+}
+
+impl DeleteBoxFileUseCase {
+    pub fn new(sandbox: Arc<dyn SandboxService>) -> Self {
+        Self { sandbox }
+    }
+
+    /// Delete a file from a box of `tenant`, or a directory and everything in it when
+    /// `recursive` is set. Returns the normalized path that was deleted.
+    pub async fn execute(
+        &self,
+        box_id: u32,
+        tenant: &str,
+        path: &str,
+        recursive: bool,
+    ) -> DomainResult<PathBuf> {
+        self.sandbox.check_box_owner(box_id, tenant)?;
+        let path = normalize_relative_path(path)?;
+        self.sandbox.remove_path(box_id, &path, recursive).await?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{SchedulingContext, DEFAULT_TENANT};
+    use crate::domain::error::DomainError;
+    use crate::use_cases::testing::FakeSandbox;
+
+    /// Sandbox whose first box is held by the default tenant
+    async fn sandbox() -> Arc<FakeSandbox> {
+        let sandbox = Arc::new(FakeSandbox::new(2));
+        let scheduling = SchedulingContext::default();
+        sandbox.acquire_box_id(&scheduling).await.unwrap();
+        sandbox
+    }
+
+    #[tokio::test]
+    async fn delete_removes_the_normalized_path() {
+        let sandbox = sandbox().await;
+        let delete = DeleteBoxFileUseCase::new(sandbox.clone());
+
+        let path = delete.execute(0, DEFAULT_TENANT, "out/./data", false).await;
+        assert_eq!(path.unwrap(), PathBuf::from("out/data"));
+        delete
+            .execute(0, DEFAULT_TENANT, "out", true)
+            .await
+            .unwrap();
+
+        let removed = sandbox.removed.lock().unwrap().clone();
+        assert_eq!(
+            removed,
+            [
+                (0, PathBuf::from("out/data"), false),
+                (0, PathBuf::from("out"), true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn paths_outside_the_box_are_rejected() {
+        let sandbox = sandbox().await;
+        let delete = DeleteBoxFileUseCase::new(sandbox.clone());
+
+        for path in ["..", "../other/file", "out/../../file", "/etc/passwd", "."] {
+            let result = delete.execute(0, DEFAULT_TENANT, path, true).await;
+            assert!(
+                matches!(result, Err(DomainError::InvalidRequest(_))),
+                "{:?}",
+                path
+            );
+        }
+        assert!(sandbox.removed.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn boxes_of_other_tenants_are_not_found() {
+        let sandbox = sandbox().await;
+        let delete = DeleteBoxFileUseCase::new(sandbox.clone());
+
+        // Held by another tenant, and held by no one
+        for (box_id, tenant) in [(0, "other"), (1, DEFAULT_TENANT)] {
+            let result = delete.execute(box_id, tenant, "data.txt", false).await;
+            assert!(
+                matches!(result, Err(DomainError::BoxNotFound(id)) if id == box_id),
+                "box {} of {}",
+                box_id,
+                tenant
+            );
+        }
+        assert!(sandbox.removed.lock().unwrap().is_empty());
+    }
+}
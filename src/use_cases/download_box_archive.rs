@@ -0,0 +1,199 @@
+use crate::domain::entities::{ArchiveFormat, BoxEntryKind};
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::paths::Glob;
+use crate::domain::services::SandboxService;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::fs::File;
+
+/// An archive of box contents, ready to be read
+pub struct BoxArchive {
+    pub file: File,
+    pub size: u64,
+}
+
+pub struct DownloadBoxArchiveUseCase {
+    sandbox: Arc<dyn SandboxService>,
+    /// Bytes of box contents archived at once (0 = unlimited)
+    max_archive_size: u64,
+}
+
+impl DownloadBoxArchiveUseCase {
+    pub fn new(sandbox: Arc<dyn SandboxService>, max_archive_size: u64) -> Self {
+        Self {
+            sandbox,
+            max_archive_size,
+        }
+    }
+
+    fn check_size(&self, size: u64, what: &str) -> DomainResult<()> {
+        if self.max_archive_size > 0 && size > self.max_archive_size {
+            return Err(DomainError::PayloadTooLarge(format!(
+                "{} of {} bytes exceeds the archive limit of {} bytes",
+                what, size, self.max_archive_size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Archive the entries of a box of `tenant` matching the `include` glob, or all of them
+    pub async fn execute(
+        &self,
+        box_id: u32,
+        tenant: &str,
+        format: ArchiveFormat,
+        include: Option<&str>,
+    ) -> DomainResult<BoxArchive> {
+        self.sandbox.check_box_owner(box_id, tenant)?;
+        let glob = include.map(Glob::new).transpose()?;
+
+        let entries = self.sandbox.list_entries(box_id, true).await?;
+        let entries: Vec<_> = entries
+            .into_iter()
+            .filter(|entry| glob.as_ref().is_none_or(|glob| glob.is_match(&entry.path)))
+            .collect();
+
+        // Refuse before packing anything when the files alone are too large
+        let content_size = entries
+            .iter()
+            .filter(|entry| entry.kind == BoxEntryKind::File)
+            .map(|entry| entry.size)
+            .sum();
+        self.check_size(content_size, "Box content")?;
+
+        let paths: Vec<String> = entries.into_iter().map(|entry| entry.path).collect();
+        log::debug!(
+            "Archiving {} entries of box ID: {} as {}",
+            paths.len(),
+            box_id,
+            format.extension()
+        );
+
+        // The archive is staged on the host and read back from there; the open file stays
+        // readable once the temporary directory is removed
+        let temp_dir = TempDir::new()?;
+        let archive_path = temp_dir
+            .path()
+            .join(format!("archive.{}", format.extension()));
+        self.sandbox
+            .archive(box_id, &paths, format, &archive_path)
+            .await?;
+
+        let file = File::open(&archive_path).await?;
+        let size = file.metadata().await?.len();
+        // Files may have grown while they were packed
+        self.check_size(size, "Archive")?;
+
+        Ok(BoxArchive { file, size })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{SchedulingContext, DEFAULT_TENANT};
+    use crate::use_cases::testing::{entry, FakeSandbox};
+    use tokio::io::AsyncReadExt;
+
+    /// Sandbox listing the same entries in every box, the first of which is held by the
+    /// default tenant. Its archives hold the archived paths, one per line.
+    async fn sandbox() -> Arc<FakeSandbox> {
+        let mut sandbox = FakeSandbox::new(2);
+        sandbox.entries = vec![
+            entry("main.py", BoxEntryKind::File, 40),
+            entry("out", BoxEntryKind::Directory, 4096),
+            entry("out/data.bin", BoxEntryKind::File, 60),
+            entry("out/log.txt", BoxEntryKind::File, 10),
+            entry("out/latest", BoxEntryKind::Symlink, 7),
+        ];
+        let sandbox = Arc::new(sandbox);
+        let scheduling = SchedulingContext::default();
+        sandbox.acquire_box_id(&scheduling).await.unwrap();
+        sandbox
+    }
+
+    async fn archived_paths(archive: BoxArchive) -> Vec<String> {
+        let mut content = String::new();
+        let mut file = archive.file;
+        file.read_to_string(&mut content).await.unwrap();
+        content.lines().map(str::to_string).collect()
+    }
+
+    #[tokio::test]
+    async fn include_selects_the_archived_entries() {
+        let sandbox = sandbox().await;
+        let download = DownloadBoxArchiveUseCase::new(sandbox.clone(), 0);
+        let cases: [(Option<&str>, &[&str]); 3] = [
+            (
+                None,
+                &[
+                    "main.py",
+                    "out",
+                    "out/data.bin",
+                    "out/log.txt",
+                    "out/latest",
+                ],
+            ),
+            (Some("out/*.txt"), &["out/log.txt"]),
+            (Some("*.rs"), &[]),
+        ];
+
+        for (include, expected) in cases {
+            let archive = download
+                .execute(0, DEFAULT_TENANT, ArchiveFormat::TarGz, include)
+                .await
+                .unwrap();
+            assert_eq!(archived_paths(archive).await, expected, "{:?}", include);
+        }
+    }
+
+    #[tokio::test]
+    async fn content_over_the_ceiling_is_refused_before_packing() {
+        let sandbox = sandbox().await;
+        // The files take 110 bytes; directories and symlinks do not count
+        let download = DownloadBoxArchiveUseCase::new(sandbox.clone(), 109);
+
+        let result = download
+            .execute(0, DEFAULT_TENANT, ArchiveFormat::Zip, None)
+            .await;
+        assert!(matches!(result, Err(DomainError::PayloadTooLarge(_))));
+        assert!(sandbox.archived.lock().unwrap().is_empty());
+
+        // Leaving out the larger files brings the content under the ceiling
+        let archive = download
+            .execute(0, DEFAULT_TENANT, ArchiveFormat::Zip, Some("*.txt"))
+            .await;
+        assert_eq!(archived_paths(archive.unwrap()).await, ["out/log.txt"]);
+    }
+
+    #[tokio::test]
+    async fn archive_over_the_ceiling_is_refused() {
+        let sandbox = sandbox().await;
+        // A symlink has no content, but the packed archive ("out/latest") is 10 bytes
+        let download = DownloadBoxArchiveUseCase::new(sandbox.clone(), 8);
+
+        let result = download
+            .execute(0, DEFAULT_TENANT, ArchiveFormat::TarGz, Some("latest"))
+            .await;
+        assert!(matches!(result, Err(DomainError::PayloadTooLarge(_))));
+        assert_eq!(sandbox.archived.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn boxes_of_other_tenants_are_not_found() {
+        let download = DownloadBoxArchiveUseCase::new(sandbox().await, 0);
+
+        // Held by another tenant, and held by no one
+        for (box_id, tenant) in [(0, "other"), (1, DEFAULT_TENANT)] {
+            let result = download
+                .execute(box_id, tenant, ArchiveFormat::TarGz, None)
+                .await;
+            assert!(
+                matches!(result, Err(DomainError::BoxNotFound(id)) if id == box_id),
+                "box {} of {}",
+                box_id,
+                tenant
+            );
+        }
+    }
+}
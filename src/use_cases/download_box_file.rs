@@ -0,0 +1,120 @@
+use crate::domain::entities::{BoxEntry, BoxEntryKind};
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::paths::normalize_relative_path;
+use crate::domain::services::{FileReader, SandboxService};
+use std::path::Path;
+use std::sync::Arc;
+
+pub struct DownloadBoxFileUseCase {
+    sandbox: Arc<dyn SandboxService>,
+}
+
+impl DownloadBoxFileUseCase {
+    pub fn new(sandbox: Arc<dyn SandboxService>) -> Self {
+        Self { sandbox }
+    }
+
+    /// Metadata of a regular file in a box of `tenant`, with the path it resolves to
+    pub async fn stat(&self, box_id: u32, tenant: &str, path: &str) -> DomainResult<BoxEntry> {
+        self.sandbox.check_box_owner(box_id, tenant)?;
+        let box_path = normalize_relative_path(path)?;
+        let entry = self.sandbox.stat_path(box_id, &box_path).await?;
+
+        match entry.kind {
+            BoxEntryKind::File => Ok(entry),
+            BoxEntryKind::Directory => Err(DomainError::InvalidRequest(format!(
+                "Path '{}' is a directory",
+                path
+            ))),
+            _ => Err(DomainError::InvalidRequest(format!(
+                "Path '{}' is not a regular file",
+                path
+            ))),
+        }
+    }
+
+    /// Stream `length` bytes of a file returned by `stat`, starting at byte `offset`
+    pub async fn open(
+        &self,
+        box_id: u32,
+        tenant: &str,
+        file: &BoxEntry,
+        offset: u64,
+        length: u64,
+    ) -> DomainResult<FileReader> {
+        // The box may have been released since `stat`
+        self.sandbox.check_box_owner(box_id, tenant)?;
+        log::debug!(
+            "Streaming {} bytes of {} from box ID: {} at offset {}",
+            length,
+            file.path,
+            box_id,
+            offset
+        );
+        self.sandbox
+            .read_file(box_id, Path::new(&file.path), offset, length)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{SchedulingContext, DEFAULT_TENANT};
+    use crate::use_cases::testing::{entry, FakeSandbox};
+
+    async fn download() -> DownloadBoxFileUseCase {
+        let mut sandbox = FakeSandbox::new(2);
+        sandbox.entries = vec![
+            entry("out", BoxEntryKind::Directory, 4096),
+            entry("out/log.txt", BoxEntryKind::File, 10),
+            entry("out/latest", BoxEntryKind::Symlink, 7),
+        ];
+        let sandbox = Arc::new(sandbox);
+        let scheduling = SchedulingContext::default();
+        sandbox.acquire_box_id(&scheduling).await.unwrap();
+        DownloadBoxFileUseCase::new(sandbox)
+    }
+
+    #[tokio::test]
+    async fn stat_only_accepts_regular_files() {
+        let download = download().await;
+
+        let file = download.stat(0, DEFAULT_TENANT, "./out/log.txt").await;
+        assert_eq!(file.unwrap().size, 10);
+
+        for path in ["out", "out/latest", "../out/log.txt"] {
+            let result = download.stat(0, DEFAULT_TENANT, path).await;
+            assert!(
+                matches!(result, Err(DomainError::InvalidRequest(_))),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn boxes_of_other_tenants_are_not_found() {
+        let download = download().await;
+        let file = entry("out/log.txt", BoxEntryKind::File, 10);
+
+        // Held by another tenant, and held by no one
+        for (box_id, tenant) in [(0, "other"), (1, DEFAULT_TENANT)] {
+            let stat = download.stat(box_id, tenant, "out/log.txt").await;
+            assert!(
+                matches!(stat, Err(DomainError::BoxNotFound(id)) if id == box_id),
+                "box {} of {}",
+                box_id,
+                tenant
+            );
+
+            let open = download.open(box_id, tenant, &file, 0, 10).await;
+            assert!(
+                matches!(open, Err(DomainError::BoxNotFound(id)) if id == box_id),
+                "box {} of {}",
+                box_id,
+                tenant
+            );
+        }
+    }
+}
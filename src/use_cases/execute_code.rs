@@ -1,10 +1,21 @@
-use crate::domain::entities::{ExecutionRequest, ExecutionResult};
-use crate::domain::error::DomainResult;
+use crate::domain::entities::{
+    ExecutionRequest, ExecutionResult, OutputChunk, OutputEncoding, ResourceLimits,
+    SchedulingContext,
+};
+use crate::domain::error::{DomainError, DomainResult};
 use crate::domain::repositories::LanguageRepository;
-use crate::domain::services::{CompilerService, SandboxExecutionConfig, SandboxService};
+use crate::domain::services::{
+    CompilerService, SandboxExecutionConfig, SandboxExecutionResult, SandboxService,
+};
+use crate::use_cases::cancellation::cancellable;
+use crate::use_cases::program::{
+    compile_program, encode_output, validate_args_and_env, CompiledProgram,
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tempfile::TempDir;
-use tokio::fs;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
 pub struct ExecuteCodeUseCase {
     language_repo: Arc<dyn LanguageRepository>,
@@ -25,59 +36,199 @@ impl ExecuteCodeUseCase {
         }
     }
 
-    pub async fn execute(&self, request: ExecutionRequest) -> DomainResult<ExecutionResult> {
-        // Find language configuration
-        log::debug!("Finding language configuration for: {}", request.language);
-        let language = self.language_repo.find_by_name(&request.language).await?;
-        log::debug!("Found language: {} with extension: {}", language.name, language.extension);
-
-        // Create temporary directory for this execution
-        log::debug!("Creating temporary directory for execution");
-        let temp_dir = TempDir::new()?;
-        let temp_path = temp_dir.path();
-        log::debug!("Created temporary directory at: {:?}", temp_path);
-
-        // Write source code to temp directory
-        let source_file = temp_path.join(format!("source.{}", language.extension));
-        log::debug!("Writing source code to: {:?} ({} bytes)", source_file, request.code.len());
-        fs::write(&source_file, &request.code).await?;
-        log::debug!("Source code written successfully");
-
-        // Compile the code
-        log::debug!("Compiling code for language: {}", language.name);
-        let binary_path = self.compiler.compile(&language, &source_file, &temp_path.to_path_buf()).await?;
-        log::debug!("Code compiled successfully, binary path: {:?}", binary_path);
+    /// Execute the program, stopping wherever it got to when `cancel` is triggered
+    pub async fn execute(
+        &self,
+        request: ExecutionRequest,
+        cancel: &CancellationToken,
+    ) -> DomainResult<ExecutionResult> {
+        let prepared = cancellable(cancel, self.compile(request)).await?;
 
         // Acquire box ID from pool
         log::debug!("Acquiring box ID from pool");
-        let box_id = self.sandbox.acquire_box_id().await?;
+        let box_id = cancellable(cancel, self.sandbox.acquire_box_id(prepared.scheduling())).await?;
         log::debug!("Acquired box ID: {}", box_id);
 
-        // Execute in sandbox
-        let metadata_path = temp_path.join(format!("meta-{}.txt", box_id));
-        log::debug!("Configuring sandbox execution for box ID: {}", box_id);
-        let config = SandboxExecutionConfig {
-            box_id,
-            binary_path,
-            runner_path: language.runner_path(),
-            language: language.clone(),
-            metadata_path,
+        self.run(prepared, box_id, cancel).await
+    }
+
+    /// Execute the program, sending its output to `output` while it runs.
+    ///
+    /// The returned result carries the metadata and box ID; stdout and stderr are empty
+    /// since they were already streamed.
+    pub async fn execute_streaming(
+        &self,
+        request: ExecutionRequest,
+        output: mpsc::Sender<OutputChunk>,
+        cancel: &CancellationToken,
+    ) -> DomainResult<ExecutionResult> {
+        let prepared = cancellable(cancel, self.compile(request)).await?;
+
+        log::debug!("Acquiring box ID from pool");
+        let box_id = cancellable(cancel, self.sandbox.acquire_box_id(prepared.scheduling())).await?;
+        log::debug!("Acquired box ID: {}", box_id);
+
+        log::debug!(
+            "Streaming code execution in sandbox with box ID: {}",
+            box_id
+        );
+        let sandbox_result = self
+            .sandbox
+            .execute_streaming(prepared.sandbox_config(box_id, cancel), output)
+            .await;
+        let sandbox_result = self.release_if_cancelled(box_id, sandbox_result).await?;
+        log::debug!("Sandbox execution completed for box ID: {}", box_id);
+
+        // As with `execute`, the box is kept for inspection until cleaned up explicitly
+        Ok(execution_result(sandbox_result, prepared.output_encoding))
+    }
+
+    /// Check the parts of a request that can be rejected without compiling it
+    pub fn validate(&self, request: &ExecutionRequest) -> DomainResult<ResourceLimits> {
+        // Resolve resource limits before doing any work so invalid overrides fail fast
+        let limits = self.sandbox.resolve_limits(&request.limits)?;
+        log::debug!("Resolved resource limits: {:?}", limits);
+
+        validate_args_and_env(&request.args, &request.env)?;
+
+        Ok(limits)
+    }
+
+    /// Validate the request and compile the program, ready to run in a box
+    pub async fn compile(&self, request: ExecutionRequest) -> DomainResult<PreparedExecution> {
+        let limits = self.validate(&request)?;
+
+        // Write and compile the sources
+        let program = compile_program(
+            self.language_repo.as_ref(),
+            self.compiler.as_ref(),
+            &request.program,
+        )
+        .await?;
+
+        // Write standard input to temp directory if provided
+        let stdin_path = match &request.stdin {
+            Some(stdin) => Some(program.write_stdin("stdin", stdin).await?),
+            None => None,
         };
 
+        Ok(PreparedExecution {
+            program,
+            limits,
+            stdin_path,
+            args: request.args,
+            env: request.env,
+            scheduling: request.scheduling,
+            output_encoding: request.output_encoding,
+        })
+    }
+
+    /// Run a compiled program in an already acquired box.
+    ///
+    /// If the run is cancelled, the box has been cleaned up and is released to the pool.
+    pub async fn run(
+        &self,
+        prepared: PreparedExecution,
+        box_id: u32,
+        cancel: &CancellationToken,
+    ) -> DomainResult<ExecutionResult> {
         log::debug!("Executing code in sandbox with box ID: {}", box_id);
-        let sandbox_result = self.sandbox.execute(config).await?;
+        let sandbox_result = self
+            .sandbox
+            .execute(prepared.sandbox_config(box_id, cancel))
+            .await;
+        let sandbox_result = self.release_if_cancelled(box_id, sandbox_result).await?;
         log::debug!("Sandbox execution completed for box ID: {}", box_id);
 
         // Note: Box ID is NOT released here anymore - it must be explicitly cleaned up
         // via the cleanup endpoint to allow file inspection after execution
 
-        log::debug!("Returning execution result with box ID: {}", sandbox_result.box_id);
-        Ok(ExecutionResult {
-            stdout: sandbox_result.stdout,
-            stderr: sandbox_result.stderr,
-            metadata: sandbox_result.metadata,
-            box_id: sandbox_result.box_id,
-        })
+        log::debug!(
+            "Returning execution result with box ID: {}",
+            sandbox_result.box_id
+        );
+        Ok(execution_result(sandbox_result, prepared.output_encoding))
+    }
+
+    /// Run a compiled program in the initialized box of a session, keeping the files left
+    /// there by earlier runs.
+    ///
+    /// If the run is cancelled, the box has been cleaned up but is still held.
+    pub async fn run_in_session(
+        &self,
+        prepared: PreparedExecution,
+        box_id: u32,
+        cancel: &CancellationToken,
+    ) -> DomainResult<ExecutionResult> {
+        log::debug!("Executing code in session box ID: {}", box_id);
+        let config = SandboxExecutionConfig {
+            reuse_box: true,
+            ..prepared.sandbox_config(box_id, cancel)
+        };
+        let sandbox_result = self.sandbox.execute(config).await?;
+        log::debug!("Session execution completed for box ID: {}", box_id);
+
+        Ok(execution_result(sandbox_result, prepared.output_encoding))
+    }
+
+    /// Return the box of a cancelled run to the pool, since nobody will inspect it
+    async fn release_if_cancelled<T>(
+        &self,
+        box_id: u32,
+        result: DomainResult<T>,
+    ) -> DomainResult<T> {
+        if let Err(DomainError::Cancelled) = result {
+            log::debug!("Releasing box ID: {} of a cancelled execution", box_id);
+            self.sandbox.release_box_id(box_id).await?;
+        }
+        result
     }
 }
 
+/// A compiled program with everything needed to run it.
+///
+/// Owns the temporary directory holding the binary, so it must outlive the execution.
+pub struct PreparedExecution {
+    program: CompiledProgram,
+    limits: ResourceLimits,
+    stdin_path: Option<PathBuf>,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    scheduling: SchedulingContext,
+    output_encoding: OutputEncoding,
+}
+
+impl PreparedExecution {
+    /// Scheduling context to acquire the box to run in with
+    pub fn scheduling(&self) -> &SchedulingContext {
+        &self.scheduling
+    }
+
+    fn sandbox_config(&self, box_id: u32, cancel: &CancellationToken) -> SandboxExecutionConfig {
+        SandboxExecutionConfig {
+            stdin_path: self.stdin_path.clone(),
+            args: self.args.clone(),
+            env: self.env.clone(),
+            cancel: cancel.clone(),
+            ..self.program.sandbox_config(box_id, self.limits)
+        }
+    }
+}
+
+fn execution_result(result: SandboxExecutionResult, encoding: OutputEncoding) -> ExecutionResult {
+    let (stdout, stdout_encoding) =
+        encode_output(result.stdout, encoding, result.stdout_size.truncated);
+    let (stderr, stderr_encoding) =
+        encode_output(result.stderr, encoding, result.stderr_size.truncated);
+
+    ExecutionResult {
+        stdout,
+        stderr,
+        stdout_encoding,
+        stderr_encoding,
+        stdout_size: result.stdout_size,
+        stderr_size: result.stderr_size,
+        metadata: result.metadata,
+        box_id: result.box_id,
+    }
+}
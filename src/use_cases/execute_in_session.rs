@@ -0,0 +1,45 @@
+use crate::domain::entities::{ExecutionRequest, ExecutionResult};
+use crate::domain::error::{DomainError, DomainResult};
+use crate::use_cases::cancellation::cancellable;
+use crate::use_cases::execute_code::ExecuteCodeUseCase;
+use crate::use_cases::sessions::SessionRegistry;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+pub struct ExecuteInSessionUseCase {
+    execute_code: Arc<ExecuteCodeUseCase>,
+    sessions: Arc<SessionRegistry>,
+}
+
+impl ExecuteInSessionUseCase {
+    pub fn new(execute_code: Arc<ExecuteCodeUseCase>, sessions: Arc<SessionRegistry>) -> Self {
+        Self {
+            execute_code,
+            sessions,
+        }
+    }
+
+    /// Run a program in the box of a session, where the files of earlier runs are kept.
+    ///
+    /// Runs in the same session are serialized. Cancelling a run resets the box, which ends
+    /// the session.
+    pub async fn execute(
+        &self,
+        box_id: u32,
+        request: ExecutionRequest,
+        cancel: &CancellationToken,
+    ) -> DomainResult<ExecutionResult> {
+        let tenant = request.scheduling.tenant.clone();
+        let _session = cancellable(cancel, self.sessions.enter(box_id, &tenant)).await?;
+
+        let prepared = cancellable(cancel, self.execute_code.compile(request)).await?;
+        let result = self
+            .execute_code
+            .run_in_session(prepared, box_id, cancel)
+            .await;
+        if let Err(DomainError::Cancelled) = result {
+            self.sessions.discard(box_id, &tenant).await?;
+        }
+        result
+    }
+}
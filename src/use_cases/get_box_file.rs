@@ -1,4 +1,5 @@
-use crate::domain::error::DomainResult;
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::paths::normalize_relative_path;
 use crate::domain::services::SandboxService;
 use std::sync::Arc;
 
@@ -11,8 +12,15 @@ impl GetBoxFileUseCase {
         Self { sandbox }
     }
 
-    pub async fn execute(&self, box_id: u32, filename: &str) -> DomainResult<String> {
-        self.sandbox.get_file_base64(box_id, filename).await
+    pub async fn execute(&self, box_id: u32, tenant: &str, filename: &str) -> DomainResult<String> {
+        self.sandbox.check_box_owner(box_id, tenant)?;
+
+        // Nested paths are allowed, but must stay within the box
+        let path = normalize_relative_path(filename)?;
+        let path = path
+            .to_str()
+            .ok_or_else(|| DomainError::InvalidRequest("Invalid file path".to_string()))?;
+
+        self.sandbox.get_file_base64(box_id, path).await
     }
 }
-
@@ -0,0 +1,17 @@
+use crate::domain::services::{CompileCacheStats, CompilerService};
+use std::sync::Arc;
+
+pub struct GetCompileCacheStatsUseCase {
+    compiler: Arc<dyn CompilerService>,
+}
+
+impl GetCompileCacheStatsUseCase {
+    pub fn new(compiler: Arc<dyn CompilerService>) -> Self {
+        Self { compiler }
+    }
+
+    /// Counters of the compile cache, or `None` when caching is disabled
+    pub async fn execute(&self) -> Option<CompileCacheStats> {
+        self.compiler.cache_stats()
+    }
+}
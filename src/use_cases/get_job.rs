@@ -0,0 +1,23 @@
+use crate::domain::entities::Job;
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::repositories::JobRepository;
+use std::sync::Arc;
+
+pub struct GetJobUseCase {
+    jobs: Arc<dyn JobRepository>,
+}
+
+impl GetJobUseCase {
+    pub fn new(jobs: Arc<dyn JobRepository>) -> Self {
+        Self { jobs }
+    }
+
+    /// Find a job of `tenant`. Jobs of other tenants are reported as not found.
+    pub async fn execute(&self, tenant: &str, job_id: &str) -> DomainResult<Job> {
+        let job = self.jobs.find_by_id(job_id).await?;
+        if job.tenant != tenant {
+            return Err(DomainError::JobNotFound(job_id.to_string()));
+        }
+        Ok(job)
+    }
+}
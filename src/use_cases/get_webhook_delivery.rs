@@ -0,0 +1,51 @@
+use crate::domain::entities::WebhookDelivery;
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::repositories::WebhookDeliveryRepository;
+use std::sync::Arc;
+
+pub struct GetWebhookDeliveryUseCase {
+    deliveries: Arc<dyn WebhookDeliveryRepository>,
+}
+
+impl GetWebhookDeliveryUseCase {
+    pub fn new(deliveries: Arc<dyn WebhookDeliveryRepository>) -> Self {
+        Self { deliveries }
+    }
+
+    /// Find a delivery of `tenant`. Deliveries of other tenants are reported as not found.
+    pub async fn execute(&self, tenant: &str, delivery_id: &str) -> DomainResult<WebhookDelivery> {
+        let delivery = self.deliveries.find_by_id(delivery_id).await?;
+        if delivery.tenant != tenant {
+            return Err(DomainError::DeliveryNotFound(delivery_id.to_string()));
+        }
+        Ok(delivery)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external::repositories::InMemoryWebhookDeliveryRepository;
+
+    #[tokio::test]
+    async fn deliveries_of_other_tenants_are_not_found() {
+        let deliveries = Arc::new(InMemoryWebhookDeliveryRepository::new());
+        let delivery = WebhookDelivery::new(
+            "delivery".to_string(),
+            "a".to_string(),
+            "https://example.com/hook".to_string(),
+            None,
+        );
+        deliveries.insert(delivery).await.unwrap();
+        let use_case = GetWebhookDeliveryUseCase::new(deliveries);
+
+        assert_eq!(
+            use_case.execute("a", "delivery").await.unwrap().id,
+            "delivery"
+        );
+        assert!(matches!(
+            use_case.execute("b", "delivery").await,
+            Err(DomainError::DeliveryNotFound(_))
+        ));
+    }
+}
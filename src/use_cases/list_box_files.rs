@@ -1,4 +1,6 @@
+use crate::domain::entities::{BoxEntryKind, BoxListing, BoxListingQuery};
 use crate::domain::error::DomainResult;
+use crate::domain::paths::Glob;
 use crate::domain::services::SandboxService;
 use std::sync::Arc;
 
@@ -11,8 +13,83 @@ impl ListBoxFilesUseCase {
         Self { sandbox }
     }
 
-    pub async fn execute(&self, box_id: u32) -> DomainResult<Vec<String>> {
-        self.sandbox.list_files(box_id).await
+    /// List a page of the entries of a box of `tenant` matching `query`
+    pub async fn execute(
+        &self,
+        box_id: u32,
+        tenant: &str,
+        query: BoxListingQuery,
+    ) -> DomainResult<BoxListing> {
+        self.sandbox.check_box_owner(box_id, tenant)?;
+        let glob = query.pattern.as_deref().map(Glob::new).transpose()?;
+
+        let mut entries = self.sandbox.list_entries(box_id, query.recursive).await?;
+        if let Some(glob) = &glob {
+            entries.retain(|entry| glob.is_match(&entry.path));
+        }
+
+        let total = entries.len();
+        let mut entries: Vec<_> = entries
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .collect();
+
+        // Only the files of the requested page are hashed
+        if query.sha256 {
+            let files: Vec<String> = entries
+                .iter()
+                .filter(|entry| entry.kind == BoxEntryKind::File)
+                .map(|entry| entry.path.clone())
+                .collect();
+            let mut hashes = self.sandbox.hash_files(box_id, &files).await?;
+            for entry in &mut entries {
+                entry.sha256 = hashes.remove(&entry.path);
+            }
+        }
+
+        Ok(BoxListing { entries, total })
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{SchedulingContext, DEFAULT_TENANT};
+    use crate::domain::error::DomainError;
+    use crate::use_cases::testing::{entry, FakeSandbox};
+
+    #[tokio::test]
+    async fn only_the_tenant_holding_the_box_can_list_it() {
+        let mut sandbox = FakeSandbox::new(2);
+        sandbox.entries = vec![
+            entry("main.py", BoxEntryKind::File, 12),
+            entry("out", BoxEntryKind::Directory, 0),
+        ];
+        let sandbox = Arc::new(sandbox);
+        let scheduling = SchedulingContext::default();
+        sandbox.acquire_box_id(&scheduling).await.unwrap();
+        let list = ListBoxFilesUseCase::new(sandbox);
+
+        let query = BoxListingQuery {
+            pattern: Some("*.py".to_string()),
+            ..BoxListingQuery::default()
+        };
+        let listing = list.execute(0, DEFAULT_TENANT, query).await.unwrap();
+        assert_eq!(listing.total, 1);
+        assert_eq!(listing.entries[0].path, "main.py");
+
+        // Held by another tenant, and held by no one
+        for (box_id, tenant) in [(0, "other"), (1, DEFAULT_TENANT)] {
+            let result = list
+                .execute(box_id, tenant, BoxListingQuery::default())
+                .await;
+            assert!(
+                matches!(result, Err(DomainError::BoxNotFound(id)) if id == box_id),
+                "box {} of {}",
+                box_id,
+                tenant
+            );
+        }
+    }
+}
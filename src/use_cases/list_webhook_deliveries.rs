@@ -0,0 +1,23 @@
+use crate::domain::entities::WebhookDelivery;
+use crate::domain::error::DomainResult;
+use crate::domain::repositories::WebhookDeliveryRepository;
+use std::sync::Arc;
+
+pub struct ListWebhookDeliveriesUseCase {
+    deliveries: Arc<dyn WebhookDeliveryRepository>,
+}
+
+impl ListWebhookDeliveriesUseCase {
+    pub fn new(deliveries: Arc<dyn WebhookDeliveryRepository>) -> Self {
+        Self { deliveries }
+    }
+
+    /// List the deliveries of `tenant`, optionally only those of one job
+    pub async fn execute(
+        &self,
+        tenant: &str,
+        job_id: Option<&str>,
+    ) -> DomainResult<Vec<WebhookDelivery>> {
+        self.deliveries.list(tenant, job_id).await
+    }
+}
@@ -1,14 +1,53 @@
+pub mod cancel_execution;
+pub mod cancel_job;
+pub mod cancellation;
 pub mod cleanup_box;
+pub mod compile_code;
+pub mod create_session;
+pub mod delete_box_file;
+pub mod download_box_archive;
+pub mod download_box_file;
 pub mod execute_code;
+pub mod execute_in_session;
 pub mod get_box_file;
+pub mod get_compile_cache_stats;
+pub mod get_job;
+pub mod get_webhook_delivery;
 pub mod health_check;
 pub mod list_box_files;
 pub mod list_languages;
+pub mod list_webhook_deliveries;
+pub mod program;
+pub mod run_test_cases;
+pub mod sessions;
+pub mod submit_job;
+#[cfg(test)]
+pub(crate) mod testing;
+pub mod upload_box_file;
+pub mod webhooks;
 
+pub use cancel_execution::CancelExecutionUseCase;
+pub use cancel_job::CancelJobUseCase;
+pub use cancellation::CancellationRegistry;
 pub use cleanup_box::CleanupBoxUseCase;
+pub use compile_code::CompileCodeUseCase;
+pub use create_session::CreateSessionUseCase;
+pub use delete_box_file::DeleteBoxFileUseCase;
+pub use download_box_archive::{BoxArchive, DownloadBoxArchiveUseCase};
+pub use download_box_file::DownloadBoxFileUseCase;
 pub use execute_code::ExecuteCodeUseCase;
+pub use execute_in_session::ExecuteInSessionUseCase;
 pub use get_box_file::GetBoxFileUseCase;
+pub use get_compile_cache_stats::GetCompileCacheStatsUseCase;
+pub use get_job::GetJobUseCase;
+pub use get_webhook_delivery::GetWebhookDeliveryUseCase;
 pub use health_check::HealthCheckUseCase;
 pub use list_box_files::ListBoxFilesUseCase;
 pub use list_languages::ListLanguagesUseCase;
+pub use list_webhook_deliveries::ListWebhookDeliveriesUseCase;
 
+pub use run_test_cases::RunTestCasesUseCase;
+pub use sessions::SessionRegistry;
+pub use submit_job::SubmitJobUseCase;
+pub use upload_box_file::UploadBoxFileUseCase;
+pub use webhooks::WebhookNotifier;
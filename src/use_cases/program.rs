@@ -0,0 +1,382 @@
+use crate::domain::entities::{Language, OutputEncoding, ProgramSource, ResourceLimits};
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::paths::normalize_relative_path;
+use crate::domain::repositories::LanguageRepository;
+use crate::domain::services::{
+    is_reserved_box_path, CompileOutput, CompilerService, SandboxExecutionConfig, SandboxFile,
+    RESERVED_ENV_VARS,
+};
+use base64::Engine;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tempfile::TempDir;
+use tokio::fs;
+use tokio_util::sync::CancellationToken;
+
+/// A program compiled into a temporary working directory, ready to be copied into a box
+pub struct CompiledProgram {
+    pub language: Language,
+    pub binary_path: PathBuf,
+    pub files: Vec<SandboxFile>,
+    temp_dir: TempDir,
+}
+
+impl CompiledProgram {
+    /// Temporary directory holding the sources, the binary and per-run files
+    pub fn work_dir(&self) -> &Path {
+        self.temp_dir.path()
+    }
+
+    /// Write standard input for a run into the working directory
+    pub async fn write_stdin(&self, name: &str, stdin: &[u8]) -> DomainResult<PathBuf> {
+        let stdin_file = self.work_dir().join(name);
+        log::debug!("Writing stdin to: {:?} ({} bytes)", stdin_file, stdin.len());
+        fs::write(&stdin_file, stdin).await?;
+        Ok(stdin_file)
+    }
+
+    /// Sandbox configuration running this program in the given box without input or arguments
+    pub fn sandbox_config(&self, box_id: u32, limits: ResourceLimits) -> SandboxExecutionConfig {
+        SandboxExecutionConfig {
+            box_id,
+            binary_path: self.binary_path.clone(),
+            runner_path: self.language.runner_path(),
+            files: self.files.clone(),
+            language: self.language.clone(),
+            metadata_path: self.work_dir().join(format!("meta-{}.txt", box_id)),
+            stdin_path: None,
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            limits,
+            cancel: CancellationToken::new(),
+            reuse_box: false,
+        }
+    }
+}
+
+/// Program sources written into a temporary directory, ready to be compiled
+pub struct ProgramSources {
+    pub language: Language,
+    /// Entry point of the program inside the source directory
+    pub source_file: PathBuf,
+    pub files: Vec<SandboxFile>,
+    temp_dir: TempDir,
+}
+
+impl ProgramSources {
+    /// Directory holding the submitted sources
+    pub fn source_dir(&self) -> PathBuf {
+        self.temp_dir.path().join("source")
+    }
+
+    /// Run the compiler of the language over the sources
+    pub async fn compile(&self, compiler: &dyn CompilerService) -> DomainResult<CompileOutput> {
+        log::debug!("Compiling code for language: {}", self.language.name);
+        compiler
+            .compile(&self.language, &self.source_file, &self.source_dir())
+            .await
+    }
+}
+
+/// Write the program sources into a temporary directory
+pub async fn write_program(
+    language_repo: &dyn LanguageRepository,
+    source: &ProgramSource,
+) -> DomainResult<ProgramSources> {
+    // Find language configuration
+    log::debug!("Finding language configuration for: {}", source.language);
+    let language = language_repo.find_by_name(&source.language).await?;
+    log::debug!(
+        "Found language: {} with extension: {}",
+        language.name,
+        language.extension
+    );
+
+    // Create temporary directory for this execution
+    log::debug!("Creating temporary directory for execution");
+    let temp_dir = TempDir::new()?;
+    log::debug!("Created temporary directory at: {:?}", temp_dir.path());
+
+    // Write source files to a dedicated working directory
+    let source_dir = temp_dir.path().join("source");
+    fs::create_dir(&source_dir).await?;
+    let (source_file, files) = write_sources(source, &language, &source_dir).await?;
+    log::debug!(
+        "Source code written successfully, entry point: {:?}",
+        source_file
+    );
+
+    Ok(ProgramSources {
+        language,
+        source_file,
+        files,
+        temp_dir,
+    })
+}
+
+/// Write the program sources into a temporary directory and compile them
+pub async fn compile_program(
+    language_repo: &dyn LanguageRepository,
+    compiler: &dyn CompilerService,
+    source: &ProgramSource,
+) -> DomainResult<CompiledProgram> {
+    let sources = write_program(language_repo, source).await?;
+
+    // Compile the code
+    let output = sources.compile(compiler).await?;
+    let binary_path = output.binary_path.ok_or_else(|| {
+        DomainError::CompilationFailed(format!(
+            "Compilation failed with exit code {}: {}",
+            output.exit_code, output.stderr
+        ))
+    })?;
+    log::debug!("Code compiled successfully, binary path: {:?}", binary_path);
+
+    Ok(CompiledProgram {
+        language: sources.language,
+        binary_path,
+        files: sources.files,
+        temp_dir: sources.temp_dir,
+    })
+}
+
+/// Write the submitted sources into `source_dir`.
+///
+/// Returns the entry point path and the files that must be copied into the box.
+async fn write_sources(
+    source: &ProgramSource,
+    language: &Language,
+    source_dir: &Path,
+) -> DomainResult<(PathBuf, Vec<SandboxFile>)> {
+    // Single file submission keeps the original layout
+    if source.files.is_empty() {
+        let source_file = source_dir.join(format!("source.{}", language.extension));
+        log::debug!(
+            "Writing source code to: {:?} ({} bytes)",
+            source_file,
+            source.code.len()
+        );
+        fs::write(&source_file, &source.code).await?;
+        return Ok((source_file, Vec::new()));
+    }
+
+    if !source.code.is_empty() {
+        return Err(DomainError::InvalidRequest(
+            "Provide either code or files, not both".to_string(),
+        ));
+    }
+
+    let mut files: Vec<SandboxFile> = Vec::with_capacity(source.files.len());
+    for file in &source.files {
+        let relative_path = normalize_relative_path(&file.path)?;
+
+        if is_reserved_box_path(&relative_path) {
+            return Err(DomainError::InvalidRequest(format!(
+                "Invalid path '{}': name is reserved",
+                file.path
+            )));
+        }
+
+        if files.iter().any(|f| f.box_path == relative_path) {
+            return Err(DomainError::InvalidRequest(format!(
+                "Duplicate file path '{}'",
+                file.path
+            )));
+        }
+
+        let host_path = source_dir.join(&relative_path);
+        if let Some(parent) = host_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        log::debug!(
+            "Writing source file to: {:?} ({} bytes)",
+            host_path,
+            file.content.len()
+        );
+        fs::write(&host_path, &file.content).await?;
+
+        files.push(SandboxFile {
+            host_path,
+            box_path: relative_path,
+        });
+    }
+
+    let entry_point = match &source.entry_point {
+        Some(entry_point) => normalize_relative_path(entry_point)?,
+        None if files.len() == 1 => files[0].box_path.clone(),
+        None => {
+            return Err(DomainError::InvalidRequest(
+                "entry_point is required when submitting multiple files".to_string(),
+            ))
+        }
+    };
+
+    if !files.iter().any(|f| f.box_path == entry_point) {
+        return Err(DomainError::InvalidRequest(format!(
+            "entry_point '{}' does not match any submitted file",
+            entry_point.display()
+        )));
+    }
+
+    Ok((source_dir.join(entry_point), files))
+}
+
+/// Validate program arguments and environment variables passed to the sandbox
+pub fn validate_args_and_env(args: &[String], env: &BTreeMap<String, String>) -> DomainResult<()> {
+    if args.iter().any(|arg| arg.contains('\0')) {
+        return Err(DomainError::InvalidRequest(
+            "args must not contain NUL characters".to_string(),
+        ));
+    }
+
+    for (name, value) in env {
+        let mut chars = name.chars();
+        let is_valid_name = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_valid_name {
+            return Err(DomainError::InvalidRequest(format!(
+                "Invalid environment variable name '{}'",
+                name
+            )));
+        }
+
+        if RESERVED_ENV_VARS.contains(&name.as_str()) {
+            return Err(DomainError::InvalidRequest(format!(
+                "Environment variable '{}' is reserved",
+                name
+            )));
+        }
+
+        if value.contains('\0') {
+            return Err(DomainError::InvalidRequest(format!(
+                "Environment variable '{}' must not contain NUL characters",
+                name
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Encode raw program output as text, returning the encoding actually used. `truncated`
+/// tells whether the output was cut off at the output limit.
+pub fn encode_output(
+    output: Vec<u8>,
+    encoding: OutputEncoding,
+    truncated: bool,
+) -> (String, OutputEncoding) {
+    let text = match encoding {
+        OutputEncoding::Utf8 => Some(String::from_utf8_lossy(&output).into_owned()),
+        OutputEncoding::Base64 => None,
+        OutputEncoding::Auto => match std::str::from_utf8(&output) {
+            Ok(text) => Some(text.to_string()),
+            // Truncation may cut the last character short, which does not make the output
+            // binary. Output the program ended that way itself is kept whole as base64.
+            Err(e) if truncated && e.error_len().is_none() => {
+                Some(String::from_utf8_lossy(&output[..e.valid_up_to()]).into_owned())
+            }
+            Err(_) => None,
+        },
+    };
+
+    match text {
+        Some(text) => (text, OutputEncoding::Utf8),
+        None => (
+            base64::engine::general_purpose::STANDARD.encode(output),
+            OutputEncoding::Base64,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use OutputEncoding::{Auto, Base64, Utf8};
+
+    #[test]
+    fn encode_output_picks_encoding() {
+        let cases: [(&[u8], OutputEncoding, &str, OutputEncoding); 6] = [
+            (b"hello", Utf8, "hello", Utf8),
+            (b"hello", Base64, "aGVsbG8=", Base64),
+            (b"hello", Auto, "hello", Utf8),
+            (b"a\xffb", Utf8, "a\u{fffd}b", Utf8),
+            (b"a\xffb", Auto, "Yf9i", Base64),
+            (b"", Auto, "", Utf8),
+        ];
+
+        for (output, requested, text, encoding) in cases {
+            assert_eq!(
+                encode_output(output.to_vec(), requested, false),
+                (text.to_string(), encoding),
+                "{:?} as {:?}",
+                output,
+                requested
+            );
+        }
+    }
+
+    #[test]
+    fn encode_output_trims_only_truncated_output() {
+        // Ends in the middle of "€" (0xE2 0x82 0xAC)
+        let output = b"price: 5\xe2\x82";
+
+        // Cut off at the output limit
+        let (text, encoding) = encode_output(output.to_vec(), Auto, true);
+        assert_eq!((text.as_str(), encoding), ("price: 5", Utf8));
+        let (text, encoding) = encode_output(output.to_vec(), Utf8, true);
+        assert_eq!((text.as_str(), encoding), ("price: 5\u{fffd}", Utf8));
+
+        // Written that way by the program
+        let (text, encoding) = encode_output(output.to_vec(), Auto, false);
+        assert_eq!((text.as_str(), encoding), ("cHJpY2U6IDXigg==", Base64));
+    }
+
+    fn env(vars: &[(&str, &str)]) -> BTreeMap<String, String> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn validate_args_and_env_accepts_plain_values() {
+        let args = vec!["--flag".to_string(), "".to_string(), "a b=c".to_string()];
+        let vars = env(&[("PATH", "/usr/bin"), ("_private", ""), ("MODE_2", "x=y")]);
+
+        assert!(validate_args_and_env(&args, &vars).is_ok());
+    }
+
+    #[test]
+    fn validate_args_and_env_rejects_nul_in_args() {
+        let args = vec!["ok".to_string(), "a\0b".to_string()];
+
+        let result = validate_args_and_env(&args, &BTreeMap::new());
+        assert!(matches!(result, Err(DomainError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn validate_args_and_env_rejects_unsafe_env() {
+        let cases = [
+            ("HOME", "/tmp"),
+            ("PYTHONPATH", "/tmp"),
+            ("LIBC_FATAL_STDERR_", "0"),
+            ("", "value"),
+            ("A=B", "value"),
+            ("A\0B", "value"),
+            ("1ST", "value"),
+            ("MY-VAR", "value"),
+            ("VALUE", "a\0b"),
+        ];
+
+        for (name, value) in cases {
+            let result = validate_args_and_env(&[], &env(&[(name, value)]));
+            assert!(
+                matches!(result, Err(DomainError::InvalidRequest(_))),
+                "{:?}={:?}",
+                name,
+                value
+            );
+        }
+    }
+}
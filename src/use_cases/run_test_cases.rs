@@ -0,0 +1,718 @@
+use crate::domain::entities::{
+    ProgramSource, ResourceLimitOverrides, ResourceLimits, TestCase, TestCaseBatch, TestCaseResult,
+    Verdict,
+};
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::repositories::LanguageRepository;
+use crate::domain::services::{
+    CompilerService, SandboxExecutionConfig, SandboxExecutionResult, SandboxFile, SandboxService,
+};
+use crate::use_cases::cancellation::cancellable;
+use crate::use_cases::program::{
+    compile_program, encode_output, validate_args_and_env, CompiledProgram,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::fs;
+use tokio_util::sync::CancellationToken;
+
+/// A compiled checker or interactor and the box it runs in
+struct JudgeProgram {
+    program: CompiledProgram,
+    box_id: u32,
+    limits: ResourceLimits,
+}
+
+/// Verdict, score and feedback reported by a checker or interactor
+struct JudgeOutcome {
+    verdict: Verdict,
+    score: f64,
+    message: String,
+}
+
+pub struct RunTestCasesUseCase {
+    language_repo: Arc<dyn LanguageRepository>,
+    compiler: Arc<dyn CompilerService>,
+    sandbox: Arc<dyn SandboxService>,
+}
+
+impl RunTestCasesUseCase {
+    pub fn new(
+        language_repo: Arc<dyn LanguageRepository>,
+        compiler: Arc<dyn CompilerService>,
+        sandbox: Arc<dyn SandboxService>,
+    ) -> Self {
+        Self {
+            language_repo,
+            compiler,
+            sandbox,
+        }
+    }
+
+    /// Run every test case of the batch, stopping wherever it got to when `cancel` is
+    /// triggered
+    pub async fn execute(
+        &self,
+        batch: TestCaseBatch,
+        cancel: &CancellationToken,
+    ) -> DomainResult<Vec<TestCaseResult>> {
+        if batch.test_cases.is_empty() {
+            return Err(DomainError::InvalidRequest(
+                "test_cases must not be empty".to_string(),
+            ));
+        }
+
+        if batch.checker.is_some() && batch.interactor.is_some() {
+            return Err(DomainError::InvalidRequest(
+                "checker and interactor cannot be used together".to_string(),
+            ));
+        }
+
+        // Validate every test case before compiling so a bad case fails the whole batch early
+        let mut limits = Vec::with_capacity(batch.test_cases.len());
+        for test_case in &batch.test_cases {
+            limits.push(self.sandbox.resolve_limits(&test_case.limits)?);
+            validate_args_and_env(&test_case.args, &batch.env)?;
+        }
+
+        let (judge_source, judge_limits) = match (&batch.checker, &batch.interactor) {
+            (Some(checker), _) => (
+                Some(("Checker", checker)),
+                ResourceLimitOverrides::default(),
+            ),
+            (_, Some(interactor)) => (
+                Some(("Interactor", interactor)),
+                batch.interactor_limits.clone(),
+            ),
+            _ => (None, ResourceLimitOverrides::default()),
+        };
+        let judge_limits = self.sandbox.resolve_limits(&judge_limits)?;
+
+        // Compile once for all test cases
+        let program = cancellable(
+            cancel,
+            compile_program(
+                self.language_repo.as_ref(),
+                self.compiler.as_ref(),
+                &batch.program,
+            ),
+        )
+        .await?;
+
+        let judge_program = match judge_source {
+            Some((role, source)) => {
+                Some(cancellable(cancel, self.compile_judge(role, source)).await?)
+            }
+            None => None,
+        };
+
+        // The program and judge boxes are acquired together, so concurrent batches never
+        // hold one box each while waiting for a second
+        let box_count = if judge_program.is_some() { 2 } else { 1 };
+        log::debug!("Acquiring {} box ID(s) from pool", box_count);
+        let box_ids = cancellable(
+            cancel,
+            self.sandbox.acquire_box_ids(&batch.scheduling, box_count),
+        )
+        .await?;
+        let boxes = BatchBoxes {
+            sandbox: self.sandbox.clone(),
+            box_ids: box_ids.clone(),
+        };
+        let box_id = box_ids[0];
+        log::debug!(
+            "Acquired box IDs: {:?} for {} test case(s)",
+            box_ids,
+            batch.test_cases.len()
+        );
+
+        let judge = judge_program.map(|program| JudgeProgram {
+            program,
+            box_id: box_ids[1],
+            limits: judge_limits,
+        });
+
+        let results = self
+            .run_all(&batch, &program, &limits, box_id, judge.as_ref(), cancel)
+            .await;
+
+        let released = boxes.release().await;
+
+        let results = results?;
+        released?;
+        Ok(results)
+    }
+
+    /// Compile a checker or interactor, reporting its compile errors as a bad request
+    async fn compile_judge(
+        &self,
+        role: &str,
+        source: &ProgramSource,
+    ) -> DomainResult<CompiledProgram> {
+        compile_program(self.language_repo.as_ref(), self.compiler.as_ref(), source)
+            .await
+            .map_err(|e| match e {
+                DomainError::CompilationFailed(msg) => {
+                    DomainError::InvalidRequest(format!("{} compilation failed: {}", role, msg))
+                }
+                other => other,
+            })
+    }
+
+    async fn run_all(
+        &self,
+        batch: &TestCaseBatch,
+        program: &CompiledProgram,
+        limits: &[ResourceLimits],
+        box_id: u32,
+        judge: Option<&JudgeProgram>,
+        cancel: &CancellationToken,
+    ) -> DomainResult<Vec<TestCaseResult>> {
+        let mut results = Vec::with_capacity(batch.test_cases.len());
+
+        for (index, (test_case, limits)) in batch.test_cases.iter().zip(limits).enumerate() {
+            // Reset the boxes so files written by one test case are not visible to the next
+            if index > 0 {
+                self.sandbox.cleanup(box_id).await?;
+                if let Some(judge) = judge {
+                    self.sandbox.cleanup(judge.box_id).await?;
+                }
+            }
+
+            let config = SandboxExecutionConfig {
+                args: test_case.args.clone(),
+                env: batch.env.clone(),
+                cancel: cancel.clone(),
+                ..program.sandbox_config(box_id, *limits)
+            };
+
+            let (sandbox_result, outcome) = match (judge, batch.interactor.is_some()) {
+                (Some(interactor), true) => {
+                    self.run_interactive(config, interactor, index, test_case)
+                        .await?
+                }
+                (checker, _) => {
+                    let stdin_path = match &test_case.stdin {
+                        Some(stdin) => Some(
+                            program
+                                .write_stdin(&format!("stdin-{}", index), stdin)
+                                .await?,
+                        ),
+                        None => None,
+                    };
+
+                    log::debug!("Running test case {} in box ID: {}", index, box_id);
+                    let sandbox_result = self
+                        .sandbox
+                        .execute(SandboxExecutionConfig {
+                            stdin_path,
+                            ..config
+                        })
+                        .await?;
+
+                    // Only judge output of runs that completed normally and was kept in full
+                    let outcome = match checker {
+                        Some(checker)
+                            if sandbox_result.metadata.is_success()
+                                && !sandbox_result.stdout_size.truncated =>
+                        {
+                            Some(
+                                self.run_checker(
+                                    checker,
+                                    index,
+                                    test_case,
+                                    &sandbox_result.stdout,
+                                    cancel,
+                                )
+                                .await?,
+                            )
+                        }
+                        _ => None,
+                    };
+                    (sandbox_result, outcome)
+                }
+            };
+
+            // Compare the raw output, before it is decoded for the response
+            let expected_verdict = match &test_case.expected_output {
+                Some(expected) if judge.is_none() => Some(
+                    test_case
+                        .comparison
+                        .verdict(expected, &sandbox_result.stdout),
+                ),
+                _ => None,
+            };
+
+            let (stdout, stdout_encoding) = encode_output(
+                sandbox_result.stdout,
+                batch.output_encoding,
+                sandbox_result.stdout_size.truncated,
+            );
+            let (stderr, stderr_encoding) = encode_output(
+                sandbox_result.stderr,
+                batch.output_encoding,
+                sandbox_result.stderr_size.truncated,
+            );
+            let mut result = TestCaseResult {
+                stdout,
+                stderr,
+                stdout_encoding,
+                stderr_encoding,
+                stdout_size: sandbox_result.stdout_size,
+                stderr_size: sandbox_result.stderr_size,
+                metadata: sandbox_result.metadata,
+                verdict: None,
+                score: None,
+                checker_message: None,
+            };
+
+            match outcome {
+                Some(outcome) => {
+                    result.verdict = Some(outcome.verdict);
+                    result.score = Some(outcome.score);
+                    result.checker_message = Some(outcome.message);
+                }
+                // Output cut off at the limit is never judged, as only part of it was kept
+                None if result.metadata.is_success()
+                    && result.stdout_size.truncated
+                    && (judge.is_some() || expected_verdict.is_some()) =>
+                {
+                    result.verdict = Some(Verdict::OutputLimitExceeded);
+                    result.score = Some(Verdict::OutputLimitExceeded.default_score());
+                }
+                None if result.metadata.is_success() => {
+                    if let Some(verdict) = expected_verdict {
+                        result.score = Some(verdict.default_score());
+                        result.verdict = Some(verdict);
+                    }
+                }
+                None => {}
+            }
+
+            let failed = result.is_failure();
+            results.push(result);
+
+            if failed && batch.stop_on_failure {
+                log::debug!("Test case {} failed, skipping remaining test cases", index);
+                break;
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Write judge input files for a test case into the judge working directory
+    async fn judge_files(
+        &self,
+        judge: &JudgeProgram,
+        index: usize,
+        contents: &[(&str, &[u8])],
+    ) -> DomainResult<Vec<SandboxFile>> {
+        let mut files = judge.program.files.clone();
+        for (name, content) in contents {
+            let host_path = judge.program.work_dir().join(format!("{}-{}", name, index));
+            fs::write(&host_path, content).await?;
+            files.push(SandboxFile {
+                host_path,
+                box_path: PathBuf::from(name),
+            });
+        }
+        Ok(files)
+    }
+
+    /// Run the checker with the test input, the contestant output and the reference answer.
+    ///
+    /// The checker is invoked as `checker input output answer`; its exit code decides the
+    /// verdict, an optional score may be printed as the first token on stdout and any
+    /// feedback is taken from stderr.
+    async fn run_checker(
+        &self,
+        checker: &JudgeProgram,
+        index: usize,
+        test_case: &TestCase,
+        output: &[u8],
+        cancel: &CancellationToken,
+    ) -> DomainResult<JudgeOutcome> {
+        let input = test_case.stdin.as_deref().unwrap_or_default();
+        let answer = test_case.expected_output.as_deref().unwrap_or_default();
+
+        let files = self
+            .judge_files(
+                checker,
+                index,
+                &[
+                    ("input", input),
+                    ("output", output),
+                    ("answer", answer.as_bytes()),
+                ],
+            )
+            .await?;
+
+        let config = SandboxExecutionConfig {
+            files,
+            args: vec![
+                "input".to_string(),
+                "output".to_string(),
+                "answer".to_string(),
+            ],
+            cancel: cancel.clone(),
+            ..checker
+                .program
+                .sandbox_config(checker.box_id, checker.limits)
+        };
+
+        log::debug!(
+            "Running checker for test case {} in box ID: {}",
+            index,
+            checker.box_id
+        );
+        let checker_result = self.sandbox.execute(config).await?;
+
+        let verdict = judge_verdict(&checker_result);
+        Ok(JudgeOutcome {
+            verdict,
+            score: checker_score(&checker_result.stdout, verdict),
+            message: String::from_utf8_lossy(&checker_result.stderr).into_owned(),
+        })
+    }
+
+    /// Run the program against the interactor with their standard streams connected.
+    ///
+    /// The interactor is invoked as `interactor input answer`. Both runs are cancelled with
+    /// the program's token.
+    async fn run_interactive(
+        &self,
+        config: SandboxExecutionConfig,
+        interactor: &JudgeProgram,
+        index: usize,
+        test_case: &TestCase,
+    ) -> DomainResult<(SandboxExecutionResult, Option<JudgeOutcome>)> {
+        let input = test_case.stdin.as_deref().unwrap_or_default();
+        let answer = test_case.expected_output.as_deref().unwrap_or_default();
+
+        let files = self
+            .judge_files(
+                interactor,
+                index,
+                &[("input", input), ("answer", answer.as_bytes())],
+            )
+            .await?;
+
+        let interactor_config = SandboxExecutionConfig {
+            files,
+            args: vec!["input".to_string(), "answer".to_string()],
+            ..interactor
+                .program
+                .sandbox_config(interactor.box_id, interactor.limits)
+        };
+
+        log::debug!(
+            "Running interactive test case {} in box IDs: {}, {}",
+            index,
+            config.box_id,
+            interactor.box_id
+        );
+        let result = self
+            .sandbox
+            .execute_interactive(config, interactor_config)
+            .await?;
+
+        let outcome = interactor_outcome(&result.program, &result.interactor);
+        Ok((result.program, outcome))
+    }
+}
+
+/// Verdict of an interactive run.
+///
+/// The interactor's exit code decides, unless the program itself crashed or exceeded its
+/// limits. A wrong answer always stands: an interactor rejecting the program may close the
+/// pipe first, so that the program dies of SIGPIPE.
+fn interactor_outcome(
+    program: &SandboxExecutionResult,
+    interactor: &SandboxExecutionResult,
+) -> Option<JudgeOutcome> {
+    let verdict = judge_verdict(interactor);
+    if verdict != Verdict::WrongAnswer && !program.metadata.is_success() {
+        return None;
+    }
+
+    Some(JudgeOutcome {
+        verdict,
+        score: verdict.default_score(),
+        message: String::from_utf8_lossy(&interactor.stderr).into_owned(),
+    })
+}
+
+/// Score printed by a checker as the first token on stdout, or the one implied by the
+/// verdict when there is none
+fn checker_score(stdout: &[u8], verdict: Verdict) -> f64 {
+    String::from_utf8_lossy(stdout)
+        .split_whitespace()
+        .next()
+        .and_then(|token| token.parse::<f64>().ok())
+        .filter(|score| score.is_finite())
+        .unwrap_or_else(|| verdict.default_score())
+}
+
+/// Boxes taken by a batch.
+///
+/// They are only used internally, so they are always cleaned up and returned to the pool,
+/// also when the batch is dropped midway, e.g. because its client disconnected.
+struct BatchBoxes {
+    sandbox: Arc<dyn SandboxService>,
+    box_ids: Vec<u32>,
+}
+
+impl BatchBoxes {
+    async fn release(mut self) -> DomainResult<()> {
+        release_boxes(self.sandbox.as_ref(), std::mem::take(&mut self.box_ids)).await
+    }
+}
+
+impl Drop for BatchBoxes {
+    fn drop(&mut self) {
+        if self.box_ids.is_empty() {
+            return;
+        }
+
+        let sandbox = self.sandbox.clone();
+        let box_ids = std::mem::take(&mut self.box_ids);
+        log::debug!("Batch dropped, releasing box IDs: {:?}", box_ids);
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(async move {
+                    if let Err(e) = release_boxes(sandbox.as_ref(), box_ids).await {
+                        log::warn!("Failed to release boxes of a dropped batch: {}", e);
+                    }
+                });
+            }
+            Err(_) => log::warn!("No runtime to release box IDs: {:?}", box_ids),
+        }
+    }
+}
+
+/// Clean up boxes and return them to the pool, even when cleaning them up fails
+async fn release_boxes(sandbox: &dyn SandboxService, box_ids: Vec<u32>) -> DomainResult<()> {
+    let mut released = Ok(());
+    for box_id in box_ids {
+        let cleaned = sandbox.cleanup(box_id).await;
+        if let Err(e) = &cleaned {
+            log::warn!("Failed to clean up box {}: {}", box_id, e);
+        }
+        released = released
+            .and(cleaned)
+            .and(sandbox.release_box_id(box_id).await);
+    }
+    released
+}
+
+/// Verdict from a checker or interactor run.
+///
+/// Crashes and limit violations of the judge program are judge errors, not wrong answers.
+fn judge_verdict(result: &SandboxExecutionResult) -> Verdict {
+    let metadata = &result.metadata;
+    if metadata.status == "OK" || metadata.status == "RE" {
+        Verdict::from_checker_exit_code(metadata.exit_code)
+    } else {
+        Verdict::JudgeError
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{
+        ExecutionMetadata, OutputComparison, OutputEncoding, SchedulingContext,
+    };
+    use crate::use_cases::testing::{FakeCompiler, FakeLanguages, FakeSandbox};
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    fn source(code: &str) -> ProgramSource {
+        ProgramSource {
+            language: "python".to_string(),
+            code: code.to_string(),
+            files: Vec::new(),
+            entry_point: None,
+        }
+    }
+
+    fn batch(checker: Option<ProgramSource>) -> TestCaseBatch {
+        TestCaseBatch {
+            program: source("print(input())"),
+            env: BTreeMap::new(),
+            test_cases: vec![TestCase {
+                stdin: Some(b"1".to_vec()),
+                args: Vec::new(),
+                limits: ResourceLimitOverrides::default(),
+                expected_output: Some("1".to_string()),
+                comparison: OutputComparison::default(),
+            }],
+            stop_on_failure: false,
+            checker,
+            interactor: None,
+            interactor_limits: ResourceLimitOverrides::default(),
+            scheduling: SchedulingContext::default(),
+            output_encoding: OutputEncoding::Auto,
+        }
+    }
+
+    fn judge_result(status: &str, exit_code: i32) -> SandboxExecutionResult {
+        SandboxExecutionResult {
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            stdout_size: Default::default(),
+            stderr_size: Default::default(),
+            metadata: ExecutionMetadata {
+                status: status.to_string(),
+                exit_code,
+                ..Default::default()
+            },
+            box_id: 0,
+        }
+    }
+
+    #[test]
+    fn judge_verdict_from_exit_code_and_status() {
+        let cases = [
+            ("OK", 0, Verdict::Accepted),
+            ("RE", 1, Verdict::WrongAnswer),
+            // Presentation errors and other testlib codes are not told apart from failures
+            ("RE", 2, Verdict::JudgeError),
+            ("RE", 3, Verdict::JudgeError),
+            ("SG", 0, Verdict::JudgeError),
+            ("TO", 0, Verdict::JudgeError),
+            ("XX", 0, Verdict::JudgeError),
+        ];
+
+        for (status, exit_code, verdict) in cases {
+            let result = judge_result(status, exit_code);
+            assert_eq!(judge_verdict(&result), verdict, "{} {}", status, exit_code);
+        }
+    }
+
+    #[test]
+    fn checker_score_parses_first_token() {
+        let cases: [(&[u8], Verdict, f64); 10] = [
+            (b"", Verdict::Accepted, 1.0),
+            (b"", Verdict::WrongAnswer, 0.0),
+            (b"0.5", Verdict::Accepted, 0.5),
+            (b"  0.25 points\n", Verdict::WrongAnswer, 0.25),
+            (b"1e-1", Verdict::Accepted, 0.1),
+            // Malformed scores fall back to the verdict
+            (b"ok 0.5", Verdict::Accepted, 1.0),
+            (b"0,5", Verdict::WrongAnswer, 0.0),
+            (b"NaN", Verdict::Accepted, 1.0),
+            (b"inf", Verdict::WrongAnswer, 0.0),
+            (b"\xff\xfe", Verdict::JudgeError, 0.0),
+        ];
+
+        for (stdout, verdict, score) in cases {
+            assert_eq!(checker_score(stdout, verdict), score, "{:?}", stdout);
+        }
+    }
+
+    #[test]
+    fn interactor_wrong_answer_beats_program_failure() {
+        // (program status and exit code, interactor status and exit code, verdict)
+        let cases = [
+            (("OK", 0), ("OK", 0), Some(Verdict::Accepted)),
+            (("OK", 0), ("RE", 1), Some(Verdict::WrongAnswer)),
+            // The interactor closed the pipe after rejecting the program
+            (("SG", 0), ("RE", 1), Some(Verdict::WrongAnswer)),
+            (("RE", 1), ("RE", 1), Some(Verdict::WrongAnswer)),
+            // Otherwise the program's own failure is reported
+            (("TO", 0), ("OK", 0), None),
+            (("RE", 3), ("SG", 0), None),
+            (("OK", 0), ("SG", 0), Some(Verdict::JudgeError)),
+        ];
+
+        for (program, interactor, verdict) in cases {
+            let outcome = interactor_outcome(
+                &judge_result(program.0, program.1),
+                &judge_result(interactor.0, interactor.1),
+            );
+            let outcome = outcome.map(|outcome| outcome.verdict);
+            assert_eq!(outcome, verdict, "{:?} {:?}", program, interactor);
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_batch_returns_its_boxes() {
+        let mut sandbox = FakeSandbox::new(1);
+        sandbox.hang = true;
+        let sandbox = Arc::new(sandbox);
+        let use_case = RunTestCasesUseCase::new(
+            Arc::new(FakeLanguages),
+            Arc::new(FakeCompiler),
+            sandbox.clone(),
+        );
+
+        let cancel = CancellationToken::new();
+        let (result, _) = tokio::join!(use_case.execute(batch(None), &cancel), async {
+            sandbox.started.notified().await;
+            cancel.cancel();
+        });
+        assert!(matches!(result, Err(DomainError::Cancelled)));
+
+        let scheduling = SchedulingContext::default();
+        assert_eq!(sandbox.acquire_box_id(&scheduling).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn dropped_batch_returns_its_boxes() {
+        let mut sandbox = FakeSandbox::new(2);
+        sandbox.hang = true;
+        let sandbox = Arc::new(sandbox);
+        let use_case = Arc::new(RunTestCasesUseCase::new(
+            Arc::new(FakeLanguages),
+            Arc::new(FakeCompiler),
+            sandbox.clone(),
+        ));
+
+        let task = tokio::spawn({
+            let use_case = use_case.clone();
+            async move {
+                let batch = batch(Some(source("exit(0)")));
+                use_case.execute(batch, &CancellationToken::new()).await
+            }
+        });
+        sandbox.started.notified().await;
+        task.abort();
+        assert!(task.await.unwrap_err().is_cancelled());
+
+        // Both boxes are cleaned up and free again
+        let scheduling = SchedulingContext::default();
+        let box_ids = tokio::time::timeout(
+            Duration::from_secs(1),
+            sandbox.acquire_box_ids(&scheduling, 2),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(box_ids.len(), 2);
+        let mut cleaned = sandbox.cleaned.lock().unwrap().clone();
+        cleaned.sort();
+        assert_eq!(cleaned, vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn finished_batch_returns_its_boxes() {
+        let mut sandbox = FakeSandbox::new(1);
+        sandbox.stdout = b"1".to_vec();
+        let sandbox = Arc::new(sandbox);
+        let use_case = RunTestCasesUseCase::new(
+            Arc::new(FakeLanguages),
+            Arc::new(FakeCompiler),
+            sandbox.clone(),
+        );
+
+        let cancel = CancellationToken::new();
+        let results = use_case.execute(batch(None), &cancel).await.unwrap();
+        assert_eq!(results[0].verdict, Some(Verdict::Accepted));
+
+        let scheduling = SchedulingContext::default();
+        assert_eq!(sandbox.acquire_box_id(&scheduling).await.unwrap(), 0);
+        assert_eq!(*sandbox.cleaned.lock().unwrap(), vec![0]);
+    }
+}
@@ -0,0 +1,296 @@
+use crate::domain::entities::SchedulingContext;
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::services::SandboxService;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OwnedMutexGuard;
+
+/// How often idle sessions are looked for
+const REAP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Boxes allocated to clients for running programs one after another, keeping the files
+/// left by earlier runs
+pub struct SessionRegistry {
+    sandbox: Arc<dyn SandboxService>,
+    /// Unused sessions are ended after this long (zero = never)
+    idle_timeout: Duration,
+    /// Sessions a tenant may have open at once (zero = unlimited)
+    max_sessions: usize,
+    sessions: Mutex<HashMap<u32, Arc<Session>>>,
+    /// Sessions of each tenant still waiting for their box
+    opening: Mutex<HashMap<String, usize>>,
+}
+
+struct Session {
+    tenant: String,
+    last_used: Mutex<Instant>,
+    /// Held while a program runs, so runs in a session never overlap
+    run_lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Session {
+    fn touch(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+}
+
+impl SessionRegistry {
+    pub fn new(
+        sandbox: Arc<dyn SandboxService>,
+        idle_timeout: Duration,
+        max_sessions: usize,
+    ) -> Self {
+        Self {
+            sandbox,
+            idle_timeout,
+            max_sessions,
+            sessions: Mutex::new(HashMap::new()),
+            opening: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Acquire and initialize a box for a new session, returning its ID.
+    ///
+    /// Fails with `TooManySessions` when the tenant already has as many sessions open as
+    /// allowed, so one tenant cannot hold every box.
+    pub async fn open(&self, scheduling: &SchedulingContext) -> DomainResult<u32> {
+        let _slot = self.reserve(&scheduling.tenant)?;
+        let box_id = self.sandbox.acquire_box_id(scheduling).await?;
+        if let Err(e) = self.sandbox.init(box_id).await {
+            self.sandbox.release_box_id(box_id).await?;
+            return Err(e);
+        }
+
+        log::debug!(
+            "Opened session in box ID: {} for {}",
+            box_id,
+            scheduling.tenant
+        );
+        self.sessions.lock().unwrap().insert(
+            box_id,
+            Arc::new(Session {
+                tenant: scheduling.tenant.clone(),
+                last_used: Mutex::new(Instant::now()),
+                run_lock: Arc::new(tokio::sync::Mutex::new(())),
+            }),
+        );
+        Ok(box_id)
+    }
+
+    /// Count a session being opened against the limit of `tenant` while it waits for a box
+    fn reserve(&self, tenant: &str) -> DomainResult<OpeningSlot<'_>> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut opening = self.opening.lock().unwrap();
+        if self.max_sessions > 0 {
+            let open = sessions
+                .values()
+                .filter(|session| session.tenant == tenant)
+                .count();
+            let pending = opening.get(tenant).copied().unwrap_or(0);
+            if open + pending >= self.max_sessions {
+                return Err(DomainError::TooManySessions(self.max_sessions));
+            }
+        }
+
+        *opening.entry(tenant.to_string()).or_default() += 1;
+        Ok(OpeningSlot {
+            opening: &self.opening,
+            tenant: tenant.to_string(),
+        })
+    }
+
+    /// Take exclusive use of a session of `tenant`, waiting for a run in progress to finish.
+    ///
+    /// The session is not ended for being idle until the returned guard is dropped.
+    pub async fn enter(&self, box_id: u32, tenant: &str) -> DomainResult<SessionGuard> {
+        let session = self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(&box_id)
+            .filter(|session| session.tenant == tenant)
+            .cloned()
+            .ok_or(DomainError::SessionNotFound(box_id))?;
+
+        let run = session.run_lock.clone().lock_owned().await;
+
+        // The session may have ended while waiting for the previous run
+        let is_open = self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(&box_id)
+            .is_some_and(|current| Arc::ptr_eq(current, &session));
+        if !is_open {
+            return Err(DomainError::SessionNotFound(box_id));
+        }
+
+        session.touch();
+        Ok(SessionGuard { session, _run: run })
+    }
+
+    /// End the session in `box_id`, if any, leaving the box to the caller.
+    ///
+    /// Only `tenant` may end its own sessions; the session of another tenant is not found.
+    pub fn close(&self, box_id: u32, tenant: &str) -> DomainResult<bool> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get(&box_id) {
+            None => Ok(false),
+            Some(session) if session.tenant == tenant => {
+                sessions.remove(&box_id);
+                Ok(true)
+            }
+            Some(_) => Err(DomainError::SessionNotFound(box_id)),
+        }
+    }
+
+    /// End the session of `tenant` in `box_id` after its box was reset, e.g. by a cancelled
+    /// run, and release the box
+    pub async fn discard(&self, box_id: u32, tenant: &str) -> DomainResult<()> {
+        if self.close(box_id, tenant)? {
+            log::debug!("Discarded session in box ID: {}", box_id);
+            self.sandbox.release_box_id(box_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Clean up and release the boxes of sessions that have been idle too long
+    pub async fn expire_idle(&self) {
+        let expired: Vec<u32> = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let idle_timeout = self.idle_timeout;
+            let expired: Vec<u32> = sessions
+                .iter()
+                .filter(|(_, session)| {
+                    session.last_used.lock().unwrap().elapsed() > idle_timeout
+                        && session.run_lock.try_lock().is_ok()
+                })
+                .map(|(box_id, _)| *box_id)
+                .collect();
+            for box_id in &expired {
+                sessions.remove(box_id);
+            }
+            expired
+        };
+
+        for box_id in expired {
+            log::debug!("Session in box ID: {} expired, releasing the box", box_id);
+            if let Err(e) = self.sandbox.cleanup(box_id).await {
+                log::warn!("Failed to clean up expired session box {}: {}", box_id, e);
+            }
+            if let Err(e) = self.sandbox.release_box_id(box_id).await {
+                log::warn!("Failed to release expired session box {}: {}", box_id, e);
+            }
+        }
+    }
+
+    /// Periodically end idle sessions in the background, unless sessions never expire
+    pub fn spawn_reaper(self: &Arc<Self>) {
+        if self.idle_timeout.is_zero() {
+            return;
+        }
+
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(REAP_INTERVAL);
+            loop {
+                interval.tick().await;
+                registry.expire_idle().await;
+            }
+        });
+    }
+}
+
+/// A session being opened, no longer counted once dropped
+struct OpeningSlot<'a> {
+    opening: &'a Mutex<HashMap<String, usize>>,
+    tenant: String,
+}
+
+impl Drop for OpeningSlot<'_> {
+    fn drop(&mut self) {
+        let mut opening = self.opening.lock().unwrap();
+        if let Some(pending) = opening.get_mut(&self.tenant) {
+            *pending -= 1;
+            if *pending == 0 {
+                opening.remove(&self.tenant);
+            }
+        }
+    }
+}
+
+/// Exclusive use of a session; dropping it marks the session as last used now
+pub struct SessionGuard {
+    session: Arc<Session>,
+    _run: OwnedMutexGuard<()>,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.session.touch();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::use_cases::testing::FakeSandbox;
+
+    fn context(tenant: &str) -> SchedulingContext {
+        SchedulingContext {
+            tenant: tenant.to_string(),
+            ..SchedulingContext::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn tenant_sessions_are_capped() {
+        let sessions = SessionRegistry::new(Arc::new(FakeSandbox::new(4)), Duration::ZERO, 2);
+
+        let first = sessions.open(&context("a")).await.unwrap();
+        sessions.open(&context("a")).await.unwrap();
+        let result = sessions.open(&context("a")).await;
+        assert!(matches!(result, Err(DomainError::TooManySessions(2))));
+
+        // Other tenants have their own limit
+        sessions.open(&context("b")).await.unwrap();
+
+        // Ending a session makes room for another
+        assert!(sessions.close(first, "a").unwrap());
+        sessions.open(&context("a")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn sessions_waiting_for_a_box_count_against_the_cap() {
+        let sandbox = Arc::new(FakeSandbox::new(1));
+        let sessions = Arc::new(SessionRegistry::new(sandbox.clone(), Duration::ZERO, 1));
+        let held = sandbox.pool.acquire(&context("b")).await.unwrap();
+
+        let waiting = {
+            let sessions = sessions.clone();
+            tokio::spawn(async move { sessions.open(&context("a")).await })
+        };
+        while sessions.opening.lock().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        let result = sessions.open(&context("a")).await;
+        assert!(matches!(result, Err(DomainError::TooManySessions(1))));
+
+        sandbox.pool.release(held).await.unwrap();
+        assert_eq!(waiting.await.unwrap().unwrap(), held);
+        assert!(sessions.opening.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn zero_allows_any_number_of_sessions() {
+        let sessions = SessionRegistry::new(Arc::new(FakeSandbox::new(3)), Duration::ZERO, 0);
+        for _ in 0..3 {
+            sessions.open(&context("a")).await.unwrap();
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use crate::domain::entities::{ExecutionRequest, ExecutionResult, Job, JobStatus};
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::repositories::JobRepository;
+use crate::domain::services::SandboxService;
+use crate::use_cases::cancellation::{cancellable, CancellationRegistry, Registration};
+use crate::use_cases::{ExecuteCodeUseCase, WebhookNotifier};
+use chrono::{TimeDelta, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+pub struct SubmitJobUseCase {
+    execute_code: Arc<ExecuteCodeUseCase>,
+    sandbox: Arc<dyn SandboxService>,
+    jobs: Arc<dyn JobRepository>,
+    cancellations: Arc<CancellationRegistry>,
+    webhooks: Arc<WebhookNotifier>,
+    retention: Duration,
+}
+
+impl SubmitJobUseCase {
+    pub fn new(
+        execute_code: Arc<ExecuteCodeUseCase>,
+        sandbox: Arc<dyn SandboxService>,
+        jobs: Arc<dyn JobRepository>,
+        cancellations: Arc<CancellationRegistry>,
+        webhooks: Arc<WebhookNotifier>,
+        retention: Duration,
+    ) -> Self {
+        Self {
+            execute_code,
+            sandbox,
+            jobs,
+            cancellations,
+            webhooks,
+            retention,
+        }
+    }
+
+    /// Queue an execution and return immediately with the new job.
+    ///
+    /// When `callback_url` is set, the final state of the job is POSTed to it.
+    pub async fn execute(
+        &self,
+        request: ExecutionRequest,
+        callback_url: Option<String>,
+    ) -> DomainResult<Job> {
+        // Reject obviously invalid requests synchronously instead of as a failed job
+        self.execute_code.validate(&request)?;
+
+        // Forget jobs whose results have been kept long enough
+        let retention = TimeDelta::from_std(self.retention).unwrap_or(TimeDelta::MAX);
+        let cutoff = Utc::now() - retention;
+        let removed = self.jobs.delete_finished_before(cutoff).await?;
+        if removed > 0 {
+            log::debug!("Removed {} expired job(s)", removed);
+        }
+
+        let mut job = Job::new(
+            Uuid::new_v4().to_string(),
+            request.scheduling.tenant.clone(),
+        );
+        if let Some(callback_url) = callback_url {
+            let delivery = self
+                .webhooks
+                .register(&callback_url, &job.tenant, Some(job.id.clone()))
+                .await?;
+            job.webhook_delivery_id = Some(delivery.id);
+        }
+        let registration = self.cancellations.register(&job.tenant, &job.id)?;
+        self.jobs.insert(job.clone()).await?;
+        log::debug!("Queued job {}", job.id);
+
+        let runner = JobRunner {
+            execute_code: self.execute_code.clone(),
+            sandbox: self.sandbox.clone(),
+            jobs: self.jobs.clone(),
+            webhooks: self.webhooks.clone(),
+        };
+        let task_job = job.clone();
+        tokio::spawn(async move {
+            let job_id = task_job.id.clone();
+            if let Err(e) = runner.run(task_job, request, registration).await {
+                log::warn!("Failed to record progress of job {}: {}", job_id, e);
+            }
+        });
+
+        Ok(job)
+    }
+}
+
+/// Runs a job in the background, recording its progress in the repository
+struct JobRunner {
+    execute_code: Arc<ExecuteCodeUseCase>,
+    sandbox: Arc<dyn SandboxService>,
+    jobs: Arc<dyn JobRepository>,
+    webhooks: Arc<WebhookNotifier>,
+}
+
+impl JobRunner {
+    async fn run(
+        &self,
+        mut job: Job,
+        request: ExecutionRequest,
+        registration: Registration,
+    ) -> DomainResult<()> {
+        let outcome = self.execute(&mut job, request, &registration).await;
+
+        // Deregister before recording the outcome so the job can no longer be cancelled
+        // between completing and sending its webhook
+        drop(registration);
+
+        match outcome {
+            // Cancelling the job recorded its status and sent the webhook already
+            Err(DomainError::Cancelled) => {
+                log::debug!("Job {} was cancelled", job.id);
+                return Ok(());
+            }
+            Ok(result) => {
+                job.result = Some(result);
+                job.set_status(JobStatus::Finished);
+            }
+            Err(e) => {
+                log::debug!("Job {} failed: {}", job.id, e);
+                job.error = Some(e.to_string());
+                job.set_status(JobStatus::Failed);
+            }
+        }
+
+        let status = job.status;
+        self.jobs.update(job.clone()).await?;
+
+        // A concurrent cancellation wins, and then sends the webhook itself
+        let stored = self.jobs.find_by_id(&job.id).await?;
+        if stored.status == status {
+            notify_job(&self.webhooks, &stored);
+        }
+
+        Ok(())
+    }
+
+    async fn execute(
+        &self,
+        job: &mut Job,
+        request: ExecutionRequest,
+        registration: &Registration,
+    ) -> DomainResult<ExecutionResult> {
+        let cancel = registration.token();
+
+        job.set_status(JobStatus::Compiling);
+        self.jobs.update(job.clone()).await?;
+        let prepared = cancellable(cancel, self.execute_code.compile(request)).await?;
+
+        log::debug!("Acquiring box ID from pool for job {}", job.id);
+        let box_id = cancellable(cancel, self.sandbox.acquire_box_id(prepared.scheduling())).await?;
+
+        job.set_status(JobStatus::Running);
+        if let Err(e) = self.jobs.update(job.clone()).await {
+            self.sandbox.release_box_id(box_id).await?;
+            return Err(e);
+        }
+
+        // Like `/execute`, the box of a completed run is kept for file inspection
+        self.execute_code.run(prepared, box_id, cancel).await
+    }
+}
+
+/// Send the final state of a job to its callback URL, if it has one
+pub(crate) fn notify_job(webhooks: &WebhookNotifier, job: &Job) {
+    let Some(delivery_id) = job.webhook_delivery_id.clone() else {
+        return;
+    };
+
+    match serde_json::to_value(job) {
+        Ok(payload) => webhooks.deliver(delivery_id, payload),
+        Err(e) => log::warn!("Failed to serialize job {} for webhook: {}", job.id, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{
+        OutputEncoding, ProgramSource, ResourceLimitOverrides, SchedulingContext, DEFAULT_TENANT,
+    };
+    use crate::external::repositories::{InMemoryJobRepository, InMemoryWebhookDeliveryRepository};
+    use crate::use_cases::testing::{FakeCompiler, FakeLanguages, FakeSandbox};
+    use crate::use_cases::CancelJobUseCase;
+    use std::collections::BTreeMap;
+
+    struct Fixture {
+        sandbox: Arc<FakeSandbox>,
+        jobs: Arc<InMemoryJobRepository>,
+        submit: SubmitJobUseCase,
+        cancel: CancelJobUseCase,
+    }
+
+    fn fixture(sandbox: FakeSandbox) -> Fixture {
+        let sandbox = Arc::new(sandbox);
+        let jobs = Arc::new(InMemoryJobRepository::new());
+        let cancellations = Arc::new(CancellationRegistry::new());
+        let webhooks = Arc::new(WebhookNotifier::new(
+            None,
+            Arc::new(InMemoryWebhookDeliveryRepository::new()),
+            1,
+            Duration::ZERO,
+            Duration::from_secs(60),
+        ));
+        let execute_code = Arc::new(ExecuteCodeUseCase::new(
+            Arc::new(FakeLanguages),
+            Arc::new(FakeCompiler),
+            sandbox.clone(),
+        ));
+
+        Fixture {
+            submit: SubmitJobUseCase::new(
+                execute_code,
+                sandbox.clone(),
+                jobs.clone(),
+                cancellations.clone(),
+                webhooks.clone(),
+                Duration::from_secs(60),
+            ),
+            cancel: CancelJobUseCase::new(jobs.clone(), cancellations, webhooks),
+            sandbox,
+            jobs,
+        }
+    }
+
+    fn request() -> ExecutionRequest {
+        ExecutionRequest {
+            program: ProgramSource {
+                language: "python".to_string(),
+                code: "print('done')".to_string(),
+                files: Vec::new(),
+                entry_point: None,
+            },
+            stdin: None,
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            limits: ResourceLimitOverrides::default(),
+            scheduling: SchedulingContext::default(),
+            output_encoding: OutputEncoding::Auto,
+        }
+    }
+
+    async fn wait_for_status(jobs: &InMemoryJobRepository, id: &str, status: JobStatus) -> Job {
+        for _ in 0..100 {
+            let job = jobs.find_by_id(id).await.unwrap();
+            if job.status == status {
+                return job;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("Job {} never reached {:?}", id, status);
+    }
+
+    #[tokio::test]
+    async fn job_runs_from_queued_to_finished() {
+        let mut sandbox = FakeSandbox::new(1);
+        sandbox.hang = true;
+        sandbox.stdout = b"done".to_vec();
+        let fixture = fixture(sandbox);
+
+        let job = fixture.submit.execute(request(), None).await.unwrap();
+        assert_eq!(job.status, JobStatus::Queued);
+
+        fixture.sandbox.started.notified().await;
+        let stored = fixture.jobs.find_by_id(&job.id).await.unwrap();
+        assert_eq!(stored.status, JobStatus::Running);
+
+        fixture.sandbox.resume.notify_one();
+        let job = wait_for_status(&fixture.jobs, &job.id, JobStatus::Finished).await;
+        assert_eq!(job.result.unwrap().stdout, "done");
+        assert_eq!(job.error, None);
+    }
+
+    #[tokio::test]
+    async fn cancelled_queued_job_never_runs() {
+        let fixture = fixture(FakeSandbox::new(1));
+
+        // The job task has not had a chance to run yet
+        let job = fixture.submit.execute(request(), None).await.unwrap();
+        let cancelled = fixture.cancel.execute(DEFAULT_TENANT, &job.id).await;
+        assert_eq!(cancelled.unwrap().status, JobStatus::Cancelled);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let job = fixture.jobs.find_by_id(&job.id).await.unwrap();
+        assert_eq!(job.status, JobStatus::Cancelled);
+        assert!(job.result.is_none());
+
+        // The box was never taken
+        let scheduling = SchedulingContext::default();
+        let box_id = fixture.sandbox.acquire_box_id(&scheduling).await;
+        assert_eq!(box_id.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn completed_job_cannot_be_cancelled() {
+        let fixture = fixture(FakeSandbox::new(1));
+
+        let job = fixture.submit.execute(request(), None).await.unwrap();
+        wait_for_status(&fixture.jobs, &job.id, JobStatus::Finished).await;
+
+        let result = fixture.cancel.execute(DEFAULT_TENANT, &job.id).await;
+        assert!(matches!(result, Err(DomainError::InvalidRequest(_))));
+        let job = fixture.jobs.find_by_id(&job.id).await.unwrap();
+        assert_eq!(job.status, JobStatus::Finished);
+    }
+}
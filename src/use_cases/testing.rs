@@ -0,0 +1,279 @@
+//! In-memory stand-ins for the services use cases depend on
+
+use crate::domain::entities::{
+    ArchiveFormat, BoxEntry, BoxEntryKind, ExecutionMetadata, Language, OutputChunk, OutputSize,
+    ResourceLimitOverrides, ResourceLimits, SchedulingContext,
+};
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::repositories::LanguageRepository;
+use crate::domain::services::{
+    CompileOutput, CompilerService, FileReader, InteractiveExecutionResult, SandboxExecutionConfig,
+    SandboxExecutionResult, SandboxService,
+};
+use crate::external::services::box_pool::BoxPool;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+
+/// Languages all sharing a dummy configuration directory
+pub struct FakeLanguages;
+
+#[async_trait]
+impl LanguageRepository for FakeLanguages {
+    async fn find_by_name(&self, name: &str) -> DomainResult<Language> {
+        Ok(Language::new(
+            name.to_string(),
+            "txt".to_string(),
+            PathBuf::from("/nonexistent"),
+        ))
+    }
+
+    async fn list_all(&self) -> DomainResult<Vec<Language>> {
+        Ok(Vec::new())
+    }
+
+    async fn setup_all(&self) -> DomainResult<()> {
+        Ok(())
+    }
+}
+
+/// Compiler turning any program into a dummy binary
+pub struct FakeCompiler;
+
+#[async_trait]
+impl CompilerService for FakeCompiler {
+    async fn compile(
+        &self,
+        _language: &Language,
+        _source_path: &Path,
+        output_dir: &Path,
+    ) -> DomainResult<CompileOutput> {
+        let binary_path = output_dir.join("bin");
+        tokio::fs::write(&binary_path, b"binary").await?;
+        Ok(CompileOutput {
+            binary_path: Some(binary_path),
+            exit_code: 0,
+            stderr: String::new(),
+        })
+    }
+}
+
+/// Sandbox handing out boxes from a real pool and recording what is done in them
+pub struct FakeSandbox {
+    pub pool: BoxPool,
+    /// Default and maximum limits of every run
+    pub limits: ResourceLimits,
+    /// Runs only end when resumed or cancelled
+    pub hang: bool,
+    /// Notified whenever a run starts
+    pub started: Notify,
+    /// Lets a hanging run finish
+    pub resume: Notify,
+    /// Output of every run
+    pub stdout: Vec<u8>,
+    /// Entries listed for every box
+    pub entries: Vec<BoxEntry>,
+    pub cleaned: Mutex<Vec<u32>>,
+    pub written: Mutex<Vec<(u32, PathBuf, Vec<u8>)>>,
+    pub removed: Mutex<Vec<(u32, PathBuf, bool)>>,
+    pub archived: Mutex<Vec<Vec<String>>>,
+}
+
+impl FakeSandbox {
+    pub fn new(box_count: u32) -> Self {
+        Self {
+            pool: BoxPool::new(0..box_count, 8, Duration::from_secs(1), HashMap::new()),
+            limits: ResourceLimits {
+                cg_mem: 0,
+                mem: 0,
+                time: 0,
+                wall_time: 0,
+                extra_time: 0,
+                stack: 0,
+                fsize: 0,
+                open_files: 0,
+                processes: 0,
+            },
+            hang: false,
+            started: Notify::new(),
+            resume: Notify::new(),
+            stdout: Vec::new(),
+            entries: Vec::new(),
+            cleaned: Mutex::new(Vec::new()),
+            written: Mutex::new(Vec::new()),
+            removed: Mutex::new(Vec::new()),
+            archived: Mutex::new(Vec::new()),
+        }
+    }
+
+    async fn run(&self, config: &SandboxExecutionConfig) -> DomainResult<SandboxExecutionResult> {
+        self.started.notify_one();
+        if self.hang {
+            tokio::select! {
+                _ = self.resume.notified() => {}
+                _ = config.cancel.cancelled() => return Err(DomainError::Cancelled),
+            }
+        }
+        Ok(SandboxExecutionResult {
+            stdout: self.stdout.clone(),
+            stderr: Vec::new(),
+            stdout_size: OutputSize {
+                total: self.stdout.len() as u64,
+                truncated: false,
+            },
+            stderr_size: OutputSize::default(),
+            metadata: ExecutionMetadata::new(),
+            box_id: config.box_id,
+        })
+    }
+}
+
+/// Box entry of the given kind and size
+pub fn entry(path: &str, kind: BoxEntryKind, size: u64) -> BoxEntry {
+    BoxEntry {
+        path: path.to_string(),
+        kind,
+        size,
+        modified_at: chrono::DateTime::UNIX_EPOCH,
+        sha256: None,
+    }
+}
+
+fn unsupported<T>() -> DomainResult<T> {
+    Err(DomainError::Internal(
+        "Not supported by the fake sandbox".to_string(),
+    ))
+}
+
+#[async_trait]
+impl SandboxService for FakeSandbox {
+    fn resolve_limits(&self, overrides: &ResourceLimitOverrides) -> DomainResult<ResourceLimits> {
+        self.limits.resolve(&self.limits, overrides)
+    }
+
+    async fn execute(
+        &self,
+        config: SandboxExecutionConfig,
+    ) -> DomainResult<SandboxExecutionResult> {
+        self.run(&config).await
+    }
+
+    async fn execute_streaming(
+        &self,
+        config: SandboxExecutionConfig,
+        _output: mpsc::Sender<OutputChunk>,
+    ) -> DomainResult<SandboxExecutionResult> {
+        self.run(&config).await
+    }
+
+    async fn execute_interactive(
+        &self,
+        program: SandboxExecutionConfig,
+        interactor: SandboxExecutionConfig,
+    ) -> DomainResult<InteractiveExecutionResult> {
+        Ok(InteractiveExecutionResult {
+            program: self.run(&program).await?,
+            interactor: self.run(&interactor).await?,
+        })
+    }
+
+    async fn acquire_box_id(&self, scheduling: &SchedulingContext) -> DomainResult<u32> {
+        self.pool.acquire(scheduling).await
+    }
+
+    async fn acquire_box_ids(
+        &self,
+        scheduling: &SchedulingContext,
+        count: usize,
+    ) -> DomainResult<Vec<u32>> {
+        self.pool.acquire_many(scheduling, count).await
+    }
+
+    async fn release_box_id(&self, box_id: u32) -> DomainResult<()> {
+        self.pool.release(box_id).await
+    }
+
+    fn box_holder(&self, box_id: u32) -> Option<String> {
+        self.pool.holder(box_id)
+    }
+
+    async fn init(&self, _box_id: u32) -> DomainResult<()> {
+        Ok(())
+    }
+
+    async fn list_entries(&self, _box_id: u32, _recursive: bool) -> DomainResult<Vec<BoxEntry>> {
+        Ok(self.entries.clone())
+    }
+
+    async fn hash_files(
+        &self,
+        _box_id: u32,
+        _paths: &[String],
+    ) -> DomainResult<HashMap<String, String>> {
+        Ok(HashMap::new())
+    }
+
+    async fn archive(
+        &self,
+        _box_id: u32,
+        paths: &[String],
+        _format: ArchiveFormat,
+        output: &Path,
+    ) -> DomainResult<()> {
+        self.archived.lock().unwrap().push(paths.to_vec());
+        tokio::fs::write(output, paths.join("\n")).await?;
+        Ok(())
+    }
+
+    async fn get_file_base64(&self, _box_id: u32, _filename: &str) -> DomainResult<String> {
+        unsupported()
+    }
+
+    async fn stat_path(&self, _box_id: u32, path: &Path) -> DomainResult<BoxEntry> {
+        let path = path.to_string_lossy();
+        self.entries
+            .iter()
+            .find(|entry| entry.path == path)
+            .cloned()
+            .ok_or_else(|| DomainError::FileNotFound(path.to_string()))
+    }
+
+    async fn read_file(
+        &self,
+        _box_id: u32,
+        _path: &Path,
+        _offset: u64,
+        _length: u64,
+    ) -> DomainResult<FileReader> {
+        unsupported()
+    }
+
+    async fn write_file(&self, box_id: u32, path: &Path, source: &Path) -> DomainResult<()> {
+        let content = tokio::fs::read(source).await?;
+        self.written
+            .lock()
+            .unwrap()
+            .push((box_id, path.to_path_buf(), content));
+        Ok(())
+    }
+
+    async fn delete_file(&self, _box_id: u32, _filename: &str) -> DomainResult<()> {
+        Ok(())
+    }
+
+    async fn remove_path(&self, box_id: u32, path: &Path, recursive: bool) -> DomainResult<()> {
+        self.removed
+            .lock()
+            .unwrap()
+            .push((box_id, path.to_path_buf(), recursive));
+        Ok(())
+    }
+
+    async fn cleanup(&self, box_id: u32) -> DomainResult<()> {
+        self.cleaned.lock().unwrap().push(box_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+use crate::domain::entities::ResourceLimitOverrides;
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::paths::normalize_relative_path;
+use crate::domain::services::{is_reserved_box_path, SandboxService};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio_stream::{Stream, StreamExt};
+
+pub struct UploadBoxFileUseCase {
+    sandbox: Arc<dyn SandboxService>,
+    /// Bytes accepted per file (0 = unlimited)
+    max_upload_size: u64,
+}
+
+impl UploadBoxFileUseCase {
+    pub fn new(sandbox: Arc<dyn SandboxService>, max_upload_size: u64) -> Self {
+        Self {
+            sandbox,
+            max_upload_size,
+        }
+    }
+
+    /// Largest file accepted: the upload limit, further capped by the file size limit
+    /// (`--fsize`) runs in the box get by default
+    fn max_size(&self) -> DomainResult<Option<u64>> {
+        let fsize = self
+            .sandbox
+            .resolve_limits(&ResourceLimitOverrides::default())?
+            .fsize as u64
+            * 1024;
+
+        Ok([self.max_upload_size, fsize]
+            .into_iter()
+            .filter(|&limit| limit > 0)
+            .min())
+    }
+
+    /// Write `content` to `path` inside an initialized box of `tenant`, replacing any
+    /// existing file.
+    ///
+    /// Returns the normalized path and the number of bytes written.
+    pub async fn execute<S, B>(
+        &self,
+        box_id: u32,
+        tenant: &str,
+        path: &str,
+        content: S,
+    ) -> DomainResult<(PathBuf, u64)>
+    where
+        S: Stream<Item = DomainResult<B>>,
+        B: AsRef<[u8]>,
+    {
+        self.sandbox.check_box_owner(box_id, tenant)?;
+        let box_path = normalize_relative_path(path)?;
+        if is_reserved_box_path(&box_path) {
+            return Err(DomainError::InvalidRequest(format!(
+                "Invalid path '{}': name is reserved",
+                path
+            )));
+        }
+        let max_size = self.max_size()?;
+
+        // Stage the upload on the host first, so an oversized or interrupted upload never
+        // leaves a partial file in the box
+        let temp_dir = TempDir::new()?;
+        let staged_path = temp_dir.path().join("upload");
+        let mut staged = File::create(&staged_path).await?;
+        let mut size: u64 = 0;
+
+        tokio::pin!(content);
+        while let Some(chunk) = content.next().await {
+            let chunk = chunk?;
+            let chunk = chunk.as_ref();
+            size += chunk.len() as u64;
+            if let Some(max_size) = max_size.filter(|&max_size| size > max_size) {
+                return Err(DomainError::PayloadTooLarge(format!(
+                    "File exceeds the upload limit of {} bytes",
+                    max_size
+                )));
+            }
+            staged.write_all(chunk).await?;
+        }
+        staged.flush().await?;
+        drop(staged);
+
+        log::debug!("Uploading {:?} ({} bytes) to box ID: {}", box_path, size, box_id);
+        self.sandbox
+            .write_file(box_id, &box_path, &staged_path)
+            .await?;
+
+        Ok((box_path, size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::entities::{SchedulingContext, DEFAULT_TENANT};
+    use crate::use_cases::testing::FakeSandbox;
+
+    /// Upload use case for a sandbox whose first box is held by the default tenant
+    async fn upload(sandbox: FakeSandbox, max_upload_size: u64) -> UploadBoxFileUseCase {
+        let sandbox = Arc::new(sandbox);
+        let scheduling = SchedulingContext::default();
+        assert_eq!(sandbox.acquire_box_id(&scheduling).await.unwrap(), 0);
+        UploadBoxFileUseCase::new(sandbox, max_upload_size)
+    }
+
+    /// Content arriving in chunks of at most 4 bytes
+    fn content(size: usize) -> impl Stream<Item = DomainResult<Vec<u8>>> {
+        let chunks: Vec<DomainResult<Vec<u8>>> = vec![b'x'; size]
+            .chunks(4)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+        tokio_stream::iter(chunks)
+    }
+
+    #[tokio::test]
+    async fn upload_is_capped_by_upload_and_file_size_limits() {
+        // (upload limit, fsize in KB, largest accepted file)
+        let cases = [(10, 0, 10), (0, 1, 1024), (10, 1, 10), (4096, 1, 1024)];
+
+        for (max_upload_size, fsize, max_size) in cases {
+            let mut sandbox = FakeSandbox::new(1);
+            sandbox.limits.fsize = fsize;
+            let upload = upload(sandbox, max_upload_size).await;
+
+            let written = upload
+                .execute(0, DEFAULT_TENANT, "data.txt", content(max_size))
+                .await;
+            assert_eq!(written.unwrap().1, max_size as u64);
+
+            let result = upload
+                .execute(0, DEFAULT_TENANT, "data.txt", content(max_size + 1))
+                .await;
+            assert!(
+                matches!(result, Err(DomainError::PayloadTooLarge(_))),
+                "upload limit {}, fsize {}",
+                max_upload_size,
+                fsize
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn oversized_upload_leaves_the_box_untouched() {
+        let sandbox = Arc::new(FakeSandbox::new(1));
+        let scheduling = SchedulingContext::default();
+        sandbox.acquire_box_id(&scheduling).await.unwrap();
+        let upload = UploadBoxFileUseCase::new(sandbox.clone(), 8);
+
+        let result = upload.execute(0, DEFAULT_TENANT, "big", content(9)).await;
+        assert!(matches!(result, Err(DomainError::PayloadTooLarge(_))));
+        assert!(sandbox.written.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reserved_and_escaping_paths_are_rejected() {
+        let upload = upload(FakeSandbox::new(1), 0).await;
+        let paths = ["bin", "runner/data", ".stdin", "../outside", "/etc/passwd"];
+
+        for path in paths {
+            let result = upload.execute(0, DEFAULT_TENANT, path, content(1)).await;
+            assert!(
+                matches!(result, Err(DomainError::InvalidRequest(_))),
+                "{:?}",
+                path
+            );
+        }
+
+        let (path, _) = upload
+            .execute(0, DEFAULT_TENANT, "src/./main.c", content(1))
+            .await
+            .unwrap();
+        assert_eq!(path, PathBuf::from("src/main.c"));
+    }
+
+    #[tokio::test]
+    async fn boxes_of_other_tenants_are_not_found() {
+        let upload = upload(FakeSandbox::new(2), 0).await;
+
+        // Held by another tenant, and held by no one
+        for (box_id, tenant) in [(0, "other"), (1, DEFAULT_TENANT)] {
+            let result = upload.execute(box_id, tenant, "data.txt", content(1)).await;
+            assert!(
+                matches!(result, Err(DomainError::BoxNotFound(id)) if id == box_id),
+                "box {} of {}",
+                box_id,
+                tenant
+            );
+        }
+    }
+}
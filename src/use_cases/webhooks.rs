@@ -0,0 +1,169 @@
+use crate::domain::entities::{DeliveryAttempt, DeliveryStatus, WebhookDelivery};
+use crate::domain::error::{DomainError, DomainResult};
+use crate::domain::repositories::WebhookDeliveryRepository;
+use crate::domain::services::WebhookSender;
+use chrono::{TimeDelta, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Sends completion webhooks, shared by the use cases that accept a callback URL
+pub struct WebhookNotifier {
+    /// Unset when no signing secret is configured, which disables webhooks
+    sender: Option<Arc<dyn WebhookSender>>,
+    deliveries: Arc<dyn WebhookDeliveryRepository>,
+    max_attempts: u32,
+    retry_delay: Duration,
+    retention: Duration,
+}
+
+impl WebhookNotifier {
+    pub fn new(
+        sender: Option<Arc<dyn WebhookSender>>,
+        deliveries: Arc<dyn WebhookDeliveryRepository>,
+        max_attempts: u32,
+        retry_delay: Duration,
+        retention: Duration,
+    ) -> Self {
+        Self {
+            sender,
+            deliveries,
+            max_attempts,
+            retry_delay,
+            retention,
+        }
+    }
+
+    /// Record a pending delivery for an execution of `tenant` that has not completed yet.
+    ///
+    /// Called before the execution starts so an unusable callback URL is rejected up front.
+    pub async fn register(
+        &self,
+        callback_url: &str,
+        tenant: &str,
+        job_id: Option<String>,
+    ) -> DomainResult<WebhookDelivery> {
+        let Some(sender) = &self.sender else {
+            return Err(DomainError::InvalidRequest(
+                "callback_url is not supported because webhooks are not configured".to_string(),
+            ));
+        };
+
+        let valid_scheme =
+            callback_url.starts_with("http://") || callback_url.starts_with("https://");
+        if !valid_scheme || callback_url.chars().any(char::is_whitespace) {
+            return Err(DomainError::InvalidRequest(
+                "callback_url must be an http or https URL".to_string(),
+            ));
+        }
+        sender.check_url(callback_url).await?;
+
+        // Forget deliveries that have been kept long enough
+        let retention = TimeDelta::from_std(self.retention).unwrap_or(TimeDelta::MAX);
+        let removed = self
+            .deliveries
+            .delete_completed_before(Utc::now() - retention)
+            .await?;
+        if removed > 0 {
+            log::debug!("Removed {} expired webhook delivery record(s)", removed);
+        }
+
+        let delivery = WebhookDelivery::new(
+            Uuid::new_v4().to_string(),
+            tenant.to_string(),
+            callback_url.to_string(),
+            job_id,
+        );
+        self.deliveries.insert(delivery.clone()).await?;
+        log::debug!(
+            "Registered webhook delivery {} to {}",
+            delivery.id,
+            delivery.callback_url
+        );
+
+        Ok(delivery)
+    }
+
+    /// Send the payload of a completed execution in the background.
+    ///
+    /// Failed attempts are retried with exponential backoff until `max_attempts` is reached.
+    pub fn deliver(&self, delivery_id: String, payload: serde_json::Value) {
+        let Some(sender) = self.sender.clone() else {
+            return;
+        };
+        let deliveries = self.deliveries.clone();
+        let max_attempts = self.max_attempts.max(1);
+        let retry_delay = self.retry_delay;
+
+        tokio::spawn(async move {
+            let result = send_with_retries(
+                sender.as_ref(),
+                deliveries.as_ref(),
+                &delivery_id,
+                &payload,
+                max_attempts,
+                retry_delay,
+            )
+            .await;
+
+            if let Err(e) = result {
+                log::warn!("Failed to record webhook delivery {}: {}", delivery_id, e);
+            }
+        });
+    }
+}
+
+async fn send_with_retries(
+    sender: &dyn WebhookSender,
+    deliveries: &dyn WebhookDeliveryRepository,
+    delivery_id: &str,
+    payload: &serde_json::Value,
+    max_attempts: u32,
+    retry_delay: Duration,
+) -> DomainResult<()> {
+    let mut delivery = deliveries.find_by_id(delivery_id).await?;
+    let body = serde_json::to_vec(payload)
+        .map_err(|e| DomainError::Internal(format!("Failed to serialize webhook: {}", e)))?;
+
+    for attempt in 0..max_attempts {
+        if attempt > 0 {
+            let backoff = retry_delay.saturating_mul(1 << (attempt - 1).min(16));
+            log::debug!("Retrying webhook delivery {} in {:?}", delivery_id, backoff);
+            tokio::time::sleep(backoff).await;
+        }
+
+        let (status_code, error) = match sender
+            .send(&delivery.callback_url, delivery_id, &body)
+            .await
+        {
+            Ok(code) if (200..300).contains(&code) => (Some(code), None),
+            Ok(code) => (
+                Some(code),
+                Some(format!("Receiver responded with status {}", code)),
+            ),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        let delivered = error.is_none();
+        delivery.add_attempt(DeliveryAttempt {
+            attempted_at: Utc::now(),
+            status_code,
+            error,
+        });
+
+        if delivered {
+            log::debug!("Webhook delivery {} succeeded", delivery_id);
+            delivery.set_status(DeliveryStatus::Delivered);
+            return deliveries.update(delivery).await;
+        }
+        deliveries.update(delivery.clone()).await?;
+    }
+
+    log::warn!(
+        "Webhook delivery {} failed after {} attempt(s)",
+        delivery_id,
+        max_attempts
+    );
+    delivery.set_status(DeliveryStatus::Failed);
+    deliveries.update(delivery).await
+}